@@ -0,0 +1,905 @@
+use std::sync::Arc;
+
+use paste::paste;
+
+use crate::{
+    backend::{
+        buffers::{Buffer, BufferReadError, RESULT_USAGE},
+        device::{Context, ContextError},
+        kernels::{reduced_shape, Abs, ArgReduceOp, BinaryOp, KernelError, ReduceOp, ScanOp},
+        traits::BufferType,
+    },
+    indexing::{broadcast_shapes, IndexError, Layout, SliceArg},
+    initialization::{current_context, try_current_context},
+};
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum ArrayError {
+    #[error("Could not get the current context: {0}")]
+    Context(#[from] ContextError),
+
+    #[error("Shape {shape:?} requires {expected} elements, but {found} were provided.")]
+    ShapeMismatch {
+        shape: Vec<usize>,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("Invalid index: {0}")]
+    Index(#[from] IndexError),
+
+    #[error("Failed to run kernel: {0}")]
+    Kernel(#[from] KernelError),
+
+    #[error("Failed to read results: {0}")]
+    Read(#[from] BufferReadError),
+}
+
+/// An n-dimensional, row-major array living on the device of a [`Context`].
+pub struct Array<T: BufferType> {
+    context: Arc<Context>,
+    buffer: Buffer<T>,
+    layout: Layout,
+}
+
+impl<T: BufferType> Array<T> {
+    /// Creates an array on the [`current_context`].
+    pub fn from_vec_default(vec: Vec<T>, shape: &[usize]) -> Self {
+        Self::from_vec(&current_context(), vec, shape)
+    }
+
+    pub fn try_from_vec_default(vec: Vec<T>, shape: &[usize]) -> Result<Self, ArrayError> {
+        Self::try_from_vec(&try_current_context()?, vec, shape)
+    }
+
+    pub fn zeros_default(shape: &[usize]) -> Self {
+        Self::zeros(&current_context(), shape)
+    }
+
+    pub fn ones_default(shape: &[usize]) -> Self {
+        Self::ones(&current_context(), shape)
+    }
+
+    pub fn full_default(shape: &[usize], value: T) -> Self {
+        Self::full(&current_context(), shape, value)
+    }
+
+    pub fn from_vec(context: &Arc<Context>, vec: Vec<T>, shape: &[usize]) -> Self {
+        let from_vec_result = Self::try_from_vec(context, vec, shape);
+
+        if let Err(e) = &from_vec_result {
+            log::error!("Failed at Array::from_vec: {}", e);
+        }
+
+        from_vec_result.unwrap()
+    }
+
+    pub fn try_from_vec(
+        context: &Arc<Context>,
+        vec: Vec<T>,
+        shape: &[usize],
+    ) -> Result<Self, ArrayError> {
+        let expected = shape.iter().product();
+        if vec.len() != expected {
+            return Err(ArrayError::ShapeMismatch {
+                shape: shape.to_vec(),
+                expected,
+                found: vec.len(),
+            });
+        }
+
+        Ok(Array {
+            context: context.clone(),
+            buffer: Buffer::from_vec(context, RESULT_USAGE, vec),
+            layout: Layout::contiguous(shape),
+        })
+    }
+
+    pub fn zeros(context: &Arc<Context>, shape: &[usize]) -> Self {
+        Self::full(context, shape, T::zeroed())
+    }
+
+    pub fn ones(context: &Arc<Context>, shape: &[usize]) -> Self {
+        Self::full(context, shape, T::ONE)
+    }
+
+    pub fn full(context: &Arc<Context>, shape: &[usize], value: T) -> Self {
+        let len = shape.iter().product();
+        Self::from_vec(context, vec![value; len], shape)
+    }
+
+    /// Reads the contents of the array back to the host in row-major order.
+    pub fn to_vec(&self) -> Vec<T> {
+        self.buffer.read_to_vec(&self.context)
+    }
+
+    pub fn context(&self) -> &Arc<Context> {
+        &self.context
+    }
+
+    pub(crate) fn buffer(&self) -> &Buffer<T> {
+        &self.buffer
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    pub fn shape(&self) -> &[usize] {
+        self.layout.shape()
+    }
+
+    /// The strides of each axis, measured in elements.
+    pub fn strides(&self) -> &[isize] {
+        self.layout.strides()
+    }
+
+    pub fn ndim(&self) -> usize {
+        self.layout.ndim()
+    }
+
+    pub fn len(&self) -> usize {
+        self.layout.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layout.is_empty()
+    }
+
+    pub fn view(&self) -> ArrayView<'_, T> {
+        ArrayView {
+            array: self,
+            layout: self.layout.clone(),
+        }
+    }
+
+    pub fn slice(&self, info: impl SliceArg) -> ArrayView<'_, T> {
+        self.view().slice(info)
+    }
+
+    pub fn try_slice(&self, info: impl SliceArg) -> Result<ArrayView<'_, T>, IndexError> {
+        self.view().try_slice(info)
+    }
+
+    pub fn t(&self) -> ArrayView<'_, T> {
+        self.view().t()
+    }
+
+    pub fn permute_axes(&self, axes: &[usize]) -> Result<ArrayView<'_, T>, IndexError> {
+        self.view().permute_axes(axes)
+    }
+}
+
+/// A strided view into the buffer of an [`Array`]. Creating views never
+/// copies data on the device.
+#[derive(Clone)]
+pub struct ArrayView<'a, T: BufferType> {
+    array: &'a Array<T>,
+    layout: Layout,
+}
+
+impl<'a, T: BufferType> ArrayView<'a, T> {
+    pub fn slice(&self, info: impl SliceArg) -> ArrayView<'a, T> {
+        let slice_result = self.try_slice(info);
+
+        if let Err(e) = &slice_result {
+            log::error!("Failed at ArrayView::slice: {}", e);
+        }
+
+        slice_result.unwrap()
+    }
+
+    pub fn try_slice(&self, info: impl SliceArg) -> Result<ArrayView<'a, T>, IndexError> {
+        Ok(ArrayView {
+            array: self.array,
+            layout: self.layout.slice(info)?,
+        })
+    }
+
+    pub fn t(&self) -> ArrayView<'a, T> {
+        ArrayView {
+            array: self.array,
+            layout: self.layout.t(),
+        }
+    }
+
+    pub fn permute_axes(&self, axes: &[usize]) -> Result<ArrayView<'a, T>, IndexError> {
+        Ok(ArrayView {
+            array: self.array,
+            layout: self.layout.permute_axes(axes)?,
+        })
+    }
+
+    /// Reads the viewed elements back to the host in row-major order. Only
+    /// the viewed range is transferred, and strided views are gathered on the
+    /// device first.
+    pub fn to_vec(&self) -> Vec<T> {
+        let to_vec_result = self.try_to_vec();
+
+        if let Err(e) = &to_vec_result {
+            log::error!("Failed at ArrayView::to_vec: {}", e);
+        }
+
+        to_vec_result.unwrap()
+    }
+
+    pub fn try_to_vec(&self) -> Result<Vec<T>, ArrayError> {
+        let context = self.array.context();
+        if self.layout.is_contiguous() {
+            let start = self.layout.offset();
+            let range = start..start + self.layout.len();
+            return Ok(self.array.buffer.try_read_range_to_vec(context, range)?);
+        }
+
+        let mut buffer = Buffer::with_len(context, RESULT_USAGE, self.layout.len());
+        let mut batch = context.batch();
+        batch.gather((&self.array.buffer, &self.layout), &mut buffer)?;
+        batch.submit();
+
+        Ok(buffer.try_read_to_vec(context)?)
+    }
+
+    pub fn array(&self) -> &'a Array<T> {
+        self.array
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    pub fn shape(&self) -> &[usize] {
+        self.layout.shape()
+    }
+
+    pub fn strides(&self) -> &[isize] {
+        self.layout.strides()
+    }
+
+    pub fn ndim(&self) -> usize {
+        self.layout.ndim()
+    }
+
+    pub fn len(&self) -> usize {
+        self.layout.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layout.is_empty()
+    }
+}
+
+/// Arrays and views that element-wise operations accept as operands.
+pub trait AsView<T: BufferType> {
+    fn as_view(&self) -> ArrayView<'_, T>;
+}
+
+impl<T: BufferType> AsView<T> for Array<T> {
+    fn as_view(&self) -> ArrayView<'_, T> {
+        self.view()
+    }
+}
+
+impl<T: BufferType> AsView<T> for ArrayView<'_, T> {
+    fn as_view(&self) -> ArrayView<'_, T> {
+        self.clone()
+    }
+}
+
+impl<T: BufferType> ArrayView<'_, T> {
+    /// Computes `op(self, rhs)` element-wise into a new array on the context
+    /// of `self`, broadcasting both operands NumPy-style. Shapes are checked
+    /// before anything is dispatched, and broadcasting never copies operands.
+    pub fn binary(&self, op: BinaryOp, rhs: &impl AsView<T>) -> Array<T> {
+        let binary_result = self.try_binary(op, rhs);
+
+        if let Err(e) = &binary_result {
+            log::error!("Failed at ArrayView::{}: {}", op.name(), e);
+        }
+
+        binary_result.unwrap()
+    }
+
+    pub fn try_binary(&self, op: BinaryOp, rhs: &impl AsView<T>) -> Result<Array<T>, ArrayError> {
+        let rhs = rhs.as_view();
+        let shape = broadcast_shapes(self.shape(), rhs.shape())?;
+
+        let context = self.array.context();
+        let mut buffer = Buffer::with_len(context, RESULT_USAGE, shape.iter().product());
+
+        let mut batch = context.batch();
+        batch.binary_broadcast(
+            op,
+            (self.array.buffer(), &self.layout),
+            (rhs.array.buffer(), &rhs.layout),
+            &mut buffer,
+        )?;
+        batch.submit();
+
+        Ok(Array {
+            context: context.clone(),
+            buffer,
+            layout: Layout::contiguous(&shape),
+        })
+    }
+}
+
+impl<T: BufferType> Array<T> {
+    /// Computes `op(self, rhs)` element-wise, see [`ArrayView::binary`].
+    pub fn binary(&self, op: BinaryOp, rhs: &impl AsView<T>) -> Array<T> {
+        self.view().binary(op, rhs)
+    }
+
+    pub fn try_binary(&self, op: BinaryOp, rhs: &impl AsView<T>) -> Result<Array<T>, ArrayError> {
+        self.view().try_binary(op, rhs)
+    }
+}
+
+impl<T: Abs> ArrayView<'_, T> {
+    /// Computes `abs` element-wise into a new array, of reals for complex
+    /// elements, see [`Abs`].
+    pub fn abs(&self) -> Array<T::Output> {
+        let abs_result = self.try_abs();
+
+        if let Err(e) = &abs_result {
+            log::error!("Failed at ArrayView::abs: {}", e);
+        }
+
+        abs_result.unwrap()
+    }
+
+    pub fn try_abs(&self) -> Result<Array<T::Output>, ArrayError> {
+        let context = self.array.context();
+        let mut buffer = Buffer::with_len(context, RESULT_USAGE, self.len());
+
+        let mut batch = context.batch();
+        // NOTE: Unary kernels read their operand whole, so views of part of
+        // an array, or strided ones, are gathered first.
+        let mut gathered;
+        let whole = self.layout.is_contiguous() && self.len() == self.array.len();
+        let operand = match whole {
+            true => self.array.buffer(),
+            false => {
+                gathered = Buffer::with_len(context, RESULT_USAGE, self.len());
+                batch.gather((self.array.buffer(), &self.layout), &mut gathered)?;
+                &gathered
+            }
+        };
+        batch.abs(operand, &mut buffer)?;
+        batch.submit();
+
+        Ok(Array {
+            context: context.clone(),
+            buffer,
+            layout: Layout::contiguous(self.shape()),
+        })
+    }
+}
+
+impl<T: Abs> Array<T> {
+    /// Computes `abs` element-wise, see [`ArrayView::abs`].
+    pub fn abs(&self) -> Array<T::Output> {
+        self.view().abs()
+    }
+
+    pub fn try_abs(&self) -> Result<Array<T::Output>, ArrayError> {
+        self.view().try_abs()
+    }
+}
+
+macro_rules! binary_methods {
+    ($($name:ident => $op:ident),+ $(,)?) => {
+        paste! {
+            impl<T: BufferType> Array<T> {
+                $(
+                    #[doc = concat!("Computes `", stringify!($name), "` element-wise with broadcasting.")]
+                    pub fn $name(&self, rhs: &impl AsView<T>) -> Array<T> {
+                        self.binary(BinaryOp::$op, rhs)
+                    }
+
+                    pub fn [<try_ $name>](&self, rhs: &impl AsView<T>) -> Result<Array<T>, ArrayError> {
+                        self.try_binary(BinaryOp::$op, rhs)
+                    }
+                )+
+            }
+
+            impl<T: BufferType> ArrayView<'_, T> {
+                $(
+                    #[doc = concat!("Computes `", stringify!($name), "` element-wise with broadcasting.")]
+                    pub fn $name(&self, rhs: &impl AsView<T>) -> Array<T> {
+                        self.binary(BinaryOp::$op, rhs)
+                    }
+
+                    pub fn [<try_ $name>](&self, rhs: &impl AsView<T>) -> Result<Array<T>, ArrayError> {
+                        self.try_binary(BinaryOp::$op, rhs)
+                    }
+                )+
+            }
+        }
+    };
+}
+
+binary_methods! {
+    add => Add,
+    sub => Sub,
+    mul => Mul,
+    div => Div,
+    pow => Pow,
+    min => Min,
+    max => Max,
+}
+
+impl<T: BufferType> ArrayView<'_, T> {
+    /// Reduces `axes`, or every axis when `None`, into a new array. With
+    /// `keepdims`, reduced axes remain with a length of one.
+    pub fn reduce(&self, op: ReduceOp, axes: Option<&[usize]>, keepdims: bool) -> Array<T> {
+        let reduce_result = self.try_reduce(op, axes, keepdims);
+
+        if let Err(e) = &reduce_result {
+            log::error!("Failed at ArrayView::{}: {}", op.name(), e);
+        }
+
+        reduce_result.unwrap()
+    }
+
+    pub fn try_reduce(
+        &self,
+        op: ReduceOp,
+        axes: Option<&[usize]>,
+        keepdims: bool,
+    ) -> Result<Array<T>, ArrayError> {
+        let (axes, shape) = self.reduced(axes, keepdims)?;
+
+        let context = self.array.context();
+        let mut buffer = Buffer::with_len(context, RESULT_USAGE, shape.iter().product());
+
+        let mut batch = context.batch();
+        batch.reduce(op, (self.array.buffer(), &self.layout), &axes, &mut buffer)?;
+        batch.submit();
+
+        Ok(Array {
+            context: context.clone(),
+            buffer,
+            layout: Layout::contiguous(&shape),
+        })
+    }
+
+    /// Finds the positions selected by `op` along `axis`, or in the flattened
+    /// array when `None`.
+    pub fn arg_reduce(&self, op: ArgReduceOp, axis: Option<usize>, keepdims: bool) -> Array<u32> {
+        let arg_reduce_result = self.try_arg_reduce(op, axis, keepdims);
+
+        if let Err(e) = &arg_reduce_result {
+            log::error!("Failed at ArrayView::{}: {}", op.name(), e);
+        }
+
+        arg_reduce_result.unwrap()
+    }
+
+    pub fn try_arg_reduce(
+        &self,
+        op: ArgReduceOp,
+        axis: Option<usize>,
+        keepdims: bool,
+    ) -> Result<Array<u32>, ArrayError> {
+        let (axes, shape) = self.reduced(axis.as_ref().map(std::slice::from_ref), keepdims)?;
+
+        let context = self.array.context();
+        let mut buffer = Buffer::with_len(context, RESULT_USAGE, shape.iter().product());
+
+        let mut batch = context.batch();
+        batch.arg_reduce(op, (self.array.buffer(), &self.layout), &axes, &mut buffer)?;
+        batch.submit();
+
+        Ok(Array {
+            context: context.clone(),
+            buffer,
+            layout: Layout::contiguous(&shape),
+        })
+    }
+
+    /// Validates the reduced axes, returning them along with the result shape.
+    fn reduced(
+        &self,
+        axes: Option<&[usize]>,
+        keepdims: bool,
+    ) -> Result<(Vec<usize>, Vec<usize>), ArrayError> {
+        let axes = match axes {
+            Some(axes) => axes.to_vec(),
+            None => (0..self.ndim()).collect(),
+        };
+        self.layout.split_axes(&axes)?;
+
+        let shape = reduced_shape(&self.layout, &axes, keepdims);
+        Ok((axes, shape))
+    }
+}
+
+impl<T: BufferType> Array<T> {
+    /// Reduces `axes` into a new array, see [`ArrayView::reduce`].
+    pub fn reduce(&self, op: ReduceOp, axes: Option<&[usize]>, keepdims: bool) -> Array<T> {
+        self.view().reduce(op, axes, keepdims)
+    }
+
+    pub fn try_reduce(
+        &self,
+        op: ReduceOp,
+        axes: Option<&[usize]>,
+        keepdims: bool,
+    ) -> Result<Array<T>, ArrayError> {
+        self.view().try_reduce(op, axes, keepdims)
+    }
+
+    /// Finds the positions selected by `op`, see [`ArrayView::arg_reduce`].
+    pub fn arg_reduce(&self, op: ArgReduceOp, axis: Option<usize>, keepdims: bool) -> Array<u32> {
+        self.view().arg_reduce(op, axis, keepdims)
+    }
+
+    pub fn try_arg_reduce(
+        &self,
+        op: ArgReduceOp,
+        axis: Option<usize>,
+        keepdims: bool,
+    ) -> Result<Array<u32>, ArrayError> {
+        self.view().try_arg_reduce(op, axis, keepdims)
+    }
+}
+
+impl<T: BufferType> ArrayView<'_, T> {
+    /// Computes the prefix scan along `axis` into a new array of the same
+    /// shape. An exclusive scan leaves each element out of its own result.
+    pub fn scan(&self, op: ScanOp, axis: usize, exclusive: bool) -> Array<T> {
+        let scan_result = self.try_scan(op, axis, exclusive);
+
+        if let Err(e) = &scan_result {
+            log::error!("Failed at ArrayView::{}: {}", op.name(), e);
+        }
+
+        scan_result.unwrap()
+    }
+
+    pub fn try_scan(
+        &self,
+        op: ScanOp,
+        axis: usize,
+        exclusive: bool,
+    ) -> Result<Array<T>, ArrayError> {
+        self.layout.split_axes(&[axis])?;
+
+        let context = self.array.context();
+        let mut buffer = Buffer::with_len(context, RESULT_USAGE, self.len());
+
+        let mut batch = context.batch();
+        batch.scan(
+            op,
+            (self.array.buffer(), &self.layout),
+            axis,
+            exclusive,
+            &mut buffer,
+        )?;
+        batch.submit();
+
+        Ok(Array {
+            context: context.clone(),
+            buffer,
+            layout: Layout::contiguous(self.shape()),
+        })
+    }
+
+    /// Computes the inclusive cumulative sum along `axis`.
+    pub fn cumsum(&self, axis: usize) -> Array<T> {
+        self.scan(ScanOp::Sum, axis, false)
+    }
+
+    pub fn try_cumsum(&self, axis: usize) -> Result<Array<T>, ArrayError> {
+        self.try_scan(ScanOp::Sum, axis, false)
+    }
+
+    /// Computes the inclusive cumulative product along `axis`.
+    pub fn cumprod(&self, axis: usize) -> Array<T> {
+        self.scan(ScanOp::Prod, axis, false)
+    }
+
+    pub fn try_cumprod(&self, axis: usize) -> Result<Array<T>, ArrayError> {
+        self.try_scan(ScanOp::Prod, axis, false)
+    }
+}
+
+impl<T: BufferType> Array<T> {
+    /// Computes the prefix scan along `axis`, see [`ArrayView::scan`].
+    pub fn scan(&self, op: ScanOp, axis: usize, exclusive: bool) -> Array<T> {
+        self.view().scan(op, axis, exclusive)
+    }
+
+    pub fn try_scan(
+        &self,
+        op: ScanOp,
+        axis: usize,
+        exclusive: bool,
+    ) -> Result<Array<T>, ArrayError> {
+        self.view().try_scan(op, axis, exclusive)
+    }
+
+    /// Computes the inclusive cumulative sum along `axis`.
+    pub fn cumsum(&self, axis: usize) -> Array<T> {
+        self.view().cumsum(axis)
+    }
+
+    pub fn try_cumsum(&self, axis: usize) -> Result<Array<T>, ArrayError> {
+        self.view().try_cumsum(axis)
+    }
+
+    /// Computes the inclusive cumulative product along `axis`.
+    pub fn cumprod(&self, axis: usize) -> Array<T> {
+        self.view().cumprod(axis)
+    }
+
+    pub fn try_cumprod(&self, axis: usize) -> Result<Array<T>, ArrayError> {
+        self.view().try_cumprod(axis)
+    }
+}
+
+macro_rules! reduce_methods {
+    ($($ty:ty),+ $(,)?) => {
+        $(
+            reduce_methods!(
+                @impl $ty,
+                [sum => Sum, mean => Mean, prod => Prod, amax => Max, amin => Min],
+                [argmax => ArgMax, argmin => ArgMin]
+            );
+        )+
+    };
+    (@impl $ty:ty, [$($name:ident => $op:ident),+], [$($arg_name:ident => $arg_op:ident),+]) => {
+        paste! {
+            impl<T: BufferType> $ty {
+                $(
+                    #[doc = concat!("Computes the `", stringify!($name), "` reduction of `axes`, or of every axis.")]
+                    pub fn $name(&self, axes: Option<&[usize]>, keepdims: bool) -> Array<T> {
+                        self.reduce(ReduceOp::$op, axes, keepdims)
+                    }
+
+                    pub fn [<try_ $name>](
+                        &self,
+                        axes: Option<&[usize]>,
+                        keepdims: bool,
+                    ) -> Result<Array<T>, ArrayError> {
+                        self.try_reduce(ReduceOp::$op, axes, keepdims)
+                    }
+                )+
+
+                $(
+                    #[doc = concat!("Computes `", stringify!($arg_name), "` along `axis`, or of the flattened array.")]
+                    pub fn $arg_name(&self, axis: Option<usize>, keepdims: bool) -> Array<u32> {
+                        self.arg_reduce(ArgReduceOp::$arg_op, axis, keepdims)
+                    }
+
+                    pub fn [<try_ $arg_name>](
+                        &self,
+                        axis: Option<usize>,
+                        keepdims: bool,
+                    ) -> Result<Array<u32>, ArrayError> {
+                        self.try_arg_reduce(ArgReduceOp::$arg_op, axis, keepdims)
+                    }
+                )+
+            }
+        }
+    };
+}
+
+reduce_methods!(Array<T>, ArrayView<'_, T>);
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use num_complex::Complex;
+
+    use crate::{
+        backend::{device::test_context, kernels::ScanOp},
+        indexing::{step_by, IndexError, NewAxis},
+        initialization::with_context,
+    };
+
+    use super::{Array, ArrayError};
+
+    #[test]
+    fn test_round_trip() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let vec = vec![1., 2., 3., 4., 5., 6.];
+        let x = Array::<f32>::from_vec(&context, vec.clone(), &[2, 3]);
+
+        assert_eq!(x.shape(), &[2, 3]);
+        assert_eq!(x.strides(), &[3, 1]);
+        assert_eq!(x.to_vec(), vec);
+    }
+
+    #[test]
+    fn test_constructors() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        assert_eq!(Array::<u32>::zeros(&context, &[2, 2]).to_vec(), vec![0; 4]);
+        assert_eq!(Array::<i32>::ones(&context, &[3]).to_vec(), vec![1; 3]);
+        assert_eq!(
+            Array::<f32>::full(&context, &[1, 2], 2.5).to_vec(),
+            vec![2.5; 2]
+        );
+        assert!(Array::<f32>::zeros(&context, &[0, 3]).to_vec().is_empty());
+    }
+
+    #[test]
+    fn test_shape_mismatch() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let err = Array::<f32>::try_from_vec(&context, vec![1., 2., 3.], &[2, 2]).err();
+        assert!(matches!(
+            err,
+            Some(ArrayError::ShapeMismatch {
+                shape,
+                expected: 4,
+                found: 3
+            }) if shape == [2, 2]
+        ));
+    }
+
+    #[test]
+    fn test_views() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Array::<u32>::from_vec(&context, (0..12).collect(), &[3, 4]);
+
+        assert_eq!(x.t().to_vec(), vec![0, 4, 8, 1, 5, 9, 2, 6, 10, 3, 7, 11]);
+        assert_eq!(x.slice((.., 1)).to_vec(), vec![1, 5, 9]);
+        assert_eq!(x.slice((1.., 1..3)).to_vec(), vec![5, 6, 9, 10]);
+        assert_eq!(x.slice((1,)).to_vec(), vec![4, 5, 6, 7]);
+        assert_eq!(x.slice((step_by(.., -2), 0)).t().to_vec(), vec![8, 0]);
+        assert!(x.try_slice((3,)).is_err());
+
+        // Packed and wide elements are gathered without regard to words.
+        let y = Array::<i8>::from_vec(&context, (0..15).collect(), &[3, 5]);
+        assert_eq!(y.slice((1, 1..4)).to_vec(), vec![6, 7, 8]);
+        assert_eq!(y.slice((.., -1)).to_vec(), vec![4, 9, 14]);
+        assert_eq!(
+            y.slice((step_by(.., -1), step_by(1.., 3))).to_vec(),
+            vec![11, 14, 6, 9, 1, 4]
+        );
+
+        let z = Array::<u64>::from_vec(&context, (0..6).map(|i| i << 40 | i).collect(), &[2, 3]);
+        assert_eq!(
+            z.t().to_vec(),
+            [0u64, 3, 1, 4, 2, 5].map(|i| i << 40 | i).to_vec()
+        );
+    }
+
+    #[test]
+    fn test_abs() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Array::<i8>::from_vec(&context, vec![-1, 2, 3, -4, 5, -6], &[2, 3]);
+        assert_eq!(x.abs().to_vec(), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(x.t().slice((1..,)).abs().to_vec(), vec![2, 5, 3, 6]);
+
+        // Complex elements have real absolute values.
+        let z = Array::from_vec(
+            &context,
+            vec![Complex::new(3f32, -4.), Complex::new(-5., 12.)],
+            &[2, 1],
+        );
+        let norms = z.abs();
+        assert_eq!(norms.shape(), &[2, 1]);
+        assert_eq!(norms.to_vec(), vec![5f32, 13.]);
+    }
+
+    #[test]
+    fn test_current_context() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        with_context(&context, || {
+            let x = Array::<f32>::from_vec_default(vec![1., 2.], &[2]);
+
+            assert!(Arc::ptr_eq(x.context(), &context));
+            assert_eq!(x.to_vec(), vec![1., 2.]);
+        });
+    }
+
+    #[test]
+    fn test_broadcast_ops() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Array::<f32>::from_vec(&context, vec![1., 2., 3., 4., 5., 6.], &[2, 3]);
+        let row = Array::<f32>::from_vec(&context, vec![10., 20., 30.], &[3]);
+        let column = Array::<f32>::from_vec(&context, vec![2., 4.], &[2, 1]);
+
+        let y = x.add(&row);
+        assert_eq!(y.shape(), &[2, 3]);
+        assert_eq!(y.to_vec(), vec![11., 22., 33., 14., 25., 36.]);
+
+        assert_eq!(x.div(&column).to_vec(), vec![0.5, 1., 1.5, 1., 1.25, 1.5]);
+        assert_eq!(
+            row.slice((.., NewAxis)).mul(&column.t()).to_vec(),
+            vec![20., 40., 40., 80., 60., 120.]
+        );
+        assert_eq!(x.t().max(&x.t()).to_vec(), vec![1., 4., 2., 5., 3., 6.]);
+
+        assert!(matches!(
+            x.try_sub(&column.t()).err(),
+            Some(ArrayError::Index(e)) if e == IndexError::BroadcastMismatch {
+                lhs: vec![2, 3],
+                rhs: vec![1, 2]
+            }
+        ));
+    }
+
+    #[test]
+    fn test_reductions() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Array::<f32>::from_vec(&context, vec![1., 5., 3., 4., 2., 6.], &[2, 3]);
+
+        let total = x.sum(None, false);
+        assert_eq!(total.shape(), &[] as &[usize]);
+        assert_eq!(total.to_vec(), vec![21.]);
+
+        let rows = x.mean(Some(&[1]), true);
+        assert_eq!(rows.shape(), &[2, 1]);
+        assert_eq!(rows.to_vec(), vec![3., 4.]);
+
+        assert_eq!(x.amax(Some(&[0]), false).to_vec(), vec![4., 5., 6.]);
+        assert_eq!(x.t().amin(Some(&[0]), false).to_vec(), vec![1., 2.]);
+        assert_eq!(x.prod(Some(&[0, 1]), false).to_vec(), vec![720.]);
+
+        assert_eq!(x.argmax(None, false).to_vec(), vec![5]);
+        assert_eq!(x.argmax(Some(1), false).to_vec(), vec![1, 2]);
+        assert_eq!(x.t().argmin(Some(1), true).to_vec(), vec![0, 1, 0]);
+
+        let y = Array::<i32>::from_vec(&context, vec![-3, 7, 7, 2], &[4]);
+        assert_eq!(y.sum(None, false).to_vec(), vec![13]);
+        assert_eq!(y.argmax(Some(0), false).to_vec(), vec![1]);
+
+        assert!(matches!(
+            x.try_sum(Some(&[2]), false).err(),
+            Some(ArrayError::Index(e)) if e == IndexError::InvalidAxes(vec![2], 2)
+        ));
+    }
+
+    #[test]
+    fn test_scans() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Array::<f32>::from_vec(&context, vec![1., 5., 3., 4., 2., 6.], &[2, 3]);
+
+        assert_eq!(x.cumsum(1).to_vec(), vec![1., 6., 9., 4., 6., 12.]);
+        assert_eq!(x.cumprod(0).to_vec(), vec![1., 5., 3., 4., 10., 18.]);
+
+        let columns = x.t().cumsum(1);
+        assert_eq!(columns.shape(), &[3, 2]);
+        assert_eq!(columns.to_vec(), vec![1., 5., 5., 7., 3., 9.]);
+
+        assert_eq!(
+            x.scan(ScanOp::Sum, 1, true).to_vec(),
+            vec![0., 1., 6., 0., 4., 6.]
+        );
+
+        assert!(matches!(
+            x.try_cumsum(2).err(),
+            Some(ArrayError::Index(e)) if e == IndexError::InvalidAxes(vec![2], 2)
+        ));
+    }
+}
@@ -0,0 +1,82 @@
+use std::{convert::Infallible, fmt::Display, str::FromStr};
+
+/// A summary of an adapter, as reported by [`Context::adapters`](super::Context::adapters).
+#[derive(Debug, Clone)]
+pub struct AdapterDescription {
+    pub index: usize,
+    pub info: wgpu::AdapterInfo,
+    pub features: wgpu::Features,
+    pub limits: wgpu::Limits,
+}
+
+impl AdapterDescription {
+    pub(crate) fn new(index: usize, adapter: &wgpu::Adapter) -> Self {
+        AdapterDescription {
+            index,
+            info: adapter.get_info(),
+            features: adapter.features(),
+            limits: adapter.limits(),
+        }
+    }
+}
+
+/// Picks a physical adapter out of those enumerated for the requested backends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdapterSelector {
+    /// The adapter at this position in [`Context::adapters`](super::Context::adapters).
+    Index(usize),
+    /// The first adapter whose name contains this string, ignoring case.
+    Name(String),
+    /// The first adapter using this backend.
+    Backend(wgpu::Backend),
+    /// The first software rasterizer, such as llvmpipe, lavapipe or WARP.
+    Software,
+}
+
+impl AdapterSelector {
+    pub fn matches(&self, description: &AdapterDescription) -> bool {
+        match self {
+            AdapterSelector::Index(index) => description.index == *index,
+            AdapterSelector::Name(name) => description
+                .info
+                .name
+                .to_lowercase()
+                .contains(&name.to_lowercase()),
+            AdapterSelector::Backend(backend) => description.info.backend == *backend,
+            AdapterSelector::Software => description.info.device_type == wgpu::DeviceType::Cpu,
+        }
+    }
+}
+
+impl Display for AdapterSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AdapterSelector::Index(index) => write!(f, "adapter #{}", index),
+            AdapterSelector::Name(name) => write!(f, "adapter named {:?}", name),
+            AdapterSelector::Backend(backend) => write!(f, "{:?} adapter", backend),
+            AdapterSelector::Software => write!(f, "software adapter"),
+        }
+    }
+}
+
+impl FromStr for AdapterSelector {
+    type Err = Infallible;
+
+    /// Parses `software`/`cpu`, an adapter index, a backend name, or otherwise
+    /// a name to search for.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let selector = match s.trim().to_lowercase().as_str() {
+            "software" | "cpu" => AdapterSelector::Software,
+            "vulkan" | "vk" => AdapterSelector::Backend(wgpu::Backend::Vulkan),
+            "metal" | "mtl" => AdapterSelector::Backend(wgpu::Backend::Metal),
+            "dx12" | "d3d12" => AdapterSelector::Backend(wgpu::Backend::Dx12),
+            "gl" | "gles" | "opengl" => AdapterSelector::Backend(wgpu::Backend::Gl),
+            lower => match lower.parse() {
+                Ok(index) => AdapterSelector::Index(index),
+                Err(_) => AdapterSelector::Name(s.trim().to_string()),
+            },
+        };
+
+        Ok(selector)
+    }
+}
@@ -0,0 +1,343 @@
+use std::{
+    ops::RangeBounds,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use bytemuck::try_cast_slice;
+
+use super::{
+    buffers::{Buffer, BufferCopyError, BufferWriteError, CopyRegion, PooledBuffer},
+    device::Context,
+    traits::BufferType,
+};
+
+/// Records copies, writes and compute dispatches into a single command
+/// encoder, so that a whole sequence of operations costs one submission.
+///
+/// Commands execute in the order they were recorded.
+pub struct CommandBatch<'a> {
+    context: &'a Context,
+    encoder: wgpu::CommandEncoder,
+    /// Upload buffers for recorded writes, kept alive until submission.
+    staging: Vec<PooledBuffer>,
+    /// The allocations of the operands of recorded commands, which must not
+    /// return to the pool before submission even if their buffers are dropped.
+    operands: Vec<Arc<PooledBuffer>>,
+}
+
+impl<'a> CommandBatch<'a> {
+    pub(crate) fn new(context: &'a Context) -> Self {
+        CommandBatch {
+            context,
+            encoder: context.command_encoder(),
+            staging: Vec::new(),
+            operands: Vec::new(),
+        }
+    }
+
+    /// Records a copy of the elements in `src_range` to `dst_range` of `dst`.
+    pub fn copy<T, A, B>(
+        &mut self,
+        src: &Buffer<T>,
+        src_range: A,
+        dst: &mut Buffer<T>,
+        dst_range: B,
+    ) -> Result<&mut Self, BufferCopyError<A, B>>
+    where
+        T: BufferType,
+        A: RangeBounds<usize> + Clone,
+        B: RangeBounds<usize> + Clone,
+    {
+        let region = src.resolve_copy(src_range, dst, dst_range)?;
+        self.record_copy(src, dst, region);
+        Ok(self)
+    }
+
+    /// Records a copy of the bytes in `src_range` to `dst_range` of `dst`.
+    pub fn copy_bytes<T, A, B>(
+        &mut self,
+        src: &Buffer<T>,
+        src_range: A,
+        dst: &mut Buffer<T>,
+        dst_range: B,
+    ) -> Result<&mut Self, BufferCopyError<A, B>>
+    where
+        T: BufferType,
+        A: RangeBounds<usize> + Clone,
+        B: RangeBounds<usize> + Clone,
+    {
+        let region = src.resolve_copy_bytes(src_range, dst, dst_range)?;
+        self.record_copy(src, dst, region);
+        Ok(self)
+    }
+
+    fn record_copy<T: BufferType>(&mut self, src: &Buffer<T>, dst: &Buffer<T>, region: CopyRegion) {
+        if region.size == 0 {
+            return;
+        }
+
+        self.keep_alive(src).keep_alive(dst);
+        self.encoder.copy_buffer_to_buffer(
+            src.raw(),
+            region.src_offset,
+            dst.raw(),
+            region.dst_offset,
+            region.size,
+        );
+    }
+
+    /// Records a write of `data` starting at element `offset` of `dst`.
+    ///
+    /// Unlike [`Buffer::queue_buffer_write`], which always lands before the
+    /// next submission, the write is ordered with the other recorded commands.
+    pub fn write<T: BufferType>(
+        &mut self,
+        dst: &Buffer<T>,
+        offset: usize,
+        data: &[T],
+    ) -> Result<&mut Self, BufferWriteError> {
+        let elem = size_of::<T>() as u64;
+        self.write_bytes(dst, offset as u64 * elem, try_cast_slice(data)?)
+    }
+
+    pub fn write_bytes<T: BufferType>(
+        &mut self,
+        dst: &Buffer<T>,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<&mut Self, BufferWriteError> {
+        let padded_size = dst.validate_write(offset, data.len() as u64)?;
+        if padded_size == 0 {
+            return Ok(self);
+        }
+
+        let mut contents = data.to_vec();
+        contents.resize(padded_size as usize, 0);
+
+        // NOTE: Mappable staging is filled at creation rather than through
+        // the queue, so nothing is written before the batch is submitted.
+        let staging = self.context.pool().create_init(
+            self.context.device(),
+            self.context.queue(),
+            wgpu::BufferUsages::MAP_WRITE | wgpu::BufferUsages::COPY_SRC,
+            &contents,
+        );
+        self.keep_alive(dst);
+        self.encoder
+            .copy_buffer_to_buffer(&staging, 0, dst.raw(), offset, padded_size);
+        self.staging.push(staging);
+
+        Ok(self)
+    }
+
+    /// Keeps the allocation of `buffer` from returning to the pool until the
+    /// batch is submitted, even if `buffer` is dropped before. Commands the
+    /// batch records do this for their operands.
+    pub fn keep_alive<T: BufferType>(&mut self, buffer: &Buffer<T>) -> &mut Self {
+        self.operands.push(buffer.allocation());
+        self
+    }
+
+    /// Records a dispatch of `pipeline` with `bind_groups` bound in order.
+    ///
+    /// The batch cannot see the buffers bound by `bind_groups`, so each must
+    /// be passed to [`CommandBatch::keep_alive`] unless it outlives the
+    /// submission.
+    pub fn dispatch(
+        &mut self,
+        pipeline: &wgpu::ComputePipeline,
+        bind_groups: &[&wgpu::BindGroup],
+        workgroups: (u32, u32, u32),
+    ) -> &mut Self {
+        let mut compute_pass = self.encoder.begin_compute_pass(&Default::default());
+
+        compute_pass.set_pipeline(pipeline);
+        for (index, bind_group) in bind_groups.iter().enumerate() {
+            compute_pass.set_bind_group(index as u32, bind_group, &[]);
+        }
+        compute_pass.dispatch_workgroups(workgroups.0, workgroups.1, workgroups.2);
+
+        drop(compute_pass);
+        self
+    }
+
+    /// Records a dispatch of `pipeline` whose workgroup counts are read on the
+    /// device from three `u32` at byte `offset` of `indirect`.
+    ///
+    /// Like [`CommandBatch::dispatch`], buffers bound by `bind_groups` must be
+    /// kept alive by the caller. `indirect` is kept alive by the batch.
+    pub fn dispatch_indirect(
+        &mut self,
+        pipeline: &wgpu::ComputePipeline,
+        bind_groups: &[&wgpu::BindGroup],
+        indirect: &Buffer<u32>,
+        offset: u64,
+    ) -> &mut Self {
+        self.keep_alive(indirect);
+        let mut compute_pass = self.encoder.begin_compute_pass(&Default::default());
+
+        compute_pass.set_pipeline(pipeline);
+        for (index, bind_group) in bind_groups.iter().enumerate() {
+            compute_pass.set_bind_group(index as u32, bind_group, &[]);
+        }
+        compute_pass.dispatch_workgroups_indirect(indirect.raw(), offset);
+
+        drop(compute_pass);
+        self
+    }
+
+    /// The underlying encoder, for commands the batch does not wrap.
+    pub fn encoder(&mut self) -> &mut wgpu::CommandEncoder {
+        &mut self.encoder
+    }
+
+    pub fn context(&self) -> &'a Context {
+        self.context
+    }
+
+    /// Submits every recorded command at once.
+    pub fn submit(self) -> SubmissionFence<'a> {
+        let index = self.context.submit([self.encoder.finish()]);
+
+        let done = Arc::new(AtomicBool::new(false));
+        let signal = done.clone();
+        self.context
+            .queue()
+            .on_submitted_work_done(move || signal.store(true, Ordering::Release));
+
+        SubmissionFence {
+            context: self.context,
+            index,
+            done,
+        }
+    }
+}
+
+/// Tracks a submitted [`CommandBatch`].
+pub struct SubmissionFence<'a> {
+    context: &'a Context,
+    index: wgpu::SubmissionIndex,
+    done: Arc<AtomicBool>,
+}
+
+impl SubmissionFence<'_> {
+    /// Checks whether the submission has finished, without blocking.
+    pub fn is_complete(&self) -> bool {
+        if !self.done.load(Ordering::Acquire) {
+            self.context.device().poll(wgpu::MaintainBase::Poll);
+        }
+
+        self.done.load(Ordering::Acquire)
+    }
+
+    /// Blocks until the submission has finished.
+    pub fn wait(self) {
+        self.context
+            .device()
+            .poll(wgpu::MaintainBase::WaitForSubmissionIndex(self.index));
+    }
+}
+
+impl Context {
+    /// Starts recording a [`CommandBatch`].
+    pub fn batch(&self) -> CommandBatch<'_> {
+        CommandBatch::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::{buffers::Buffer, device::test_context};
+
+    #[test]
+    fn test_batch_ordering() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let usage = wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST;
+        let x = Buffer::<u32>::with_len(&context, usage, 4);
+        let mut y = Buffer::<u32>::with_len(&context, usage, 4);
+        let mut z = Buffer::<u32>::with_len(
+            &context,
+            wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            4,
+        );
+
+        let mut batch = context.batch();
+        batch.write(&x, 0, &[1, 2, 3, 4]).unwrap();
+        batch.copy(&x, .., &mut y, ..).unwrap();
+        batch.write(&x, 2, &[9, 9]).unwrap();
+        batch.copy(&y, ..2, &mut z, ..2).unwrap();
+        batch.copy(&x, 2.., &mut z, 2..).unwrap();
+
+        let fence = batch.submit();
+        fence.wait();
+
+        assert_eq!(&*z.slice(..).map(&context), &[1, 2, 9, 9]);
+        z.unmap();
+    }
+
+    #[test]
+    fn test_writes_wait_for_submit() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let usage = wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST;
+        let x = Buffer::from_vec(&context, usage, vec![1u32, 2, 3, 4]);
+
+        let mut batch = context.batch();
+        batch.write(&x, 1, &[7, 8]).unwrap();
+        assert_eq!(x.read_to_vec(&context), vec![1, 2, 3, 4]);
+
+        batch.submit().wait();
+        assert_eq!(x.read_to_vec(&context), vec![1, 7, 8, 4]);
+    }
+
+    #[test]
+    fn test_fence_completes() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Buffer::<u32>::with_len(&context, wgpu::BufferUsages::COPY_DST, 4);
+        let mut batch = context.batch();
+        batch.write(&x, 0, &[1, 2, 3, 4]).unwrap();
+        let fence = batch.submit();
+
+        while !fence.is_complete() {
+            std::thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn test_dropped_operands() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let usage = wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST;
+        let x = Buffer::from_vec(&context, usage, vec![1u32, 2, 3, 4]);
+        let mut copied = Buffer::<u32>::with_len(
+            &context,
+            wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            4,
+        );
+
+        let mut batch = context.batch();
+        batch.copy(&x, .., &mut copied, ..).unwrap();
+        drop(x);
+
+        // This would otherwise reuse the allocation of `x`, and overwrite it
+        // before the batch runs.
+        let _written = Buffer::from_vec(&context, usage, vec![7u32; 4]);
+        batch.submit().wait();
+
+        assert_eq!(&*copied.slice(..).map(&context), &[1, 2, 3, 4]);
+        copied.unmap();
+    }
+}
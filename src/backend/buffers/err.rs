@@ -1,5 +1,6 @@
 use std::ops::RangeBounds;
 
+use bytemuck::PodCastError;
 use futures_channel::oneshot;
 
 #[derive(Debug, Clone, thiserror::Error)]
@@ -12,9 +13,50 @@ pub enum BufferCopyError<A: RangeBounds<usize>, B: RangeBounds<usize>> {
 
     #[error("Invalid Destination Buffer Usage: {0:?}")]
     InvalidDestinationBuffer(wgpu::BufferUsages),
+
+    #[error("Misaligned copy of {size} bytes from offset {src_offset} to offset {dst_offset}: offsets and sizes must be multiples of {}.", wgpu::COPY_BUFFER_ALIGNMENT)]
+    MisalignedCopy {
+        src_offset: u64,
+        dst_offset: u64,
+        size: u64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum BufferWriteError {
+    #[error("Invalid Buffer Usage: {0:?}")]
+    InvalidBufferUsage(wgpu::BufferUsages),
+
+    #[error("Failed to cast data: {0:?}")]
+    CastError(PodCastError),
+
+    #[error("Write of {size} bytes at offset {offset} exceeds the buffer size of {buffer_size}.")]
+    OutOfBounds {
+        offset: u64,
+        size: u64,
+        buffer_size: u64,
+    },
+
+    #[error("Misaligned write of {size} bytes at offset {offset}: offsets and sizes must be multiples of {}.", wgpu::COPY_BUFFER_ALIGNMENT)]
+    MisalignedWrite { offset: u64, size: u64 },
+
+    #[error("Expected {expected} elements to write, but {found} were provided.")]
+    LengthMismatch { expected: usize, found: usize },
+
+    #[error("Failed to map buffer for writing: {0}")]
+    Mapping(#[from] BufferMappingError<'W'>),
 }
 
 #[derive(Debug, Clone, thiserror::Error)]
+pub enum BufferReadError {
+    #[error("Invalid Buffer Usage: {0:?}")]
+    InvalidBufferUsage(wgpu::BufferUsages),
+
+    #[error("Failed to map buffer for reading: {0}")]
+    Mapping(#[from] BufferMappingError<'R'>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
 pub enum BufferMappingError<const TYPE: char> {
     #[error("Invalid {TYPE} Buffer Usage: {0:?}")]
     InvalidBufferUsage(wgpu::BufferUsages),
@@ -25,3 +67,9 @@ pub enum BufferMappingError<const TYPE: char> {
     #[error("Failed to map buffer: {0}")]
     BufferAsyncError(#[from] wgpu::BufferAsyncError),
 }
+
+impl From<PodCastError> for BufferWriteError {
+    fn from(value: PodCastError) -> Self {
+        BufferWriteError::CastError(value)
+    }
+}
@@ -1,68 +1,147 @@
-use bytemuck::{try_cast_slice, PodCastError};
+use bytemuck::try_cast_slice;
+use std::num::NonZeroU64;
+use std::ops::Bound;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
 use std::{marker::PhantomData, ops::RangeBounds};
-use wgpu::util::DeviceExt;
-use wgpu::BufferDescriptor;
 
-use wgpu::util::BufferInitDescriptor;
-
-use super::util::materialize;
+use super::util::{materialize, materialize_len};
 use super::{device::Context, traits::BufferType};
 
 mod err;
+mod pool;
 mod slice;
+mod transfer;
 mod view;
 
-pub use self::{err::BufferCopyError, slice::BufferSlice};
-
+pub(crate) use self::pool::{BufferPool, PooledBuffer, DEFAULT_MAX_CACHED_BYTES};
+pub use self::{
+    err::{BufferCopyError, BufferReadError, BufferWriteError},
+    pool::PoolStats,
+    slice::BufferSlice,
+};
+
+/// The usages of the buffers that kernels and arrays allocate, so that they can
+/// be bound to compute pipelines and copied to and from staging buffers.
+pub(crate) const RESULT_USAGE: wgpu::BufferUsages = wgpu::BufferUsages::STORAGE
+    .union(wgpu::BufferUsages::COPY_SRC)
+    .union(wgpu::BufferUsages::COPY_DST);
+
+/// A typed buffer allocated from the buffer pool of a [`Context`]. The
+/// underlying `wgpu::Buffer` may be larger than `size`, and is returned to the
+/// pool on drop.
 #[derive(Debug)]
 pub struct Buffer<T: BufferType> {
-    buffer: wgpu::Buffer,
+    /// Shared with the batches that recorded commands on the buffer, so that
+    /// it is not recycled before they are submitted.
+    buffer: Arc<PooledBuffer>,
     usage: wgpu::BufferUsages,
+    size: u64,
+    mapped: AtomicBool,
     _phantom: PhantomData<T>,
 }
 
 impl<T: BufferType> Buffer<T> {
-    pub fn new(context: &Context, usage: wgpu::BufferUsages, size: u64) -> Buffer<T> {
-        let buffer = context.device().create_buffer(&BufferDescriptor {
-            label: None,
-            size,
-            usage,
-            mapped_at_creation: false,
-        });
+    /// Allocates a zeroed buffer holding `len` elements.
+    pub fn with_len(context: &Context, usage: wgpu::BufferUsages, len: usize) -> Buffer<T> {
+        Self::with_size_bytes(context, usage, (len * size_of::<T>()) as u64)
+    }
+
+    /// Allocates a zeroed buffer of `size` bytes. Recycled allocations are
+    /// zeroed at the start of the next submission, together with any others.
+    pub fn with_size_bytes(context: &Context, usage: wgpu::BufferUsages, size: u64) -> Buffer<T> {
+        let (buffer, recycled) = context.pool().acquire(context.device(), usage, size);
+        let buffer = Arc::new(buffer);
+
+        if recycled {
+            context.pool().defer_clear(buffer.clone());
+        }
 
         Buffer {
             buffer,
             usage,
+            size,
+            mapped: AtomicBool::new(false),
             _phantom: PhantomData,
         }
     }
 
     pub fn from_vec(context: &Context, usage: wgpu::BufferUsages, vec: Vec<T>) -> Buffer<T> {
-        let buffer = context.device().create_buffer_init(&BufferInitDescriptor {
-            label: None,
-            contents: bytemuck::checked::cast_slice(&vec),
-            usage,
-        });
+        let contents: &[u8] = bytemuck::checked::cast_slice(&vec);
+        let buffer = context
+            .pool()
+            .create_init(context.device(), context.queue(), usage, contents);
 
         Buffer {
-            buffer,
+            buffer: Arc::new(buffer),
             usage,
+            size: contents.len() as u64,
+            mapped: AtomicBool::new(false),
             _phantom: PhantomData,
         }
     }
 
+    /// The number of elements in the buffer.
+    pub fn len(&self) -> usize {
+        self.size as usize / size_of::<T>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// The size of the buffer in bytes.
     pub fn size(&self) -> u64 {
-        self.buffer.size()
+        self.size
+    }
+
+    pub fn usage(&self) -> wgpu::BufferUsages {
+        self.usage
     }
 
-    pub fn slice(&self, range: impl RangeBounds<u64>) -> BufferSlice<T> {
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> BufferSlice<'_, T> {
+        let bound = materialize(range, self);
+        let elem = size_of::<T>() as u64;
+
+        self.slice_bytes(bound.start() as u64 * elem..bound.end() as u64 * elem)
+    }
+
+    /// Slices the buffer by byte offsets. Mapping requires aligned offsets,
+    /// so the mapped range is widened as needed and trimmed again by the view.
+    pub fn slice_bytes(&self, range: impl RangeBounds<u64>) -> BufferSlice<'_, T> {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end + 1,
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => self.size,
+        }
+        .min(self.size);
+        let start = start.min(end);
+
+        // NOTE: Pooled buffers are at least `MAP_ALIGNMENT` bytes and padded to
+        // `COPY_BUFFER_ALIGNMENT`, so the widened range is always in bounds.
+        let aligned_start =
+            (start - start % wgpu::MAP_ALIGNMENT).min(self.buffer.size() - wgpu::MAP_ALIGNMENT);
+        let aligned_end = end
+            .next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT)
+            .max(aligned_start + wgpu::COPY_BUFFER_ALIGNMENT);
+
         BufferSlice {
             buffer: self,
-            slice: self.buffer.slice(range),
+            slice: self.buffer.slice(aligned_start..aligned_end),
+            start: (start - aligned_start) as usize,
+            end: (end - aligned_start) as usize,
             _phantom: PhantomData,
         }
     }
 
+    /// Copies the elements in `src_range` to `dst_range` of `buffer`.
     pub fn copy_to<A, B>(
         &self,
         context: &Context,
@@ -70,8 +149,8 @@ impl<T: BufferType> Buffer<T> {
         buffer: &mut Buffer<T>,
         dst_range: B,
     ) where
-        A: RangeBounds<usize> + std::fmt::Debug + std::marker::Copy,
-        B: RangeBounds<usize> + std::fmt::Debug + std::marker::Copy,
+        A: RangeBounds<usize> + std::fmt::Debug + Clone,
+        B: RangeBounds<usize> + std::fmt::Debug + Clone,
     {
         let copy_to_result = self.try_copy_to(context, src_range, buffer, dst_range);
         if let Err(e) = &copy_to_result {
@@ -89,19 +168,95 @@ impl<T: BufferType> Buffer<T> {
         dst_range: B,
     ) -> Result<(), BufferCopyError<A, B>>
     where
-        A: RangeBounds<usize> + std::marker::Copy,
-        B: RangeBounds<usize> + std::marker::Copy,
+        A: RangeBounds<usize> + Clone,
+        B: RangeBounds<usize> + Clone,
     {
-        if !self.usage.contains(wgpu::BufferUsages::COPY_SRC) {
-            return Err(BufferCopyError::InvalidSourceBuffer(self.usage));
+        let mut batch = context.batch();
+        batch.copy(self, src_range, buffer, dst_range)?;
+        batch.submit().wait();
+
+        Ok(())
+    }
+
+    /// Copies the bytes in `src_range` to `dst_range` of `buffer`.
+    pub fn copy_to_bytes<A, B>(
+        &self,
+        context: &Context,
+        src_range: A,
+        buffer: &mut Buffer<T>,
+        dst_range: B,
+    ) where
+        A: RangeBounds<usize> + std::fmt::Debug + Clone,
+        B: RangeBounds<usize> + std::fmt::Debug + Clone,
+    {
+        let copy_to_result = self.try_copy_to_bytes(context, src_range, buffer, dst_range);
+        if let Err(e) = &copy_to_result {
+            log::error!("Failed at Buffer::copy_to_bytes: {:?}", e);
         }
 
-        if !buffer.usage.contains(wgpu::BufferUsages::COPY_DST) {
-            return Err(BufferCopyError::InvalidDestinationBuffer(self.usage));
+        copy_to_result.unwrap();
+    }
+
+    pub fn try_copy_to_bytes<A, B>(
+        &self,
+        context: &Context,
+        src_range: A,
+        buffer: &mut Buffer<T>,
+        dst_range: B,
+    ) -> Result<(), BufferCopyError<A, B>>
+    where
+        A: RangeBounds<usize> + Clone,
+        B: RangeBounds<usize> + Clone,
+    {
+        let mut batch = context.batch();
+        batch.copy_bytes(self, src_range, buffer, dst_range)?;
+        batch.submit().wait();
+
+        Ok(())
+    }
+
+    /// Resolves element ranges into the byte offsets and size of a copy.
+    pub(crate) fn resolve_copy<A, B>(
+        &self,
+        src_range: A,
+        buffer: &Buffer<T>,
+        dst_range: B,
+    ) -> Result<CopyRegion, BufferCopyError<A, B>>
+    where
+        A: RangeBounds<usize> + Clone,
+        B: RangeBounds<usize> + Clone,
+    {
+        let src_bound = materialize(src_range.clone(), self);
+        let dst_bound = materialize(dst_range.clone(), buffer);
+
+        if src_bound.len() != dst_bound.len() {
+            return Err(BufferCopyError::UnequalReferenceLengths(
+                src_range, dst_range,
+            ));
         }
 
-        let src_bound = materialize(src_range, self);
-        let dst_bound = materialize(dst_range, self);
+        let elem = size_of::<T>() as u64;
+        self.validate_copy(
+            src_bound.start() as u64 * elem,
+            buffer,
+            dst_bound.start() as u64 * elem,
+            src_bound.len() as u64 * elem,
+        )
+    }
+
+    /// Resolves byte ranges into the byte offsets and size of a copy.
+    pub(crate) fn resolve_copy_bytes<A, B>(
+        &self,
+        src_range: A,
+        buffer: &Buffer<T>,
+        dst_range: B,
+    ) -> Result<CopyRegion, BufferCopyError<A, B>>
+    where
+        A: RangeBounds<usize> + Clone,
+        B: RangeBounds<usize> + Clone,
+    {
+        let src_bound = materialize_len(src_range.clone(), self.size as usize);
+        let dst_bound = materialize_len(dst_range.clone(), buffer.size as usize);
 
         if src_bound.len() != dst_bound.len() {
             return Err(BufferCopyError::UnequalReferenceLengths(
@@ -109,38 +264,133 @@ impl<T: BufferType> Buffer<T> {
             ));
         }
 
-        let mut command_encoder = context.command_encoder();
-        command_encoder.copy_buffer_to_buffer(
-            &self.buffer,
+        self.validate_copy(
             src_bound.start() as u64,
-            &buffer.buffer,
+            buffer,
             dst_bound.start() as u64,
             src_bound.len() as u64,
-        );
-        let command_buffer = command_encoder.finish();
+        )
+    }
+
+    /// Validates usages and alignment of a copy of `size` bytes. A copy that
+    /// runs to the end of both buffers is padded up to `COPY_BUFFER_ALIGNMENT`.
+    fn validate_copy<A, B>(
+        &self,
+        src_offset: u64,
+        buffer: &Buffer<T>,
+        dst_offset: u64,
+        size: u64,
+    ) -> Result<CopyRegion, BufferCopyError<A, B>>
+    where
+        A: RangeBounds<usize>,
+        B: RangeBounds<usize>,
+    {
+        if !self.usage.contains(wgpu::BufferUsages::COPY_SRC) {
+            return Err(BufferCopyError::InvalidSourceBuffer(self.usage));
+        }
 
-        let idx = context.queue().submit([command_buffer]);
-        context
-            .device()
-            .poll(wgpu::MaintainBase::WaitForSubmissionIndex(idx));
+        if !buffer.usage.contains(wgpu::BufferUsages::COPY_DST) {
+            return Err(BufferCopyError::InvalidDestinationBuffer(buffer.usage));
+        }
 
-        Ok(())
+        let reaches_end = src_offset + size == self.size && dst_offset + size == buffer.size;
+        let padded_size = match reaches_end {
+            true => size.next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT),
+            false => size,
+        };
+
+        if !src_offset.is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT)
+            || !dst_offset.is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT)
+            || !padded_size.is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT)
+        {
+            return Err(BufferCopyError::MisalignedCopy {
+                src_offset,
+                dst_offset,
+                size,
+            });
+        }
+
+        Ok(CopyRegion {
+            src_offset,
+            dst_offset,
+            size: padded_size,
+        })
     }
 
+    /// Queues a write of `data` starting at element `offset`.
     pub fn try_queue_buffer_write(
         &self,
         context: &Context,
-        position: u64,
+        offset: usize,
         data: &[T],
-    ) -> Result<(), PodCastError> {
-        context
-            .queue()
-            .write_buffer(&self.buffer, position, try_cast_slice(data)?);
+    ) -> Result<(), BufferWriteError> {
+        let elem = size_of::<T>() as u64;
+        self.try_queue_buffer_write_bytes(context, offset as u64 * elem, try_cast_slice(data)?)
+    }
+
+    pub fn queue_buffer_write(&self, context: &Context, offset: usize, data: &[T]) {
+        let queue_buffer_write_result = self.try_queue_buffer_write(context, offset, data);
+
+        if let Err(e) = &queue_buffer_write_result {
+            log::error!("Failed to queue buffer write result: {}", e);
+        }
+        queue_buffer_write_result.unwrap();
+    }
+
+    /// Queues a write of `data` starting at byte `offset`. Like copies, a
+    /// write that runs to the end of the buffer is padded to alignment.
+    pub fn try_queue_buffer_write_bytes(
+        &self,
+        context: &Context,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<(), BufferWriteError> {
+        let padded_size = self.validate_write(offset, data.len() as u64)?;
+
+        // NOTE: Queue writes land before the commands of the next submission,
+        // so a pending clear of this buffer must be submitted first.
+        context.submit_clears();
+        match padded_size == data.len() as u64 {
+            true => context.queue().write_buffer(&self.buffer, offset, data),
+            false => {
+                let mut data = data.to_vec();
+                data.resize(padded_size as usize, 0);
+                context.queue().write_buffer(&self.buffer, offset, &data);
+            }
+        }
         Ok(())
     }
 
-    pub fn queue_buffer_write(&self, context: &Context, position: u64, data: &[T]) {
-        let queue_buffer_write_result = self.try_queue_buffer_write(context, position, data);
+    /// Validates a write of `size` bytes at `offset`, returning the padded size.
+    pub(crate) fn validate_write(&self, offset: u64, size: u64) -> Result<u64, BufferWriteError> {
+        if !self.usage.contains(wgpu::BufferUsages::COPY_DST) {
+            return Err(BufferWriteError::InvalidBufferUsage(self.usage));
+        }
+
+        if offset + size > self.size {
+            return Err(BufferWriteError::OutOfBounds {
+                offset,
+                size,
+                buffer_size: self.size,
+            });
+        }
+
+        let padded_size = match offset + size == self.size {
+            true => size.next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT),
+            false => size,
+        };
+
+        if !offset.is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT)
+            || !padded_size.is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT)
+        {
+            return Err(BufferWriteError::MisalignedWrite { offset, size });
+        }
+
+        Ok(padded_size)
+    }
+
+    pub fn queue_buffer_write_bytes(&self, context: &Context, offset: u64, data: &[u8]) {
+        let queue_buffer_write_result = self.try_queue_buffer_write_bytes(context, offset, data);
 
         if let Err(e) = &queue_buffer_write_result {
             log::error!("Failed to queue buffer write result: {}", e);
@@ -150,19 +400,77 @@ impl<T: BufferType> Buffer<T> {
 
     pub fn unmap(&mut self) {
         self.buffer.unmap();
+        *self.mapped.get_mut() = false;
+    }
+
+    /// Unmaps a buffer mapped by this crate, once no view into it remains.
+    pub(crate) fn unmap_shared(&self) {
+        self.buffer.unmap();
+        self.mapped.store(false, Ordering::Release);
     }
 
-    pub fn get_resource(&self) -> wgpu::BindingResource {
-        self.buffer.as_entire_binding()
+    pub(crate) fn mark_mapped(&self) {
+        self.mapped.store(true, Ordering::Release);
+    }
+
+    pub(crate) fn raw(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    pub(crate) fn allocation(&self) -> Arc<PooledBuffer> {
+        self.buffer.clone()
+    }
+
+    /// Binds the whole buffer. Storage bindings must be sized in whole
+    /// words, so buffers of 8- and 16-bit elements are bound with the
+    /// padding after their last element, which is always allocated.
+    pub fn get_resource(&self) -> wgpu::BindingResource<'_> {
+        wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+            buffer: &self.buffer,
+            offset: 0,
+            size: NonZeroU64::new(self.size.next_multiple_of(4)),
+        })
+    }
+}
+
+/// A validated, aligned region of a buffer-to-buffer copy, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CopyRegion {
+    pub(crate) src_offset: u64,
+    pub(crate) dst_offset: u64,
+    pub(crate) size: u64,
+}
+
+impl<T: BufferType> Drop for Buffer<T> {
+    fn drop(&mut self) {
+        // Buffers must be unmapped before they can be handed out again.
+        if *self.mapped.get_mut() {
+            self.buffer.unmap();
+        }
     }
 }
 #[cfg(test)]
 mod tests {
-    use crate::backend::{buffers::Buffer, device::Context};
+    use std::{
+        future::Future,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        task::{self, Poll, Wake, Waker},
+        time::{Duration, Instant},
+    };
+
+    use crate::backend::{
+        buffers::{err::BufferMappingError, Buffer, BufferCopyError},
+        device::{test_context, Context},
+    };
 
     #[test]
     fn test_map() {
-        let context = Context::new();
+        let Some(context) = test_context() else {
+            return;
+        };
 
         let vec = vec![0., 0., 0., 0.];
         let mut x = Buffer::from_vec(&context, wgpu::BufferUsages::MAP_READ, vec.clone());
@@ -176,7 +484,9 @@ mod tests {
 
     #[test]
     fn test_map_mut() {
-        let context = Context::new();
+        let Some(context) = test_context() else {
+            return;
+        };
 
         let vec = vec![0., 0., 0., 0.];
         let mut x = Buffer::from_vec(&context, wgpu::BufferUsages::MAP_WRITE, vec.clone());
@@ -192,48 +502,116 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
-    fn test_invalid_map() {
-        let context = Context::new();
-
-        let vec = vec![0., 0., 0., 0.];
-        let mut x = Buffer::from_vec(&context, wgpu::BufferUsages::MAP_WRITE, vec.clone());
-        let y = x.slice(..).map(&context);
-
-        assert_eq!(&vec, &*y);
-
+    fn test_map_async() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let mut x = Buffer::from_vec(&context, wgpu::BufferUsages::MAP_READ, vec![1u32, 2, 3, 4]);
+        let y = smol::block_on(x.slice(1..3).map_async(&context)).unwrap();
+        assert_eq!(&*y, &[2, 3]);
         drop(y);
         x.unmap();
+
+        let mut z = Buffer::from_vec(&context, wgpu::BufferUsages::MAP_WRITE, vec![0u32; 4]);
+        let mut w = smol::block_on(z.slice(..).map_mut_async(&context)).unwrap();
+        w.copy_from_slice(&[4, 3, 2, 1]);
+        drop(w);
+        z.unmap();
+
+        let result = smol::block_on(z.slice(..).map_async(&context));
+        assert!(matches!(
+            result,
+            Err(BufferMappingError::InvalidBufferUsage(_))
+        ));
     }
 
     #[test]
-    #[should_panic]
-    fn test_invalid_map_mut() {
-        let context = Context::new();
+    fn test_map_async_wakes() {
+        let Some(context) = test_context() else {
+            return;
+        };
 
-        let vec = vec![0., 0., 0., 0.];
-        let mut x = Buffer::from_vec(&context, wgpu::BufferUsages::MAP_READ, vec.clone());
-        let mut y = x.slice(..).map_mut(&context);
+        struct CountingWaker(AtomicUsize);
 
-        assert_eq!(&vec, &*y);
-        y.iter_mut().enumerate().for_each(|(i, v)| {
-            *v = i as f32 * 4.;
-        });
+        impl Wake for CountingWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
 
-        drop(y);
-        x.unmap();
+        let usage = wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST;
+        let x = Buffer::<u32>::with_len(&context, usage, 1 << 16);
+        let y = Buffer::<u32>::with_len(&context, usage, 1 << 16);
+
+        let counter = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let waker = Waker::from(counter.clone());
+        let mut cx = task::Context::from_waker(&waker);
+
+        // The poller thread completes the mappings and wakes the task, which
+        // is polled again only once woken.
+        for buffer in [&x, &y] {
+            let slice = buffer.slice(..);
+            let mut future = Box::pin(slice.map_async(&context));
+            let wakes = counter.0.load(Ordering::SeqCst);
+
+            let deadline = Instant::now() + Duration::from_secs(10);
+            let mut polls = 0;
+            let view = loop {
+                polls += 1;
+                if let Poll::Ready(view) = future.as_mut().poll(&mut cx) {
+                    break view.unwrap();
+                }
+
+                while counter.0.load(Ordering::SeqCst) == wakes + polls - 1 {
+                    assert!(Instant::now() < deadline, "map_async was never woken");
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+            };
+
+            assert!(polls <= 2, "map_async was polled {} times", polls);
+            assert!(view.iter().all(|&v| v == 0));
+        }
+    }
+
+    #[test]
+    fn test_invalid_map() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Buffer::from_vec(&context, wgpu::BufferUsages::MAP_WRITE, vec![0f32; 4]);
+        assert!(matches!(
+            x.slice(..).try_map(&context),
+            Err(BufferMappingError::InvalidBufferUsage(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_map_mut() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Buffer::from_vec(&context, wgpu::BufferUsages::MAP_READ, vec![0f32; 4]);
+        assert!(matches!(
+            x.slice(..).try_map_mut(&context),
+            Err(BufferMappingError::InvalidBufferUsage(_))
+        ));
     }
 
     #[test]
     fn test_copy_between() {
-        let context = Context::new();
+        let Some(context) = test_context() else {
+            return;
+        };
 
         let vec = vec![1., 2., 3., 4.];
         let x = Buffer::from_vec(&context, wgpu::BufferUsages::COPY_SRC, vec.clone());
-        let mut y = Buffer::new(
+        let mut y = Buffer::with_len(
             &context,
             wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
-            x.size(),
+            x.len(),
         );
 
         x.copy_to(&context, .., &mut y, ..);
@@ -246,24 +624,152 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
     fn test_invalid_copy_between() {
-        let context = Context::new();
+        let Some(context) = test_context() else {
+            return;
+        };
 
-        let vec = vec![1., 2., 3., 4.];
-        let x = Buffer::from_vec(&context, wgpu::BufferUsages::COPY_DST, vec.clone());
-        let mut y = Buffer::new(
+        let x = Buffer::from_vec(
             &context,
-            wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::MAP_READ,
-            x.size(),
+            wgpu::BufferUsages::COPY_DST,
+            vec![1f32, 2., 3., 4.],
         );
+        let mut y = Buffer::with_len(&context, wgpu::BufferUsages::COPY_DST, x.len());
 
-        x.copy_to(&context, .., &mut y, ..);
-        let y_read = y.slice(..).map(&context);
+        assert!(matches!(
+            x.try_copy_to(&context, .., &mut y, ..),
+            Err(BufferCopyError::InvalidSourceBuffer(_))
+        ));
+    }
 
-        assert_eq!(&vec, &*y_read);
+    #[test]
+    fn test_pool_reuse() {
+        let Some(context) = test_context() else {
+            return;
+        };
 
-        drop(y_read);
+        let usage = wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC;
+        let x = Buffer::from_vec(&context, usage, vec![1u32, 2, 3, 4]);
+        drop(x);
+
+        let stats = context.pool_stats();
+        assert_eq!(stats.bytes_live, 0);
+        assert_eq!(stats.bytes_cached, 256);
+
+        let y = Buffer::<u32>::with_len(&context, usage, 4);
+        let stats = context.pool_stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.bytes_cached, 0);
+
+        let mut w = Buffer::<u32>::with_len(
+            &context,
+            wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            y.len(),
+        );
+        y.copy_to(&context, .., &mut w, ..);
+        assert_eq!(w.slice(..).map(&context).to_vec(), vec![0; 4]);
+
+        drop((y, w));
+        assert_eq!(context.pool_stats().bytes_cached, 512);
+
+        context.trim_pool();
+        assert_eq!(context.pool_stats().bytes_cached, 0);
+    }
+
+    #[test]
+    fn test_pool_deferred_clears() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let usage = wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::COPY_SRC
+            | wgpu::BufferUsages::COPY_DST;
+        drop(Buffer::from_vec(&context, usage, vec![7u32; 8]));
+        drop(Buffer::from_vec(&context, usage, vec![9u32; 8]));
+
+        // Both recycled buffers are cleared by the next submission, but a
+        // queued write lands after the clear of its buffer.
+        let x = Buffer::<u32>::with_len(&context, usage, 8);
+        let y = Buffer::<u32>::with_len(&context, usage, 8);
+        assert_eq!(context.pool_stats().hits, 2);
+        y.queue_buffer_write(&context, 2, &[1, 2]);
+
+        assert_eq!(x.read_to_vec(&context), vec![0; 8]);
+        assert_eq!(y.read_to_vec(&context), vec![0, 0, 1, 2, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_pool_capacity() {
+        let Ok(context) = smol::block_on(Context::builder().max_cached_bytes(512).try_build())
+        else {
+            return;
+        };
+
+        let usage = wgpu::BufferUsages::STORAGE;
+        let buffers = (0..3)
+            .map(|_| Buffer::<u32>::with_len(&context, usage, 4))
+            .collect::<Vec<_>>();
+        drop(buffers);
+
+        // Only two of the three 256-byte allocations fit the cap.
+        let stats = context.pool_stats();
+        assert_eq!(stats.bytes_live, 0);
+        assert_eq!(stats.bytes_cached, 512);
+    }
+
+    #[test]
+    fn test_copy_elements() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Buffer::from_vec(
+            &context,
+            wgpu::BufferUsages::COPY_SRC,
+            (1..=8).collect::<Vec<u32>>(),
+        );
+        let mut y = Buffer::<u32>::with_len(
+            &context,
+            wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            4,
+        );
+        assert_eq!(x.len(), 8);
+        assert_eq!(x.size(), 32);
+
+        x.copy_to(&context, 2..6, &mut y, ..);
+
+        assert_eq!(&*y.slice(..).map(&context), &[3, 4, 5, 6]);
+        y.unmap();
+        assert_eq!(&*y.slice(1..=2).map(&context), &[4, 5]);
+        y.unmap();
+    }
+
+    #[test]
+    fn test_misaligned_copy() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Buffer::from_vec(&context, wgpu::BufferUsages::COPY_SRC, vec![1u8, 2, 3]);
+        let mut y = Buffer::<u8>::with_len(
+            &context,
+            wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            3,
+        );
+
+        assert!(matches!(
+            x.try_copy_to(&context, 1..3, &mut y, 0..2),
+            Err(BufferCopyError::MisalignedCopy {
+                src_offset: 1,
+                dst_offset: 0,
+                size: 2
+            })
+        ));
+
+        // Copies to the end of both buffers are padded, so odd sizes still work.
+        x.copy_to(&context, .., &mut y, ..);
+        assert_eq!(&*y.slice(..).map(&context), &[1, 2, 3]);
         y.unmap();
     }
 }
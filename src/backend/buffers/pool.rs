@@ -0,0 +1,279 @@
+use std::{
+    collections::HashMap,
+    ops::Deref,
+    sync::{Arc, Weak},
+};
+
+use parking_lot::Mutex;
+
+/// Buffers up to this size all share the smallest size class.
+const MIN_SIZE_CLASS: u64 = 256;
+
+/// The most bytes a pool keeps cached unless configured otherwise, see
+/// [`ContextBuilder::max_cached_bytes`](crate::ContextBuilder::max_cached_bytes).
+pub(crate) const DEFAULT_MAX_CACHED_BYTES: u64 = 1 << 30;
+
+/// Statistics about the buffers handed out and cached by a [`Context`](crate::Context).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Bytes held by buffers that are currently alive.
+    pub bytes_live: u64,
+    /// Bytes held by freed buffers waiting to be reused.
+    pub bytes_cached: u64,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl PoolStats {
+    pub fn hit_rate(&self) -> f64 {
+        let requests = self.hits + self.misses;
+        if requests == 0 {
+            return 0.;
+        }
+
+        self.hits as f64 / requests as f64
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+struct PoolKey {
+    usage: wgpu::BufferUsages,
+    size: u64,
+}
+
+#[derive(Debug, Default)]
+struct PoolInner {
+    free: HashMap<PoolKey, Vec<wgpu::Buffer>>,
+    /// Recycled buffers to zero before the next submission.
+    clears: Vec<Arc<PooledBuffer>>,
+    stats: PoolStats,
+}
+
+/// A caching allocator that recycles freed `wgpu::Buffer`s by usage and size
+/// class, keeping at most `max_cached_bytes` of them.
+#[derive(Debug)]
+pub(crate) struct BufferPool {
+    inner: Mutex<PoolInner>,
+    max_cached_bytes: u64,
+}
+
+impl BufferPool {
+    pub(crate) fn new(max_cached_bytes: u64) -> Self {
+        BufferPool {
+            inner: Default::default(),
+            max_cached_bytes,
+        }
+    }
+
+    /// Hands out a buffer of at least `size` bytes. The returned flag is set
+    /// when the buffer was recycled, and thus holds stale contents.
+    pub(crate) fn acquire(
+        self: &Arc<Self>,
+        device: &wgpu::Device,
+        usage: wgpu::BufferUsages,
+        size: u64,
+    ) -> (PooledBuffer, bool) {
+        let reusable = is_reusable(usage);
+        let key = PoolKey {
+            usage,
+            size: size_class(size, device.limits().max_buffer_size),
+        };
+
+        let mut inner = self.inner.lock();
+        inner.stats.bytes_live += key.size;
+
+        let recycled = match reusable {
+            true => inner.free.get_mut(&key).and_then(Vec::pop),
+            false => None,
+        };
+
+        let (buffer, hit) = match recycled {
+            Some(buffer) => {
+                inner.stats.hits += 1;
+                inner.stats.bytes_cached -= key.size;
+                (buffer, true)
+            }
+            None => {
+                inner.stats.misses += 1;
+                drop(inner);
+
+                let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                    label: None,
+                    size: key.size,
+                    usage: wgpu_usage(usage),
+                    mapped_at_creation: false,
+                });
+                (buffer, false)
+            }
+        };
+
+        let pooled = PooledBuffer {
+            buffer: Some(buffer),
+            pool: Arc::downgrade(self),
+            key,
+            reusable,
+        };
+
+        (pooled, hit)
+    }
+
+    /// Creates a buffer initialized with `contents` that is returned to the
+    /// pool like any other once dropped. Recycled buffers are written through
+    /// the queue, so the write lands with the next submission.
+    pub(crate) fn create_init(
+        self: &Arc<Self>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        usage: wgpu::BufferUsages,
+        contents: &[u8],
+    ) -> PooledBuffer {
+        let size = contents.len() as u64;
+
+        if is_reusable(usage) {
+            let (buffer, _) = self.acquire(device, usage, size);
+
+            if !contents.is_empty() {
+                let padded = size.next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT) as usize;
+                match padded == contents.len() {
+                    true => queue.write_buffer(&buffer, 0, contents),
+                    false => {
+                        let mut contents = contents.to_vec();
+                        contents.resize(padded, 0);
+                        queue.write_buffer(&buffer, 0, &contents);
+                    }
+                }
+            }
+
+            return buffer;
+        }
+
+        let key = PoolKey {
+            usage,
+            size: size_class(size, device.limits().max_buffer_size),
+        };
+
+        let mut inner = self.inner.lock();
+        inner.stats.bytes_live += key.size;
+        inner.stats.misses += 1;
+        drop(inner);
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: key.size,
+            usage: wgpu_usage(usage),
+            mapped_at_creation: true,
+        });
+        buffer.slice(..).get_mapped_range_mut()[..contents.len()].copy_from_slice(contents);
+        buffer.unmap();
+
+        PooledBuffer {
+            buffer: Some(buffer),
+            pool: Arc::downgrade(self),
+            key,
+            reusable: false,
+        }
+    }
+
+    /// Defers zeroing the recycled `buffer` to the next submission, which
+    /// takes the clears with [`BufferPool::take_clears`].
+    pub(crate) fn defer_clear(&self, buffer: Arc<PooledBuffer>) {
+        self.inner.lock().clears.push(buffer);
+    }
+
+    pub(crate) fn take_clears(&self) -> Vec<Arc<PooledBuffer>> {
+        std::mem::take(&mut self.inner.lock().clears)
+    }
+
+    /// Caches `buffer` for reuse, unless that would exceed the byte cap, in
+    /// which case it is destroyed.
+    fn release(&self, key: PoolKey, buffer: wgpu::Buffer, reusable: bool) {
+        let mut inner = self.inner.lock();
+        inner.stats.bytes_live -= key.size;
+
+        if reusable && inner.stats.bytes_cached + key.size <= self.max_cached_bytes {
+            inner.stats.bytes_cached += key.size;
+            inner.free.entry(key).or_default().push(buffer);
+        }
+    }
+
+    pub(crate) fn stats(&self) -> PoolStats {
+        self.inner.lock().stats
+    }
+
+    /// Frees every cached buffer.
+    pub(crate) fn trim(&self) {
+        let mut inner = self.inner.lock();
+        inner.free.clear();
+        inner.stats.bytes_cached = 0;
+    }
+}
+
+/// A `wgpu::Buffer` that returns itself to its [`BufferPool`] when dropped.
+#[derive(Debug)]
+pub(crate) struct PooledBuffer {
+    buffer: Option<wgpu::Buffer>,
+    pool: Weak<BufferPool>,
+    key: PoolKey,
+    reusable: bool,
+}
+
+impl Deref for PooledBuffer {
+    type Target = wgpu::Buffer;
+
+    fn deref(&self) -> &Self::Target {
+        // NOTE: The buffer is only ever taken in `drop`.
+        self.buffer.as_ref().unwrap()
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        if let (Some(pool), Some(buffer)) = (self.pool.upgrade(), self.buffer.take()) {
+            pool.release(self.key, buffer, self.reusable);
+        }
+    }
+}
+
+/// Recycled buffers are zeroed with `clear_buffer`, so they need `COPY_DST`,
+/// which wgpu does not allow alongside `MAP_WRITE`.
+fn is_reusable(usage: wgpu::BufferUsages) -> bool {
+    !usage.contains(wgpu::BufferUsages::MAP_WRITE)
+}
+
+fn wgpu_usage(usage: wgpu::BufferUsages) -> wgpu::BufferUsages {
+    match is_reusable(usage) {
+        true => usage | wgpu::BufferUsages::COPY_DST,
+        false => usage,
+    }
+}
+
+/// Rounds `size` up to one of four classes per power of two, so that at most
+/// a fifth of each allocation is wasted.
+fn size_class(size: u64, max_size: u64) -> u64 {
+    let class = if size <= MIN_SIZE_CLASS {
+        MIN_SIZE_CLASS
+    } else {
+        let step = size.next_power_of_two() / 8;
+        size.div_ceil(step) * step
+    };
+
+    if class > max_size {
+        return size.next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT);
+    }
+    class
+}
+
+#[cfg(test)]
+mod tests {
+    use super::size_class;
+
+    #[test]
+    fn test_size_class() {
+        assert_eq!(size_class(0, u64::MAX), 256);
+        assert_eq!(size_class(256, u64::MAX), 256);
+        assert_eq!(size_class(257, u64::MAX), 320);
+        assert_eq!(size_class(1000, u64::MAX), 1024);
+        assert_eq!(size_class(1025, u64::MAX), 1280);
+        assert_eq!(size_class(1025, 1200), 1028);
+    }
+}
@@ -14,6 +14,9 @@ use super::view::BufferView;
 pub struct BufferSlice<'a, T: BufferType> {
     pub(crate) buffer: &'a Buffer<T>,
     pub(crate) slice: wgpu::BufferSlice<'a>,
+    /// The byte range requested, relative to the start of `slice`.
+    pub(crate) start: usize,
+    pub(crate) end: usize,
     pub(crate) _phantom: PhantomData<&'a T>,
 }
 
@@ -29,24 +32,26 @@ impl<'a, T: BufferType> BufferSlice<'a, T> {
     }
 
     pub fn try_map(&self, context: &Context) -> Result<BufferView<'a, T>, BufferMappingError<'R'>> {
-        if !self.buffer.usage.contains(wgpu::BufferUsages::MAP_READ) {
-            return Err(BufferMappingError::InvalidBufferUsage(self.buffer.usage));
-        }
-
-        let (sndr, rcvr) = oneshot::channel();
-        self.slice.map_async(wgpu::MapMode::Read, move |status| {
-            if sndr.send(status).is_err() {
-                log::error!("Could not send map_async result.")
-            }
-        });
+        let receiver =
+            self.request_map(context, wgpu::MapMode::Read, wgpu::BufferUsages::MAP_READ)?;
         context.device().poll(wgpu::MaintainBase::Wait);
-        smol::block_on(rcvr)??;
+        smol::block_on(receiver)??;
 
-        Ok(BufferView {
-            // NOTE: This will panic if map_async failed.
-            view: self.slice.get_mapped_range(),
-            _phantom: PhantomData,
-        })
+        Ok(self.mapped_view())
+    }
+
+    /// Maps the slice for reading without blocking. The device is polled by
+    /// the poller thread of `context` until the mapping completes.
+    pub async fn map_async(
+        &self,
+        context: &Context,
+    ) -> Result<BufferView<'a, T>, BufferMappingError<'R'>> {
+        let receiver =
+            self.request_map(context, wgpu::MapMode::Read, wgpu::BufferUsages::MAP_READ)?;
+        let _polling = context.poller().watch();
+        receiver.await??;
+
+        Ok(self.mapped_view())
     }
 
     pub fn map_mut(&self, context: &Context) -> BufferViewMut<'a, T> {
@@ -63,23 +68,80 @@ impl<'a, T: BufferType> BufferSlice<'a, T> {
         &self,
         context: &Context,
     ) -> Result<BufferViewMut<'a, T>, BufferMappingError<'W'>> {
-        if !self.buffer.usage.contains(wgpu::BufferUsages::MAP_WRITE) {
+        let receiver =
+            self.request_map(context, wgpu::MapMode::Write, wgpu::BufferUsages::MAP_WRITE)?;
+        context.device().poll(wgpu::MaintainBase::Wait);
+        smol::block_on(receiver)??;
+
+        Ok(self.mapped_view_mut())
+    }
+
+    /// Maps the slice for writing without blocking, see
+    /// [`BufferSlice::map_async`].
+    pub async fn map_mut_async(
+        &self,
+        context: &Context,
+    ) -> Result<BufferViewMut<'a, T>, BufferMappingError<'W'>> {
+        let receiver =
+            self.request_map(context, wgpu::MapMode::Write, wgpu::BufferUsages::MAP_WRITE)?;
+        let _polling = context.poller().watch();
+        receiver.await??;
+
+        Ok(self.mapped_view_mut())
+    }
+
+    fn request_map<const TYPE: char>(
+        &self,
+        context: &Context,
+        mode: wgpu::MapMode,
+        required: wgpu::BufferUsages,
+    ) -> Result<MapReceiver, BufferMappingError<TYPE>> {
+        if !self.buffer.usage.contains(required) {
             return Err(BufferMappingError::InvalidBufferUsage(self.buffer.usage));
         }
 
+        // NOTE: Queue writes and the clears of recycled buffers only land
+        // with a submission, and a mapping for reading would otherwise wait
+        // for, or miss, pending ones.
+        if mode == wgpu::MapMode::Read {
+            context.submit([]);
+        }
+
+        // NOTE: Awaiting the receiver stores the waker of the task in the
+        // channel, and sending from the callback wakes it.
         let (sndr, rcvr) = oneshot::channel();
-        self.slice.map_async(wgpu::MapMode::Write, move |status| {
+        self.slice.map_async(mode, move |status| {
             if sndr.send(status).is_err() {
                 log::error!("Could not send map_async result.")
             }
         });
-        context.device().poll(wgpu::MaintainBase::Wait);
-        smol::block_on(rcvr)??;
 
-        Ok(BufferViewMut {
+        Ok(rcvr)
+    }
+
+    fn mapped_view(&self) -> BufferView<'a, T> {
+        self.buffer.mark_mapped();
+
+        BufferView {
+            // NOTE: This will panic if map_async failed.
+            view: self.slice.get_mapped_range(),
+            start: self.start,
+            end: self.end,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn mapped_view_mut(&self) -> BufferViewMut<'a, T> {
+        self.buffer.mark_mapped();
+
+        BufferViewMut {
             // NOTE: This will throw if map_async failed.
             view: self.slice.get_mapped_range_mut(),
+            start: self.start,
+            end: self.end,
             _phantom: PhantomData,
-        })
+        }
     }
 }
+
+type MapReceiver = oneshot::Receiver<Result<(), wgpu::BufferAsyncError>>;
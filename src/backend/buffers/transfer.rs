@@ -0,0 +1,270 @@
+use std::ops::RangeBounds;
+
+use bytemuck::try_cast_slice;
+
+use crate::backend::buffers::{Buffer, BufferReadError, BufferWriteError};
+use crate::backend::device::Context;
+use crate::backend::traits::BufferType;
+use crate::backend::util::materialize;
+
+/// The largest staging buffer a single transfer allocates. Larger buffers are
+/// transferred in chunks of this size through the same staging buffer.
+pub const STAGING_CHUNK_SIZE: u64 = 64 << 20;
+
+impl<T: BufferType> Buffer<T> {
+    /// Reads the whole buffer back to the host. Buffers without `MAP_READ`
+    /// are copied out through a staging buffer.
+    pub fn read_to_vec(&self, context: &Context) -> Vec<T> {
+        let read_result = self.try_read_to_vec(context);
+
+        if let Err(e) = &read_result {
+            log::error!("Failed at Buffer::read_to_vec: {}", e);
+        }
+
+        read_result.unwrap()
+    }
+
+    pub fn try_read_to_vec(&self, context: &Context) -> Result<Vec<T>, BufferReadError> {
+        self.read_chunked(context, .., STAGING_CHUNK_SIZE)
+    }
+
+    /// Reads the elements in `range` back to the host, staging only the words
+    /// that hold them.
+    pub fn read_range_to_vec(&self, context: &Context, range: impl RangeBounds<usize>) -> Vec<T> {
+        let read_result = self.try_read_range_to_vec(context, range);
+
+        if let Err(e) = &read_result {
+            log::error!("Failed at Buffer::read_range_to_vec: {}", e);
+        }
+
+        read_result.unwrap()
+    }
+
+    pub fn try_read_range_to_vec(
+        &self,
+        context: &Context,
+        range: impl RangeBounds<usize>,
+    ) -> Result<Vec<T>, BufferReadError> {
+        self.read_chunked(context, range, STAGING_CHUNK_SIZE)
+    }
+
+    /// Overwrites the whole buffer with `data`, which must hold exactly
+    /// [`Buffer::len`] elements. Buffers without `MAP_WRITE` are filled
+    /// through a staging buffer.
+    pub fn write_from_slice(&self, context: &Context, data: &[T]) {
+        let write_result = self.try_write_from_slice(context, data);
+
+        if let Err(e) = &write_result {
+            log::error!("Failed at Buffer::write_from_slice: {}", e);
+        }
+
+        write_result.unwrap()
+    }
+
+    pub fn try_write_from_slice(
+        &self,
+        context: &Context,
+        data: &[T],
+    ) -> Result<(), BufferWriteError> {
+        self.write_chunked(context, data, STAGING_CHUNK_SIZE)
+    }
+
+    pub(crate) fn read_chunked(
+        &self,
+        context: &Context,
+        range: impl RangeBounds<usize>,
+        chunk_size: u64,
+    ) -> Result<Vec<T>, BufferReadError> {
+        let bound = materialize(range, self);
+
+        if self.usage.contains(wgpu::BufferUsages::MAP_READ) {
+            let vec = self
+                .slice(bound.start()..bound.end())
+                .try_map(context)?
+                .to_vec();
+            self.unmap_shared();
+            return Ok(vec);
+        }
+
+        if !self.usage.contains(wgpu::BufferUsages::COPY_SRC) {
+            return Err(BufferReadError::InvalidBufferUsage(self.usage));
+        }
+
+        if bound.len() == 0 {
+            return Ok(Vec::new());
+        }
+
+        // NOTE: Copies are widened to whole words, which the padding of the
+        // allocation leaves room for, and trimmed again once mapped.
+        let elem = size_of::<T>() as u64;
+        let start = bound.start() as u64 * elem;
+        let end = bound.end() as u64 * elem;
+        let aligned_start = start - start % wgpu::COPY_BUFFER_ALIGNMENT;
+        let span = end.next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT) - aligned_start;
+
+        let chunk_size = chunk_size.min(span);
+        let mut staging = Buffer::<u8>::with_size_bytes(
+            context,
+            wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            chunk_size,
+        );
+
+        let mut vec = vec![T::zeroed(); bound.len()];
+        let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut vec);
+        for offset in (0..span).step_by(chunk_size as usize) {
+            let len = chunk_size.min(span - offset);
+
+            let mut batch = context.batch();
+            batch.encoder().copy_buffer_to_buffer(
+                self.raw(),
+                aligned_start + offset,
+                staging.raw(),
+                0,
+                len,
+            );
+            batch.submit().wait();
+
+            // The bytes of this chunk that lie in the range, relative to it.
+            let from = (start - aligned_start).max(offset) - offset;
+            let to = (end - aligned_start).min(offset + len) - offset;
+            let at = (aligned_start + offset + from - start) as usize;
+            bytes[at..at + (to - from) as usize]
+                .copy_from_slice(&staging.slice_bytes(from..to).try_map(context)?);
+            staging.unmap();
+        }
+
+        Ok(vec)
+    }
+
+    pub(crate) fn write_chunked(
+        &self,
+        context: &Context,
+        data: &[T],
+        chunk_size: u64,
+    ) -> Result<(), BufferWriteError> {
+        if data.len() != self.len() {
+            return Err(BufferWriteError::LengthMismatch {
+                expected: self.len(),
+                found: data.len(),
+            });
+        }
+
+        if self.usage.contains(wgpu::BufferUsages::MAP_WRITE) {
+            self.slice(..).try_map_mut(context)?.copy_from_slice(data);
+            self.unmap_shared();
+            return Ok(());
+        }
+
+        if !self.usage.contains(wgpu::BufferUsages::COPY_DST) {
+            return Err(BufferWriteError::InvalidBufferUsage(self.usage));
+        }
+
+        let bytes: &[u8] = try_cast_slice(data)?;
+        if bytes.is_empty() {
+            return Ok(());
+        }
+
+        let chunk_size = chunk_size.min(self.size.next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT));
+        let mut staging = Buffer::<u8>::with_size_bytes(
+            context,
+            wgpu::BufferUsages::MAP_WRITE | wgpu::BufferUsages::COPY_SRC,
+            chunk_size,
+        );
+
+        for (index, chunk) in bytes.chunks(chunk_size as usize).enumerate() {
+            staging
+                .slice_bytes(..chunk.len() as u64)
+                .try_map_mut(context)?
+                .copy_from_slice(chunk);
+            staging.unmap();
+
+            // NOTE: The padding of the last chunk only lands in the padding of
+            // this buffer's allocation, past `size`.
+            let mut batch = context.batch();
+            batch.encoder().copy_buffer_to_buffer(
+                staging.raw(),
+                0,
+                self.raw(),
+                index as u64 * chunk_size,
+                (chunk.len() as u64).next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT),
+            );
+            batch.submit().wait();
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::{
+        buffers::{Buffer, BufferReadError, BufferWriteError},
+        device::test_context,
+    };
+
+    #[test]
+    fn test_staged_round_trip() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let usage = wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::COPY_SRC
+            | wgpu::BufferUsages::COPY_DST;
+        let data = (0..1000).collect::<Vec<u32>>();
+        let x = Buffer::<u32>::with_len(&context, usage, data.len());
+
+        x.write_from_slice(&context, &data);
+        assert_eq!(x.read_to_vec(&context), data);
+
+        // Chunks that do not divide the buffer exercise the unaligned tail.
+        let odd = (0..1001).map(|i| i as u8).collect::<Vec<u8>>();
+        let y = Buffer::<u8>::with_len(&context, usage, odd.len());
+        y.write_chunked(&context, &odd, 256).unwrap();
+        assert_eq!(y.read_chunked(&context, .., 256).unwrap(), odd);
+
+        // Ranges that start and end inside words are trimmed after staging.
+        assert_eq!(y.read_chunked(&context, 3..998, 256).unwrap(), &odd[3..998]);
+        assert_eq!(y.read_range_to_vec(&context, 1001..), Vec::<u8>::new());
+        assert_eq!(x.read_range_to_vec(&context, 10..=12), vec![10, 11, 12]);
+    }
+
+    #[test]
+    fn test_mappable_round_trip() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Buffer::<u32>::with_len(&context, wgpu::BufferUsages::MAP_WRITE, 4);
+        x.write_from_slice(&context, &[1, 2, 3, 4]);
+
+        let y = Buffer::from_vec(&context, wgpu::BufferUsages::MAP_READ, vec![5u32, 6, 7]);
+        assert_eq!(y.read_to_vec(&context), vec![5, 6, 7]);
+        assert_eq!(y.read_to_vec(&context), vec![5, 6, 7]);
+        assert_eq!(y.read_range_to_vec(&context, 1..), vec![6, 7]);
+    }
+
+    #[test]
+    fn test_invalid_transfer() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Buffer::<u32>::with_len(&context, wgpu::BufferUsages::STORAGE, 4);
+        assert!(matches!(
+            x.try_read_to_vec(&context),
+            Err(BufferReadError::InvalidBufferUsage(_))
+        ));
+        assert!(matches!(
+            x.try_write_from_slice(&context, &[0; 4]),
+            Err(BufferWriteError::InvalidBufferUsage(_))
+        ));
+        assert!(matches!(
+            x.try_write_from_slice(&context, &[0; 3]),
+            Err(BufferWriteError::LengthMismatch {
+                expected: 4,
+                found: 3
+            })
+        ));
+    }
+}
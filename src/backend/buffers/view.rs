@@ -1,20 +1,23 @@
-use std::{marker::PhantomData, ops::{Deref, DerefMut}};
+use std::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
 
 use bytemuck::checked::{try_cast_slice, try_cast_slice_mut};
 
 use crate::backend::traits::BufferType;
 
-
-
 #[derive(Debug)]
 pub struct BufferView<'a, T: BufferType> {
     pub(crate) view: wgpu::BufferView<'a>,
+    pub(crate) start: usize,
+    pub(crate) end: usize,
     pub(crate) _phantom: PhantomData<&'a T>,
 }
 
 impl<T: BufferType> BufferView<'_, T> {
     fn try_deref(&self) -> Result<&[T], bytemuck::checked::CheckedCastError> {
-        try_cast_slice(self.view.deref())
+        try_cast_slice(&self.view.deref()[self.start..self.end])
     }
 }
 
@@ -35,16 +38,18 @@ impl<T: BufferType> Deref for BufferView<'_, T> {
 #[derive(Debug)]
 pub struct BufferViewMut<'a, T: BufferType> {
     pub(crate) view: wgpu::BufferViewMut<'a>,
+    pub(crate) start: usize,
+    pub(crate) end: usize,
     pub(crate) _phantom: PhantomData<&'a mut T>,
 }
 
 impl<T: BufferType> BufferViewMut<'_, T> {
     fn try_deref(&self) -> Result<&[T], bytemuck::checked::CheckedCastError> {
-        try_cast_slice(self.view.deref())
+        try_cast_slice(&self.view.deref()[self.start..self.end])
     }
 
     fn try_deref_mut(&mut self) -> Result<&mut [T], bytemuck::checked::CheckedCastError> {
-        try_cast_slice_mut(self.view.deref_mut())
+        try_cast_slice_mut(&mut self.view.deref_mut()[self.start..self.end])
     }
 }
 
@@ -73,4 +78,3 @@ impl<T: BufferType> DerefMut for BufferViewMut<'_, T> {
         deref_mut_result.unwrap()
     }
 }
-
@@ -1,8 +1,43 @@
 use std::sync::Arc;
 
+use super::{
+    adapter::{AdapterDescription, AdapterSelector},
+    buffers::{BufferPool, PoolStats, PooledBuffer, DEFAULT_MAX_CACHED_BYTES},
+    kernels::KernelCache,
+    pipeline::PipelineCache,
+    poller::DevicePoller,
+};
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum ContextError {
+    #[error("No suitable adapter was found.")]
+    NoAdapter,
+
+    #[error("No {0} was found.")]
+    NoMatchingAdapter(AdapterSelector),
+
+    #[error("Failed to request device: {0}")]
+    RequestDevice(#[from] wgpu::RequestDeviceError),
+
+    #[error("The adapter does not support the requested features: {0:?}")]
+    UnsupportedFeatures(wgpu::Features),
+
+    #[error("The adapter does not support the requested limit {name}: requested {requested}, allowed {allowed}")]
+    UnsupportedLimit {
+        name: &'static str,
+        requested: u64,
+        allowed: u64,
+    },
+}
+
 pub struct Context {
-    device: wgpu::Device,
+    device: Arc<wgpu::Device>,
     queue: wgpu::Queue,
+    adapter_info: wgpu::AdapterInfo,
+    pool: Arc<BufferPool>,
+    kernels: KernelCache,
+    pipelines: PipelineCache,
+    poller: DevicePoller,
 }
 
 impl Context {
@@ -15,13 +50,45 @@ impl Context {
         )
     }
 
+    pub fn try_new() -> Result<Arc<Self>, ContextError> {
+        smol::block_on(
+            Self::builder()
+                .adapter(Default::default())
+                .device(Default::default())
+                .try_build(),
+        )
+    }
+
     pub fn builder<'a, 'b>() -> ContextBuilder<'a, 'b> {
         ContextBuilder {
+            backends: wgpu::Backends::all(),
             adapter_options: None,
+            adapter_selector: None,
             device_options: None,
+            max_cached_bytes: DEFAULT_MAX_CACHED_BYTES,
         }
     }
 
+    /// Lists the adapters available on the given backends, in the order used
+    /// by [`AdapterSelector::Index`].
+    pub fn adapters(backends: wgpu::Backends) -> Vec<AdapterDescription> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends,
+            ..Default::default()
+        });
+
+        instance
+            .enumerate_adapters(backends)
+            .iter()
+            .enumerate()
+            .map(|(index, adapter)| AdapterDescription::new(index, adapter))
+            .collect()
+    }
+
+    pub fn adapter_info(&self) -> &wgpu::AdapterInfo {
+        &self.adapter_info
+    }
+
     pub fn device(&self) -> &wgpu::Device {
         &self.device
     }
@@ -30,46 +97,325 @@ impl Context {
         &self.queue
     }
 
+    /// The features enabled on the device, which are exactly those requested
+    /// through [`ContextBuilder::device`].
+    pub fn features(&self) -> wgpu::Features {
+        self.device.features()
+    }
+
+    /// The limits enforced on the device, which are exactly those requested
+    /// through [`ContextBuilder::device`].
+    pub fn limits(&self) -> wgpu::Limits {
+        self.device.limits()
+    }
+
+    pub(crate) fn pool(&self) -> &Arc<BufferPool> {
+        &self.pool
+    }
+
+    pub(crate) fn kernels(&self) -> &KernelCache {
+        &self.kernels
+    }
+
+    pub(crate) fn pipelines(&self) -> &PipelineCache {
+        &self.pipelines
+    }
+
+    pub(crate) fn poller(&self) -> &DevicePoller {
+        &self.poller
+    }
+
+    pub fn pool_stats(&self) -> PoolStats {
+        self.pool.stats()
+    }
+
+    /// Frees every buffer cached for reuse by the buffer pool.
+    pub fn trim_pool(&self) {
+        self.pool.trim();
+    }
+
     pub fn command_encoder(&self) -> wgpu::CommandEncoder {
         self.device.create_command_encoder(&Default::default())
     }
+
+    /// Submits `command_buffers` to the queue, after zeroing the buffers
+    /// recycled by the pool since the last submission.
+    pub(crate) fn submit(
+        &self,
+        command_buffers: impl IntoIterator<Item = wgpu::CommandBuffer>,
+    ) -> wgpu::SubmissionIndex {
+        let clears = self.pool.take_clears();
+        let clear_commands = self.clear_commands(&clears);
+
+        self.queue
+            .submit(clear_commands.into_iter().chain(command_buffers))
+    }
+
+    /// Submits the pending clears of recycled buffers, if any, so that queue
+    /// writes issued next are not overwritten by them.
+    pub(crate) fn submit_clears(&self) {
+        let clears = self.pool.take_clears();
+        if let Some(clear_commands) = self.clear_commands(&clears) {
+            self.queue.submit([clear_commands]);
+        }
+    }
+
+    fn clear_commands(&self, clears: &[Arc<PooledBuffer>]) -> Option<wgpu::CommandBuffer> {
+        if clears.is_empty() {
+            return None;
+        }
+
+        let mut command_encoder = self.command_encoder();
+        for buffer in clears {
+            command_encoder.clear_buffer(buffer, 0, None);
+        }
+        Some(command_encoder.finish())
+    }
 }
 
 pub struct ContextBuilder<'a, 'b> {
+    backends: wgpu::Backends,
     adapter_options: Option<wgpu::RequestAdapterOptions<'a, 'b>>,
+    adapter_selector: Option<AdapterSelector>,
     device_options: Option<wgpu::DeviceDescriptor<'a>>,
+    max_cached_bytes: u64,
 }
 
 impl<'a, 'b> ContextBuilder<'a, 'b> {
+    /// Restricts which backends adapters are requested from.
+    pub fn backends(mut self, backends: wgpu::Backends) -> Self {
+        self.backends = backends;
+        self
+    }
+
     pub fn adapter(mut self, options: wgpu::RequestAdapterOptions<'a, 'b>) -> Self {
         self.adapter_options.replace(options);
         self
     }
 
+    /// Picks the adapter explicitly instead of letting wgpu choose one from
+    /// the [`RequestAdapterOptions`](wgpu::RequestAdapterOptions).
+    pub fn select(mut self, selector: AdapterSelector) -> Self {
+        self.adapter_selector.replace(selector);
+        self
+    }
+
     pub fn device(mut self, options: wgpu::DeviceDescriptor<'a>) -> Self {
         self.device_options.replace(options);
         self
     }
 
+    /// Caps the bytes of freed buffers the buffer pool keeps for reuse, 1 GiB
+    /// by default. Buffers freed past the cap are destroyed.
+    pub fn max_cached_bytes(mut self, bytes: u64) -> Self {
+        self.max_cached_bytes = bytes;
+        self
+    }
+
     pub async fn build(self) -> Arc<Context> {
-        let request_device = async {
-            let instance = wgpu::Instance::new(Default::default());
+        let build_result = self.try_build().await;
 
-            let adapter = instance
+        if let Err(e) = &build_result {
+            log::error!("Could not construct device and queue: {}", e);
+        }
+
+        build_result.unwrap()
+    }
+
+    pub async fn try_build(self) -> Result<Arc<Context>, ContextError> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: self.backends,
+            ..Default::default()
+        });
+
+        let adapter = match self.adapter_selector {
+            Some(selector) => instance
+                .enumerate_adapters(self.backends)
+                .into_iter()
+                .enumerate()
+                .find(|(index, adapter)| {
+                    selector.matches(&AdapterDescription::new(*index, adapter))
+                })
+                .map(|(_, adapter)| adapter)
+                .ok_or(ContextError::NoMatchingAdapter(selector))?,
+            None => instance
                 .request_adapter(&self.adapter_options.unwrap_or_default())
-                .await?;
+                .await
+                .ok_or(ContextError::NoAdapter)?,
+        };
+
+        let descriptor = self.device_options.unwrap_or_default();
 
-            adapter.request_device(&Default::default(), None).await.ok()
+        let missing_features = descriptor.required_features - adapter.features();
+        if !missing_features.is_empty() {
+            return Err(ContextError::UnsupportedFeatures(missing_features));
+        }
+
+        let mut unsupported_limit = None;
+        descriptor.required_limits.check_limits_with_fail_fn(
+            &adapter.limits(),
+            true,
+            |name, requested, allowed| {
+                unsupported_limit.get_or_insert(ContextError::UnsupportedLimit {
+                    name,
+                    requested,
+                    allowed,
+                });
+            },
+        );
+        if let Some(e) = unsupported_limit {
+            return Err(e);
+        }
+
+        let (device, queue) = adapter.request_device(&descriptor, None).await?;
+        let device = Arc::new(device);
+
+        Ok(Context {
+            poller: DevicePoller::new(device.clone()),
+            device,
+            queue,
+            adapter_info: adapter.get_info(),
+            pool: Arc::new(BufferPool::new(self.max_cached_bytes)),
+            kernels: Default::default(),
+            pipelines: Default::default(),
+        }
+        .into())
+    }
+}
+
+/// Creates a context for tests, or `None` if this machine has no suitable
+/// adapter so that the test can be skipped.
+#[cfg(test)]
+pub(crate) fn test_context() -> Option<Arc<Context>> {
+    match Context::try_new() {
+        Ok(context) => Some(context),
+        Err(e) => {
+            log::warn!("Skipping test: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::adapter::{AdapterDescription, AdapterSelector};
+
+    use super::{Context, ContextError};
+
+    #[test]
+    fn test_select_adapter() {
+        let adapters = Context::adapters(wgpu::Backends::all());
+        let Some(first) = adapters.first() else {
+            return;
         };
 
-        let (device, queue) = match request_device.await {
-            Some((device, queue)) => (device, queue),
-            None => {
-                log::error!("Could not construct device and queue");
-                std::process::exit(1);
-            }
+        let selectors = [
+            AdapterSelector::Index(first.index),
+            AdapterSelector::Name(first.info.name.to_uppercase()),
+            AdapterSelector::Backend(first.info.backend),
+        ];
+
+        for selector in selectors {
+            let context = smol::block_on(Context::builder().select(selector.clone()).try_build())
+                .expect("Failed to build context for an enumerated adapter");
+            assert!(selector.matches(&AdapterDescription {
+                index: first.index,
+                info: context.adapter_info().clone(),
+                features: first.features,
+                limits: first.limits.clone(),
+            }));
+        }
+
+        let software = adapters
+            .iter()
+            .any(|a| a.info.device_type == wgpu::DeviceType::Cpu);
+        let result = smol::block_on(
+            Context::builder()
+                .select(AdapterSelector::Software)
+                .try_build(),
+        );
+        match result {
+            Ok(context) => assert_eq!(context.adapter_info().device_type, wgpu::DeviceType::Cpu),
+            Err(e) => assert!(!software, "Failed to select software adapter: {}", e),
+        }
+    }
+
+    #[test]
+    fn test_no_matching_adapter() {
+        let selector = AdapterSelector::Name("not a real adapter".into());
+        let result = smol::block_on(Context::builder().select(selector.clone()).try_build());
+
+        assert!(matches!(result, Err(ContextError::NoMatchingAdapter(s)) if s == selector));
+    }
+
+    #[test]
+    fn test_requested_limits() {
+        let required_limits = wgpu::Limits {
+            max_storage_buffer_binding_size: 1 << 20,
+            ..wgpu::Limits::downlevel_defaults()
+        };
+
+        let context = match smol::block_on(
+            Context::builder()
+                .device(wgpu::DeviceDescriptor {
+                    required_limits: required_limits.clone(),
+                    ..Default::default()
+                })
+                .try_build(),
+        ) {
+            Ok(context) => context,
+            Err(ContextError::NoAdapter) => return,
+            Err(e) => panic!("Failed to build context: {}", e),
         };
 
-        Context { device, queue }.into()
+        assert_eq!(
+            context.limits().max_storage_buffer_binding_size,
+            required_limits.max_storage_buffer_binding_size
+        );
+        assert_eq!(context.features(), wgpu::Features::empty());
+    }
+
+    #[test]
+    fn test_unsupported_limit() {
+        let result = smol::block_on(
+            Context::builder()
+                .device(wgpu::DeviceDescriptor {
+                    required_limits: wgpu::Limits {
+                        max_bind_groups: u32::MAX,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .try_build(),
+        );
+
+        match result {
+            Err(ContextError::NoAdapter) => (),
+            Err(ContextError::UnsupportedLimit {
+                name, requested, ..
+            }) => {
+                assert_eq!(name, "max_bind_groups");
+                assert_eq!(requested, u32::MAX as u64);
+            }
+            _ => panic!("Expected an unsupported limit error"),
+        }
+    }
+
+    #[test]
+    fn test_unsupported_features() {
+        let result = smol::block_on(
+            Context::builder()
+                .device(wgpu::DeviceDescriptor {
+                    required_features: wgpu::Features::all(),
+                    ..Default::default()
+                })
+                .try_build(),
+        );
+
+        match result {
+            Err(ContextError::NoAdapter) => (),
+            Err(ContextError::UnsupportedFeatures(missing)) => assert!(!missing.is_empty()),
+            _ => panic!("Expected an unsupported features error"),
+        }
     }
 }
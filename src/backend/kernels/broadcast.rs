@@ -0,0 +1,151 @@
+use crate::{
+    backend::{batch::CommandBatch, buffers::Buffer, traits::BufferType},
+    indexing::{broadcast_shapes, Layout},
+};
+
+use super::{kernel_type, BinaryOp, BuiltinKernel, KernelError};
+
+impl CommandBatch<'_> {
+    /// Records `result = op(lhs, rhs)`, element-wise, where each operand is
+    /// read through its layout and both are broadcast to a common shape. The
+    /// result is written contiguously, and must hold exactly as many elements
+    /// as the broadcast shape.
+    pub fn binary_broadcast<T: BufferType>(
+        &mut self,
+        op: BinaryOp,
+        (lhs, lhs_layout): (&Buffer<T>, &Layout),
+        (rhs, rhs_layout): (&Buffer<T>, &Layout),
+        result: &mut Buffer<T>,
+    ) -> Result<&mut Self, KernelError> {
+        let kernel = BuiltinKernel::Broadcast(op);
+        let ty = kernel_type::<T>(self.context().features())?;
+        if !kernel.supports(ty) {
+            return Err(KernelError::UnsupportedOp { op: op.name(), ty });
+        }
+
+        let shape = broadcast_shapes(lhs_layout.shape(), rhs_layout.shape())?;
+        let lhs_layout = lhs_layout.broadcast_to(&shape)?;
+        let rhs_layout = rhs_layout.broadcast_to(&shape)?;
+
+        for buffer in [lhs, rhs, &*result] {
+            if !buffer.usage().contains(wgpu::BufferUsages::STORAGE) {
+                return Err(KernelError::InvalidBufferUsage(buffer.usage()));
+            }
+        }
+
+        let len = shape.iter().product();
+        if result.len() != len {
+            return Err(KernelError::LengthMismatch {
+                op: op.name(),
+                expected: len,
+                found: result.len(),
+            });
+        }
+
+        if len == 0 {
+            return Ok(self);
+        }
+
+        let mut geometry = vec![
+            shape.len() as i32,
+            lhs_layout.offset() as i32,
+            rhs_layout.offset() as i32,
+        ];
+        geometry.extend(shape.iter().map(|&len| len as i32));
+        geometry.extend(lhs_layout.strides().iter().map(|&stride| stride as i32));
+        geometry.extend(rhs_layout.strides().iter().map(|&stride| stride as i32));
+        let geometry = Buffer::from_vec(self.context(), wgpu::BufferUsages::STORAGE, geometry);
+
+        self.keep_alive(lhs)
+            .keep_alive(rhs)
+            .keep_alive(result)
+            .keep_alive(&geometry);
+
+        let resources = vec![
+            lhs.get_resource(),
+            rhs.get_resource(),
+            result.get_resource(),
+            geometry.get_resource(),
+        ];
+        self.dispatch_elementwise(kernel, ty, resources, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backend::{buffers::Buffer, device::test_context, kernels::RESULT_USAGE},
+        indexing::{IndexError, Layout},
+    };
+
+    use super::{BinaryOp, KernelError};
+
+    #[test]
+    fn test_binary_broadcast() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Buffer::from_vec(&context, RESULT_USAGE, (0..6).collect::<Vec<i32>>());
+        let y = Buffer::from_vec(&context, RESULT_USAGE, vec![10i32, 20]);
+        let mut z = Buffer::<i32>::with_len(&context, RESULT_USAGE, 12);
+
+        // A [3, 1, 2] array minus a [2, 1] array broadcasts to [3, 2, 2].
+        let mut batch = context.batch();
+        batch
+            .binary_broadcast(
+                BinaryOp::Sub,
+                (&x, &Layout::contiguous(&[3, 1, 2])),
+                (&y, &Layout::contiguous(&[2, 1])),
+                &mut z,
+            )
+            .unwrap();
+        batch.submit();
+
+        assert_eq!(
+            z.read_to_vec(&context),
+            vec![-10, -9, -20, -19, -8, -7, -18, -17, -6, -5, -16, -15]
+        );
+
+        // Strided operands, like a transposed view, need no copies either.
+        let mut w = Buffer::<i32>::with_len(&context, RESULT_USAGE, 6);
+        let mut batch = context.batch();
+        batch
+            .binary_broadcast(
+                BinaryOp::Add,
+                (&x, &Layout::contiguous(&[3, 2]).t()),
+                (&x, &Layout::contiguous(&[2, 3])),
+                &mut w,
+            )
+            .unwrap();
+        batch.submit();
+
+        assert_eq!(w.read_to_vec(&context), vec![0, 3, 6, 4, 7, 10]);
+    }
+
+    #[test]
+    fn test_broadcast_mismatch() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Buffer::from_vec(&context, RESULT_USAGE, vec![1f32; 6]);
+        let mut z = Buffer::<f32>::with_len(&context, RESULT_USAGE, 6);
+
+        let mut batch = context.batch();
+        assert_eq!(
+            batch
+                .binary_broadcast(
+                    BinaryOp::Mul,
+                    (&x, &Layout::contiguous(&[2, 3])),
+                    (&x, &Layout::contiguous(&[3, 2])),
+                    &mut z,
+                )
+                .err(),
+            Some(KernelError::Shape(IndexError::BroadcastMismatch {
+                lhs: vec![2, 3],
+                rhs: vec![3, 2]
+            }))
+        );
+    }
+}
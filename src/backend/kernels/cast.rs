@@ -0,0 +1,230 @@
+use crate::backend::{batch::CommandBatch, buffers::Buffer, device::Context, traits::BufferType};
+
+use super::{
+    kernel_type,
+    template::{emulation, Emulation},
+    BuiltinKernel, KernelError, RESULT_USAGE,
+};
+
+/// The bits each element of the float kernel type `ty` is stored in, with the
+/// WGSL expressions reading one to `f32` and writing one from it, see
+/// `cast.wgsl`. `None` for other types.
+pub(crate) fn float_lanes(ty: &str) -> Option<(u32, &'static str, &'static str)> {
+    match emulation(ty) {
+        Some(emulation @ Emulation::Half { .. }) => Some((
+            emulation.bits(),
+            emulation.unpack_expression(),
+            emulation.pack_expression(),
+        )),
+        Some(_) => None,
+        None if ty == "f32" => Some((32, "bitcast<f32>(word)", "bitcast<u32>(value)")),
+        None => None,
+    }
+}
+
+impl CommandBatch<'_> {
+    /// Records the conversion of `src` into `dst`, element-wise, between
+    /// `f32`, `f16` and `bf16`. Narrowing rounds to the nearest, ties to even.
+    pub fn cast<A: BufferType, B: BufferType>(
+        &mut self,
+        src: &Buffer<A>,
+        dst: &mut Buffer<B>,
+    ) -> Result<&mut Self, KernelError> {
+        let features = self.context().features();
+        let from = kernel_type::<A>(features)?;
+        let to = kernel_type::<B>(features)?;
+
+        let kernel = BuiltinKernel::Cast(to);
+        for ty in [from, to] {
+            if float_lanes(ty).is_none() {
+                return Err(KernelError::UnsupportedOp {
+                    op: kernel.name(),
+                    ty,
+                });
+            }
+        }
+
+        for usage in [src.usage(), dst.usage()] {
+            if !usage.contains(wgpu::BufferUsages::STORAGE) {
+                return Err(KernelError::InvalidBufferUsage(usage));
+            }
+        }
+
+        if src.len() != dst.len() {
+            return Err(KernelError::LengthMismatch {
+                op: kernel.name(),
+                expected: dst.len(),
+                found: src.len(),
+            });
+        }
+
+        if dst.is_empty() {
+            return Ok(self);
+        }
+
+        let geometry = Buffer::from_vec(
+            self.context(),
+            wgpu::BufferUsages::STORAGE,
+            vec![dst.len() as i32],
+        );
+
+        self.keep_alive(src).keep_alive(dst).keep_alive(&geometry);
+        let resources = vec![
+            src.get_resource(),
+            dst.get_resource(),
+            geometry.get_resource(),
+        ];
+
+        // NOTE: Each invocation writes one word of `dst`.
+        let words = dst.size().div_ceil(4) as usize;
+        self.dispatch_elementwise(kernel, from, resources, words)
+    }
+}
+
+impl<T: BufferType> Buffer<T> {
+    /// Converts the elements into a new buffer of `U`, see
+    /// [`CommandBatch::cast`].
+    pub fn cast<U: BufferType>(&self, context: &Context) -> Buffer<U> {
+        let cast_result = self.try_cast(context);
+
+        if let Err(e) = &cast_result {
+            log::error!("Failed at Buffer::cast: {}", e);
+        }
+
+        cast_result.unwrap()
+    }
+
+    pub fn try_cast<U: BufferType>(&self, context: &Context) -> Result<Buffer<U>, KernelError> {
+        let mut result = Buffer::with_len(context, RESULT_USAGE, self.len());
+
+        let mut batch = context.batch();
+        batch.cast(self, &mut result)?;
+        batch.submit();
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use half::{bf16, f16};
+
+    use crate::backend::{buffers::Buffer, device::test_context, kernels::RESULT_USAGE};
+
+    use super::KernelError;
+
+    #[test]
+    fn test_cast() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let values = vec![1.5f32, -2.25, 65504., 0.099975586, -0.0, 3.0e-5, 7.];
+        let x = Buffer::from_vec(&context, RESULT_USAGE, values.clone());
+
+        let halves = x.cast::<f16>(&context);
+        assert_eq!(
+            halves.read_to_vec(&context),
+            values.iter().map(|&v| f16::from_f32(v)).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            halves.cast::<f32>(&context).read_to_vec(&context),
+            values
+                .iter()
+                .map(|&v| f16::from_f32(v).to_f32())
+                .collect::<Vec<_>>()
+        );
+
+        // Ties round to even, like `bf16::from_f32`.
+        let values = vec![
+            1. + 2f32.powi(-8),
+            1. + 3. * 2f32.powi(-8),
+            1.234567,
+            -1e30,
+            0.5,
+        ];
+        let x = Buffer::from_vec(&context, RESULT_USAGE, values.clone());
+        let bfloats = x.cast::<bf16>(&context);
+        assert_eq!(
+            bfloats.read_to_vec(&context),
+            values
+                .iter()
+                .map(|&v| bf16::from_f32(v))
+                .collect::<Vec<_>>()
+        );
+
+        // NaNs stay NaNs with their sign and payload, and overflows become
+        // infinities of the same sign.
+        let values = vec![
+            f32::NAN,
+            -f32::NAN,
+            f32::from_bits(0x7fffffff),
+            f32::from_bits(0xffffffff),
+            f32::from_bits(0x7f800001),
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+            65519.,
+            65520.,
+            -1e10,
+            f32::MAX,
+        ];
+        let x = Buffer::from_vec(&context, RESULT_USAGE, values.clone());
+        assert_eq!(
+            x.cast::<f16>(&context)
+                .read_to_vec(&context)
+                .iter()
+                .map(|v| v.to_bits())
+                .collect::<Vec<_>>(),
+            values
+                .iter()
+                .map(|&v| f16::from_f32(v).to_bits())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            x.cast::<bf16>(&context)
+                .read_to_vec(&context)
+                .iter()
+                .map(|v| v.to_bits())
+                .collect::<Vec<_>>(),
+            values
+                .iter()
+                .map(|&v| bf16::from_f32(v).to_bits())
+                .collect::<Vec<_>>()
+        );
+
+        // Halves convert to each other through `f32`.
+        let y = Buffer::from_vec(&context, RESULT_USAGE, vec![bf16::from_f32(0.5); 3]);
+        assert_eq!(
+            y.cast::<f16>(&context).read_to_vec(&context),
+            vec![f16::from_f32(0.5); 3]
+        );
+    }
+
+    #[test]
+    fn test_cast_errors() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Buffer::from_vec(&context, RESULT_USAGE, vec![1i32, 2]);
+        assert!(matches!(
+            x.try_cast::<f32>(&context),
+            Err(KernelError::UnsupportedOp {
+                op: "cast",
+                ty: "i32"
+            })
+        ));
+
+        let y = Buffer::from_vec(&context, RESULT_USAGE, vec![1f32, 2., 3.]);
+        let mut z = Buffer::<f16>::with_len(&context, RESULT_USAGE, 2);
+        let mut batch = context.batch();
+        assert_eq!(
+            batch.cast(&y, &mut z).err(),
+            Some(KernelError::LengthMismatch {
+                op: "cast",
+                expected: 2,
+                found: 3
+            })
+        );
+    }
+}
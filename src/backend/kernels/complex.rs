@@ -0,0 +1,382 @@
+use num_complex::Complex;
+use paste::paste;
+
+use crate::backend::{batch::CommandBatch, buffers::Buffer, device::Context};
+
+use super::{kernel_type, Abs, BuiltinKernel, KernelError, RESULT_USAGE};
+
+/// Element-wise operations taking complex numbers to reals.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum ComplexPart {
+    Re,
+    Im,
+    /// The absolute value, computed without overflowing for large parts. It
+    /// is the absolute value of complex elements, see [`Abs`].
+    Norm,
+    /// The angle in `[-pi, pi]`, like `f32::atan2`, and zero for zero.
+    Arg,
+}
+
+impl ComplexPart {
+    pub const ALL: [ComplexPart; 4] = [
+        ComplexPart::Re,
+        ComplexPart::Im,
+        ComplexPart::Norm,
+        ComplexPart::Arg,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ComplexPart::Re => "re",
+            ComplexPart::Im => "im",
+            ComplexPart::Norm => "norm",
+            ComplexPart::Arg => "arg",
+        }
+    }
+
+    /// The WGSL expression computing the result from `a`.
+    pub(crate) fn expression(&self) -> &'static str {
+        match self {
+            ComplexPart::Re => "a.x",
+            ComplexPart::Im => "a.y",
+            ComplexPart::Norm => "cabs(a)",
+            // NOTE: `atan2` is undefined at the origin in WGSL.
+            ComplexPart::Arg => "select(atan2(a.y, a.x), 0.0, all(a == vec2(0.0)))",
+        }
+    }
+}
+
+impl CommandBatch<'_> {
+    /// Records `result = part(operand)`, element-wise.
+    pub fn complex_part(
+        &mut self,
+        part: ComplexPart,
+        operand: &Buffer<Complex<f32>>,
+        result: &mut Buffer<f32>,
+    ) -> Result<&mut Self, KernelError> {
+        let kernel = BuiltinKernel::ComplexPart(part);
+        let ty = kernel_type::<Complex<f32>>(self.context().features())?;
+
+        for usage in [operand.usage(), result.usage()] {
+            if !usage.contains(wgpu::BufferUsages::STORAGE) {
+                return Err(KernelError::InvalidBufferUsage(usage));
+            }
+        }
+
+        if operand.len() != result.len() {
+            return Err(KernelError::LengthMismatch {
+                op: part.name(),
+                expected: result.len(),
+                found: operand.len(),
+            });
+        }
+
+        if result.is_empty() {
+            return Ok(self);
+        }
+
+        self.keep_alive(operand).keep_alive(result);
+        let resources = vec![operand.get_resource(), result.get_resource()];
+        self.dispatch_elementwise(kernel, ty, resources, result.len())
+    }
+}
+
+impl Buffer<Complex<f32>> {
+    /// Computes `part` element-wise into a new buffer of reals.
+    pub fn complex_part(&self, context: &Context, part: ComplexPart) -> Buffer<f32> {
+        let part_result = self.try_complex_part(context, part);
+
+        if let Err(e) = &part_result {
+            log::error!("Failed at Buffer::{}: {}", part.name(), e);
+        }
+
+        part_result.unwrap()
+    }
+
+    pub fn try_complex_part(
+        &self,
+        context: &Context,
+        part: ComplexPart,
+    ) -> Result<Buffer<f32>, KernelError> {
+        let mut result = Buffer::with_len(context, RESULT_USAGE, self.len());
+
+        let mut batch = context.batch();
+        batch.complex_part(part, self, &mut result)?;
+        batch.submit();
+
+        Ok(result)
+    }
+}
+
+impl Abs for Complex<f32> {
+    type Output = f32;
+
+    fn record_abs(
+        batch: &mut CommandBatch<'_>,
+        operand: &Buffer<Self>,
+        result: &mut Buffer<f32>,
+    ) -> Result<(), KernelError> {
+        batch.complex_part(ComplexPart::Norm, operand, result)?;
+        Ok(())
+    }
+}
+
+macro_rules! complex_part_methods {
+    ($($name:ident => $part:ident),+ $(,)?) => {
+        paste! {
+            impl Buffer<Complex<f32>> {
+                $(
+                    #[doc = concat!("Computes `", stringify!($name), "` element-wise into a new buffer of reals.")]
+                    pub fn $name(&self, context: &Context) -> Buffer<f32> {
+                        self.complex_part(context, ComplexPart::$part)
+                    }
+
+                    pub fn [<try_ $name>](&self, context: &Context) -> Result<Buffer<f32>, KernelError> {
+                        self.try_complex_part(context, ComplexPart::$part)
+                    }
+                )+
+            }
+        }
+    };
+}
+
+complex_part_methods! {
+    re => Re,
+    im => Im,
+    norm => Norm,
+    arg => Arg,
+}
+
+#[cfg(test)]
+mod tests {
+    use num_complex::Complex;
+
+    use crate::{
+        backend::{
+            buffers::Buffer,
+            device::test_context,
+            kernels::{ArgReduceOp, BinaryOp, Gemm, ReduceOp, UnaryOp, RESULT_USAGE},
+        },
+        indexing::Layout,
+    };
+
+    use super::{ComplexPart, KernelError};
+
+    fn assert_close(lhs: &[Complex<f32>], rhs: &[Complex<f32>]) {
+        assert_eq!(lhs.len(), rhs.len());
+        for (l, r) in lhs.iter().zip(rhs) {
+            assert!((l - r).norm() <= 1e-4 * r.norm().max(1.), "{} != {}", l, r);
+        }
+    }
+
+    fn complex(values: &[(f32, f32)]) -> Vec<Complex<f32>> {
+        values
+            .iter()
+            .map(|&(re, im)| Complex::new(re, im))
+            .collect()
+    }
+
+    #[test]
+    fn test_complex_ops() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let a = complex(&[(1., 2.), (-3., 0.5), (0., -1.), (4., 4.), (1e30, 1e30)]);
+        let b = complex(&[(2., -1.), (0.25, 4.), (-1., 0.), (2., 2.), (1e30, -1e30)]);
+        let x = Buffer::from_vec(&context, RESULT_USAGE, a.clone());
+        let y = Buffer::from_vec(&context, RESULT_USAGE, b.clone());
+
+        // NOTE: Quotients are computed in f64, since those of `num_complex`
+        // overflow for the last elements, unlike the kernel.
+        for op in [BinaryOp::Add, BinaryOp::Sub, BinaryOp::Div] {
+            let f = |a: Complex<f32>, b: Complex<f32>| match op {
+                BinaryOp::Add => a + b,
+                BinaryOp::Sub => a - b,
+                _ => {
+                    let wide = |z: Complex<f32>| Complex::new(z.re as f64, z.im as f64);
+                    let quotient = wide(a) / wide(b);
+                    Complex::new(quotient.re as f32, quotient.im as f32)
+                }
+            };
+            let expected = a.iter().zip(&b).map(|(&a, &b)| f(a, b)).collect::<Vec<_>>();
+
+            assert_close(&x.binary(&context, op, &y).read_to_vec(&context), &expected);
+        }
+
+        // Products of the last elements overflow, so they are left out.
+        let products = a.iter().zip(&b).map(|(a, b)| a * b).collect::<Vec<_>>();
+        assert_close(
+            &x.mul(&context, &y).read_to_vec(&context)[..4],
+            &products[..4],
+        );
+
+        assert_close(
+            &x.neg(&context).read_to_vec(&context),
+            &a.iter().map(|a| -a).collect::<Vec<_>>(),
+        );
+        assert_eq!(
+            x.conj(&context).read_to_vec(&context),
+            a.iter().map(|a| a.conj()).collect::<Vec<_>>()
+        );
+
+        // Complex numbers are not ordered, and the `abs` unary kernel would
+        // keep them complex, so `abs` computes their norms instead.
+        assert_eq!(
+            x.try_max(&context, &y).err(),
+            Some(KernelError::UnsupportedOp {
+                op: "max",
+                ty: "complex<f32>"
+            })
+        );
+        assert!(matches!(
+            x.try_unary(&context, UnaryOp::Abs),
+            Err(KernelError::UnsupportedOp { op: "abs", .. })
+        ));
+        assert_eq!(
+            x.abs(&context).read_to_vec(&context),
+            x.norm(&context).read_to_vec(&context)
+        );
+    }
+
+    #[test]
+    fn test_complex_parts() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let a = complex(&[(3., 4.), (-1., 0.), (0., -2.), (0., 0.), (3e30, 4e30)]);
+        let x = Buffer::from_vec(&context, RESULT_USAGE, a.clone());
+
+        for part in ComplexPart::ALL {
+            let f = |a: &Complex<f32>| match part {
+                ComplexPart::Re => a.re,
+                ComplexPart::Im => a.im,
+                ComplexPart::Norm => a.norm(),
+                ComplexPart::Arg => a.arg(),
+            };
+            let result = x.complex_part(&context, part).read_to_vec(&context);
+
+            for (r, e) in result.iter().zip(a.iter().map(f)) {
+                assert!(
+                    (r - e).abs() <= 1e-5 * e.abs().max(1.),
+                    "{}: {} != {}",
+                    part.name(),
+                    r,
+                    e
+                );
+            }
+        }
+
+        assert_eq!(x.norm(&context).read_to_vec(&context)[..2], [5., 1.]);
+    }
+
+    #[test]
+    fn test_complex_reduce() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let data = complex(&[
+            (1., 1.),
+            (2., -1.),
+            (0., 3.),
+            (-1., 0.),
+            (0.5, 0.5),
+            (2., 0.),
+        ]);
+        let x = Buffer::from_vec(&context, RESULT_USAGE, data.clone());
+        let layout = Layout::contiguous(&[2, 3]);
+
+        let mut sums = Buffer::<Complex<f32>>::with_len(&context, RESULT_USAGE, 2);
+        let mut products = Buffer::<Complex<f32>>::with_len(&context, RESULT_USAGE, 2);
+        let mut means = Buffer::<Complex<f32>>::with_len(&context, RESULT_USAGE, 3);
+
+        let mut batch = context.batch();
+        batch
+            .reduce(ReduceOp::Sum, (&x, &layout), &[1], &mut sums)
+            .unwrap();
+        batch
+            .reduce(ReduceOp::Prod, (&x, &layout), &[1], &mut products)
+            .unwrap();
+        batch
+            .reduce(ReduceOp::Mean, (&x, &layout), &[0], &mut means)
+            .unwrap();
+        batch.submit();
+
+        let rows = [&data[..3], &data[3..]];
+        assert_close(
+            &sums.read_to_vec(&context),
+            &rows.map(|row| row.iter().sum()),
+        );
+        assert_close(
+            &products.read_to_vec(&context),
+            &rows.map(|row| row.iter().product()),
+        );
+        assert_close(
+            &means.read_to_vec(&context),
+            &(0..3)
+                .map(|i| (data[i] + data[i + 3]) / 2.)
+                .collect::<Vec<_>>(),
+        );
+
+        let mut argmax = Buffer::<u32>::with_len(&context, RESULT_USAGE, 2);
+        let mut batch = context.batch();
+        assert!(matches!(
+            batch.arg_reduce(ArgReduceOp::ArgMax, (&x, &layout), &[1], &mut argmax),
+            Err(KernelError::UnsupportedOp { op: "argmax", .. })
+        ));
+    }
+
+    #[test]
+    fn test_complex_gemm() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let (m, k, n) = (3, 5, 4);
+        let a = (0..m * k)
+            .map(|i| Complex::new(i as f32 * 0.5 - 2., (i % 3) as f32))
+            .collect::<Vec<_>>();
+        let b = (0..k * n)
+            .map(|i| Complex::new((i % 4) as f32, 1. - i as f32 * 0.25))
+            .collect::<Vec<_>>();
+        let c = (0..m * n)
+            .map(|i| Complex::new(1., i as f32))
+            .collect::<Vec<_>>();
+        let alpha = Complex::new(0.5, -1.);
+        let beta = Complex::new(0., 2.);
+
+        let x = Buffer::from_vec(&context, RESULT_USAGE, a.clone());
+        let y = Buffer::from_vec(&context, RESULT_USAGE, b.clone());
+        let mut z = Buffer::from_vec(&context, RESULT_USAGE, c.clone());
+
+        let gemm = Gemm::new(m, k, n).alpha(alpha).beta(beta);
+        let mut batch = context.batch();
+        batch.gemm(&gemm, &x, &y, &mut z).unwrap();
+        batch.submit();
+
+        let expected = (0..m * n)
+            .map(|index| {
+                let (row, col) = (index / n, index % n);
+                let dot = (0..k)
+                    .map(|p| a[row * k + p] * b[p * n + col])
+                    .sum::<Complex<f32>>();
+                alpha * dot + beta * c[index]
+            })
+            .collect::<Vec<_>>();
+        assert_close(&z.read_to_vec(&context), &expected);
+
+        // Without beta, C is only written.
+        assert_close(
+            &x.matmul(&context, &y, (m, k, n)).read_to_vec(&context),
+            &(0..m * n)
+                .map(|index| {
+                    (0..k)
+                        .map(|p| a[index / n * k + p] * b[p * n + index % n])
+                        .sum::<Complex<f32>>()
+                })
+                .collect::<Vec<_>>(),
+        );
+    }
+}
@@ -0,0 +1,515 @@
+use crate::backend::{batch::CommandBatch, buffers::Buffer, device::Context, traits::BufferType};
+
+use super::{
+    kernel_type,
+    template::{emulation, Emulation},
+    BuiltinKernel, KernelError, RESULT_USAGE,
+};
+
+/// Tile sizes tried, largest first, when none is requested.
+const TILE_SIZES: [u32; 6] = [32, 16, 8, 4, 2, 1];
+
+/// Describes a general matrix multiply `C = alpha * op(A) * op(B) + beta * C`,
+/// where `op(A)` is `m x k`, `op(B)` is `k x n` and every matrix is row-major.
+///
+/// With a batch size above one, each operand holds either `batch` matrices
+/// back to back, or a single matrix that is broadcast over the batch.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gemm<T: BufferType> {
+    m: usize,
+    n: usize,
+    k: usize,
+    batch: usize,
+    transpose_a: bool,
+    transpose_b: bool,
+    alpha: T,
+    beta: T,
+    tile: Option<u32>,
+}
+
+impl<T: BufferType> Gemm<T> {
+    /// Describes `C = A * B` for an `m x k` matrix A and a `k x n` matrix B.
+    pub fn new(m: usize, k: usize, n: usize) -> Self {
+        Gemm {
+            m,
+            n,
+            k,
+            batch: 1,
+            transpose_a: false,
+            transpose_b: false,
+            alpha: T::ONE,
+            beta: T::zeroed(),
+            tile: None,
+        }
+    }
+
+    pub fn batch(mut self, batch: usize) -> Self {
+        self.batch = batch;
+        self
+    }
+
+    /// Reads A as its transpose, i.e. as stored `k x m`.
+    pub fn transpose_a(mut self, transpose: bool) -> Self {
+        self.transpose_a = transpose;
+        self
+    }
+
+    /// Reads B as its transpose, i.e. as stored `n x k`.
+    pub fn transpose_b(mut self, transpose: bool) -> Self {
+        self.transpose_b = transpose;
+        self
+    }
+
+    pub fn alpha(mut self, alpha: T) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    pub fn beta(mut self, beta: T) -> Self {
+        self.beta = beta;
+        self
+    }
+
+    /// Overrides the tile size, which is otherwise the largest that the
+    /// limits of the [`Context`] allow.
+    pub fn tile(mut self, tile: u32) -> Self {
+        self.tile.replace(tile);
+        self
+    }
+
+    /// The stride between the matrices of `len` elements in an operand of
+    /// `found` elements, which is zero for broadcast operands.
+    fn stride(&self, operand: &'static str, len: usize, found: usize) -> Result<u32, KernelError> {
+        match found {
+            _ if found == len * self.batch => index(len),
+            _ if found == len => Ok(0),
+            _ => Err(KernelError::MatrixSize {
+                operand,
+                expected: len * self.batch,
+                found,
+            }),
+        }
+    }
+}
+
+/// `len` as an index of the GEMM kernel.
+fn index(len: usize) -> Result<u32, KernelError> {
+    u32::try_from(len).map_err(|_| KernelError::IndexOverflow { op: "gemm", len })
+}
+
+/// Whether a `tile x tile` workgroup and its two tiles of `T` fit `limits`.
+fn tile_fits<T>(tile: u32, limits: &wgpu::Limits) -> bool {
+    let tile_bytes = (tile * tile) as usize * size_of::<T>();
+
+    tile > 0
+        && tile <= limits.max_compute_workgroup_size_x
+        && tile <= limits.max_compute_workgroup_size_y
+        && tile * tile <= limits.max_compute_invocations_per_workgroup
+        && 2 * tile_bytes <= limits.max_compute_workgroup_storage_size as usize
+}
+
+/// The largest tile size that fits `limits`.
+pub(crate) fn default_tile<T>(limits: &wgpu::Limits) -> u32 {
+    TILE_SIZES
+        .into_iter()
+        .find(|&tile| tile_fits::<T>(tile, limits))
+        .unwrap_or(1)
+}
+
+impl CommandBatch<'_> {
+    /// Records the matrix multiply described by `gemm`, accumulating into `c`.
+    pub fn gemm<T: BufferType>(
+        &mut self,
+        gemm: &Gemm<T>,
+        a: &Buffer<T>,
+        b: &Buffer<T>,
+        c: &mut Buffer<T>,
+    ) -> Result<&mut Self, KernelError> {
+        let ty = kernel_type::<T>(self.context().features())?;
+        // NOTE: Of the emulated types, only complex numbers have a GEMM kernel.
+        if !matches!(emulation(ty), None | Some(Emulation::Complex)) {
+            return Err(KernelError::UnsupportedOp { op: "gemm", ty });
+        }
+
+        for buffer in [a, b, &*c] {
+            if !buffer.usage().contains(wgpu::BufferUsages::STORAGE) {
+                return Err(KernelError::InvalidBufferUsage(buffer.usage()));
+            }
+        }
+
+        let stride_a = gemm.stride("A", gemm.m * gemm.k, a.len())?;
+        let stride_b = gemm.stride("B", gemm.k * gemm.n, b.len())?;
+        if c.len() != gemm.m * gemm.n * gemm.batch {
+            return Err(KernelError::MatrixSize {
+                operand: "C",
+                expected: gemm.m * gemm.n * gemm.batch,
+                found: c.len(),
+            });
+        }
+
+        if c.is_empty() {
+            return Ok(self);
+        }
+
+        let context = self.context();
+
+        // NOTE: Empty buffers cannot be bound. With an empty inner dimension,
+        // A and B are never read and C becomes `beta * C`.
+        let scratch;
+        let (a, b) = if gemm.k == 0 {
+            scratch = Buffer::<T>::with_len(context, wgpu::BufferUsages::STORAGE, 1);
+            (&scratch, &scratch)
+        } else {
+            (a, b)
+        };
+
+        let limits = context.limits();
+        let tile = gemm.tile.unwrap_or_else(|| default_tile::<T>(&limits));
+        if !tile_fits::<T>(tile, &limits) {
+            return Err(KernelError::InvalidTileSize(tile));
+        }
+
+        let workgroups = (
+            gemm.n.div_ceil(tile as usize),
+            gemm.m.div_ceil(tile as usize),
+            gemm.batch,
+        );
+        let limit = limits.max_compute_workgroups_per_dimension as usize;
+        if workgroups.0 > limit || workgroups.1 > limit || workgroups.2 > limit {
+            return Err(KernelError::DispatchTooLarge {
+                op: "gemm",
+                workgroups,
+                limit: limit as u32,
+            });
+        }
+
+        let mut params = vec![
+            index(gemm.m)?,
+            index(gemm.n)?,
+            index(gemm.k)?,
+            gemm.transpose_a as u32,
+            gemm.transpose_b as u32,
+            stride_a,
+            stride_b,
+            index(gemm.m * gemm.n)?,
+        ];
+        // NOTE: The offsets of the last matrices of each operand must fit too.
+        for len in [a.len(), b.len(), c.len()] {
+            index(len)?;
+        }
+        // NOTE: Complex scalars take two words each.
+        params.extend_from_slice(bytemuck::cast_slice(&[gemm.alpha, gemm.beta]));
+        params.extend([0, 0]);
+        let params = Buffer::from_vec(context, wgpu::BufferUsages::UNIFORM, params);
+
+        let kernel = BuiltinKernel::Gemm { tile };
+        let pipeline = context.kernels().get(context, kernel, ty)?;
+        let bind_group = context
+            .device()
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(kernel.name()),
+                layout: &pipeline.bind_group_layouts()[0],
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: params.get_resource(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: a.get_resource(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: b.get_resource(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: c.get_resource(),
+                    },
+                ],
+            });
+
+        self.keep_alive(&params)
+            .keep_alive(a)
+            .keep_alive(b)
+            .keep_alive(c);
+        Ok(self.dispatch(
+            pipeline.pipeline(),
+            &[&bind_group],
+            (
+                workgroups.0 as u32,
+                workgroups.1 as u32,
+                workgroups.2 as u32,
+            ),
+        ))
+    }
+}
+
+impl<T: BufferType> Buffer<T> {
+    /// Multiplies the `m x k` matrix in this buffer by the `k x n` matrix in
+    /// `rhs`, into a new buffer.
+    pub fn matmul(
+        &self,
+        context: &Context,
+        rhs: &Buffer<T>,
+        (m, k, n): (usize, usize, usize),
+    ) -> Buffer<T> {
+        let matmul_result = self.try_matmul(context, rhs, (m, k, n));
+
+        if let Err(e) = &matmul_result {
+            log::error!("Failed at Buffer::matmul: {}", e);
+        }
+
+        matmul_result.unwrap()
+    }
+
+    pub fn try_matmul(
+        &self,
+        context: &Context,
+        rhs: &Buffer<T>,
+        (m, k, n): (usize, usize, usize),
+    ) -> Result<Buffer<T>, KernelError> {
+        self.try_gemm(context, rhs, &Gemm::new(m, k, n))
+    }
+
+    /// Multiplies `batch` pairs of matrices, where either side may also hold
+    /// a single matrix that is broadcast over the batch.
+    pub fn batched_matmul(
+        &self,
+        context: &Context,
+        rhs: &Buffer<T>,
+        batch: usize,
+        (m, k, n): (usize, usize, usize),
+    ) -> Buffer<T> {
+        let matmul_result = self.try_batched_matmul(context, rhs, batch, (m, k, n));
+
+        if let Err(e) = &matmul_result {
+            log::error!("Failed at Buffer::batched_matmul: {}", e);
+        }
+
+        matmul_result.unwrap()
+    }
+
+    pub fn try_batched_matmul(
+        &self,
+        context: &Context,
+        rhs: &Buffer<T>,
+        batch: usize,
+        (m, k, n): (usize, usize, usize),
+    ) -> Result<Buffer<T>, KernelError> {
+        self.try_gemm(context, rhs, &Gemm::new(m, k, n).batch(batch))
+    }
+
+    /// Computes `gemm` with this buffer as A and `rhs` as B into a new buffer.
+    /// Since the result starts zeroed, `beta` has no effect.
+    pub fn try_gemm(
+        &self,
+        context: &Context,
+        rhs: &Buffer<T>,
+        gemm: &Gemm<T>,
+    ) -> Result<Buffer<T>, KernelError> {
+        let mut result = Buffer::with_len(context, RESULT_USAGE, gemm.m * gemm.n * gemm.batch);
+
+        let mut batch = context.batch();
+        batch.gemm(gemm, self, rhs, &mut result)?;
+        batch.submit();
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::{buffers::Buffer, device::test_context, kernels::RESULT_USAGE};
+
+    use super::{default_tile, index, Gemm, KernelError};
+
+    /// Computes `alpha * op(A) * op(B) + beta * C` on the host.
+    fn reference(gemm: &Gemm<f32>, a: &[f32], b: &[f32], c: &[f32]) -> Vec<f32> {
+        let Gemm { m, n, k, .. } = *gemm;
+        let mut out = c.to_vec();
+
+        for batch in 0..gemm.batch {
+            let a = &a[(batch * m * k) % a.len()..];
+            let b = &b[(batch * k * n) % b.len()..];
+            for i in 0..m {
+                for j in 0..n {
+                    let acc = (0..k)
+                        .map(|p| {
+                            let x = if gemm.transpose_a {
+                                a[p * m + i]
+                            } else {
+                                a[i * k + p]
+                            };
+                            let y = if gemm.transpose_b {
+                                b[j * k + p]
+                            } else {
+                                b[p * n + j]
+                            };
+                            x * y
+                        })
+                        .sum::<f32>();
+                    let index = batch * m * n + i * n + j;
+                    out[index] = gemm.alpha * acc + gemm.beta * c[index];
+                }
+            }
+        }
+
+        out
+    }
+
+    fn assert_close(lhs: &[f32], rhs: &[f32]) {
+        assert_eq!(lhs.len(), rhs.len());
+        for (a, b) in lhs.iter().zip(rhs) {
+            assert!((a - b).abs() <= 1e-3 * b.abs().max(1.), "{} != {}", a, b);
+        }
+    }
+
+    fn values(len: usize, seed: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| ((i * 7 + seed) % 13) as f32 - 6.)
+            .collect()
+    }
+
+    #[test]
+    fn test_default_tile() {
+        assert_eq!(default_tile::<f32>(&wgpu::Limits::default()), 16);
+        assert_eq!(default_tile::<f32>(&wgpu::Limits::downlevel_defaults()), 16);
+
+        let limits = wgpu::Limits {
+            max_compute_invocations_per_workgroup: 1024,
+            max_compute_workgroup_size_x: 1024,
+            max_compute_workgroup_size_y: 1024,
+            ..Default::default()
+        };
+        assert_eq!(default_tile::<f32>(&limits), 32);
+    }
+
+    #[test]
+    fn test_matmul() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let a = Buffer::from_vec(&context, RESULT_USAGE, vec![1f32, 2., 3., 4., 5., 6.]);
+        let b = Buffer::from_vec(&context, RESULT_USAGE, vec![7f32, 8., 9., 10., 11., 12.]);
+
+        assert_eq!(
+            a.matmul(&context, &b, (2, 3, 2)).read_to_vec(&context),
+            vec![58., 64., 139., 154.]
+        );
+
+        let x = Buffer::from_vec(&context, RESULT_USAGE, vec![1i32, -2, 3, 4]);
+        assert_eq!(
+            x.matmul(&context, &x, (2, 2, 2)).read_to_vec(&context),
+            vec![-5, -10, 15, 10]
+        );
+    }
+
+    #[test]
+    fn test_gemm_tiles() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        // Sizes that are not multiples of any tile exercise the edges.
+        let (m, k, n) = (37, 29, 21);
+        let a_vec = values(m * k, 1);
+        let b_vec = values(k * n, 2);
+        let c_vec = values(m * n, 3);
+        let a = Buffer::from_vec(&context, RESULT_USAGE, a_vec.clone());
+        let b = Buffer::from_vec(&context, RESULT_USAGE, b_vec.clone());
+
+        for (tile, transpose_a, transpose_b) in [
+            (16, false, false),
+            (8, true, false),
+            (4, false, true),
+            (1, true, true),
+        ] {
+            let gemm = Gemm::new(m, k, n)
+                .transpose_a(transpose_a)
+                .transpose_b(transpose_b)
+                .alpha(0.5)
+                .beta(-2.)
+                .tile(tile);
+
+            let mut c = Buffer::from_vec(&context, RESULT_USAGE, c_vec.clone());
+            let mut batch = context.batch();
+            batch.gemm(&gemm, &a, &b, &mut c).unwrap();
+            batch.submit();
+
+            assert_close(
+                &c.read_to_vec(&context),
+                &reference(&gemm, &a_vec, &b_vec, &c_vec),
+            );
+        }
+    }
+
+    #[test]
+    fn test_batched_matmul() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let (batch, m, k, n) = (3, 5, 4, 6);
+        let a_vec = values(batch * m * k, 4);
+        let b_vec = values(k * n, 5);
+        let a = Buffer::from_vec(&context, RESULT_USAGE, a_vec.clone());
+        let b = Buffer::from_vec(&context, RESULT_USAGE, b_vec.clone());
+
+        let gemm = Gemm::new(m, k, n).batch(batch);
+        assert_close(
+            &a.batched_matmul(&context, &b, batch, (m, k, n))
+                .read_to_vec(&context),
+            &reference(&gemm, &a_vec, &b_vec, &vec![0.; batch * m * n]),
+        );
+    }
+
+    #[test]
+    fn test_invalid_gemm() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let a = Buffer::from_vec(&context, RESULT_USAGE, vec![1f32; 6]);
+        assert_eq!(
+            a.try_matmul(&context, &a, (2, 3, 3)).err(),
+            Some(KernelError::MatrixSize {
+                operand: "B",
+                expected: 9,
+                found: 6
+            })
+        );
+        assert_eq!(
+            a.try_gemm(&context, &a, &Gemm::new(2, 3, 2).tile(4096))
+                .err(),
+            Some(KernelError::InvalidTileSize(4096))
+        );
+
+        let empty = Buffer::<f32>::with_len(&context, RESULT_USAGE, 0);
+        assert_eq!(
+            empty
+                .try_matmul(&context, &empty, (6, 0, 1))
+                .unwrap()
+                .read_to_vec(&context),
+            vec![0.; 6]
+        );
+
+        // With an empty inner dimension, C is only scaled by beta.
+        let mut c = Buffer::from_vec(&context, RESULT_USAGE, vec![1f32, -2., 3., 4.]);
+        let mut batch = context.batch();
+        batch
+            .gemm(&Gemm::new(2, 0, 2).beta(2.), &empty, &empty, &mut c)
+            .unwrap();
+        batch.submit();
+        assert_eq!(c.read_to_vec(&context), vec![2., -4., 6., 8.]);
+
+        assert_eq!(
+            index(usize::MAX).err(),
+            Some(KernelError::IndexOverflow {
+                op: "gemm",
+                len: usize::MAX
+            })
+        );
+    }
+}
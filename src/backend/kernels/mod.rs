@@ -0,0 +1,820 @@
+use std::collections::HashMap;
+
+use parking_lot::Mutex;
+use paste::paste;
+
+use super::{
+    batch::CommandBatch,
+    buffers::{Buffer, BufferWriteError, RESULT_USAGE},
+    device::Context,
+    pipeline::ComputePipeline,
+    traits::BufferType,
+};
+use crate::indexing::IndexError;
+
+mod broadcast;
+mod cast;
+mod complex;
+mod gemm;
+mod ops;
+mod packed;
+mod reduce;
+mod scan;
+mod sort;
+mod template;
+
+pub(crate) use self::reduce::reduced_shape;
+use self::template::{emulation, kernel_type, with_element_arrays, ElementArray, Emulation};
+use self::{
+    cast::float_lanes,
+    reduce::{Partials, Reduction},
+    sort::radix_key,
+};
+pub use self::{
+    complex::ComplexPart,
+    gemm::Gemm,
+    ops::{BinaryOp, UnaryOp},
+    reduce::{ArgReduceOp, ReduceOp},
+    scan::ScanOp,
+    template::ShaderTemplate,
+};
+
+const UNARY_TEMPLATE: &str = include_str!("../../shaders/unary.wgsl");
+const BINARY_TEMPLATE: &str = include_str!("../../shaders/binary.wgsl");
+const BROADCAST_TEMPLATE: &str = include_str!("../../shaders/broadcast.wgsl");
+const GEMM_TEMPLATE: &str = include_str!("../../shaders/gemm.wgsl");
+const REDUCE_TEMPLATE: &str = include_str!("../../shaders/reduce.wgsl");
+const SCAN_TEMPLATE: &str = include_str!("../../shaders/scan.wgsl");
+const SCAN_ADD_TEMPLATE: &str = include_str!("../../shaders/scan_add.wgsl");
+const RADIX_COUNT_TEMPLATE: &str = include_str!("../../shaders/radix_count.wgsl");
+const RADIX_SCATTER_TEMPLATE: &str = include_str!("../../shaders/radix_scatter.wgsl");
+const COPY_PACKED_TEMPLATE: &str = include_str!("../../shaders/copy_packed.wgsl");
+const CAST_TEMPLATE: &str = include_str!("../../shaders/cast.wgsl");
+const COMPLEX_PART_TEMPLATE: &str = include_str!("../../shaders/complex_part.wgsl");
+const WIDE_PRELUDE: &str = include_str!("../../shaders/wide.wgsl");
+const COMPLEX_PRELUDE: &str = include_str!("../../shaders/complex.wgsl");
+const HALF_PRELUDE: &str = include_str!("../../shaders/half.wgsl");
+
+/// The workgroup size the templates are instantiated with.
+const WORKGROUP_SIZE: u32 = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum KernelError {
+    #[error("WGSL cannot represent elements of type {0} natively.")]
+    UnsupportedType(&'static str),
+
+    #[error("Elements of WGSL type {ty} need the {feature:?} device feature.")]
+    MissingFeature {
+        ty: &'static str,
+        feature: wgpu::Features,
+    },
+
+    #[error("The {op} kernel is not defined for elements of type {ty}.")]
+    UnsupportedOp { op: &'static str, ty: &'static str },
+
+    #[error("Invalid Buffer Usage: {0:?}, kernel operands must be STORAGE buffers.")]
+    InvalidBufferUsage(wgpu::BufferUsages),
+
+    #[error("Operands of {op} must hold {expected} elements, but one holds {found}.")]
+    LengthMismatch {
+        op: &'static str,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("Operand {operand} must hold {expected} elements, but holds {found}.")]
+    MatrixSize {
+        operand: &'static str,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("Invalid operand shapes: {0}")]
+    Shape(#[from] IndexError),
+
+    #[error("Failed to write results: {0}")]
+    Write(#[from] BufferWriteError),
+
+    #[error("Failed to compile the {op} kernel for {ty}: {message}")]
+    Compilation {
+        op: &'static str,
+        ty: &'static str,
+        message: String,
+    },
+
+    #[error("The {0} kernel cannot be run on empty operands.")]
+    EmptyOperand(&'static str),
+
+    #[error("The {op} kernel indexes with 32-bit integers, which cannot hold {len}.")]
+    IndexOverflow { op: &'static str, len: usize },
+
+    #[error("A tile size of {0} exceeds the compute limits of the device.")]
+    InvalidTileSize(u32),
+
+    #[error("A workgroup size of {0:?} exceeds the compute limits of the device.")]
+    InvalidWorkgroupSize((u32, u32, u32)),
+
+    #[error("The {op} kernel needs {workgroups:?} workgroups, but at most {limit} are allowed per dimension.")]
+    DispatchTooLarge {
+        op: &'static str,
+        workgroups: (usize, usize, usize),
+        limit: u32,
+    },
+}
+
+/// The kernels the crate instantiates per element type, as opposed to the
+/// user-supplied [`Kernel`](crate::Kernel)s.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+enum BuiltinKernel {
+    Unary(UnaryOp),
+    Binary(BinaryOp),
+    Broadcast(BinaryOp),
+    Gemm {
+        tile: u32,
+    },
+    Reduce(Reduction, Partials),
+    Scan(ScanOp),
+    ScanAdd(ScanOp),
+    RadixCount,
+    RadixScatter,
+    CopyPacked,
+    /// Converts elements of the kernel type to the float type it holds.
+    Cast(&'static str),
+    ComplexPart(ComplexPart),
+}
+
+impl BuiltinKernel {
+    fn name(&self) -> &'static str {
+        match self {
+            BuiltinKernel::Unary(op) => op.name(),
+            BuiltinKernel::Binary(op) | BuiltinKernel::Broadcast(op) => op.name(),
+            BuiltinKernel::Gemm { .. } => "gemm",
+            BuiltinKernel::Reduce(reduction, _) => reduction.name(),
+            BuiltinKernel::Scan(op) | BuiltinKernel::ScanAdd(op) => op.name(),
+            BuiltinKernel::RadixCount => "radix_count",
+            BuiltinKernel::RadixScatter => "radix_scatter",
+            BuiltinKernel::CopyPacked => "copy",
+            BuiltinKernel::Cast(_) => "cast",
+            BuiltinKernel::ComplexPart(part) => part.name(),
+        }
+    }
+
+    fn supports(&self, ty: &str) -> bool {
+        match self {
+            BuiltinKernel::Unary(op) => op.supports(ty),
+            BuiltinKernel::Binary(op) | BuiltinKernel::Broadcast(op) => op.supports(ty),
+            BuiltinKernel::RadixCount | BuiltinKernel::RadixScatter => radix_key(ty).is_some(),
+            BuiltinKernel::Gemm { .. } => matches!(emulation(ty), None | Some(Emulation::Complex)),
+            BuiltinKernel::Scan(_) | BuiltinKernel::ScanAdd(_) => emulation(ty).is_none(),
+            BuiltinKernel::Reduce(reduction, _) => reduction.supports(ty),
+            BuiltinKernel::CopyPacked => true,
+            BuiltinKernel::ComplexPart(_) => emulation(ty) == Some(Emulation::Complex),
+            BuiltinKernel::Cast(to) => float_lanes(ty).is_some() && float_lanes(to).is_some(),
+        }
+    }
+
+    /// The template of the kernel, with the placeholders specific to the
+    /// kernel replaced for elements of type `ty`.
+    fn template(&self, ty: &str) -> ShaderTemplate {
+        let source = match self {
+            BuiltinKernel::Unary(op) => with_element_arrays(
+                &UNARY_TEMPLATE.replace("{{EXPR}}", op.expression(ty)),
+                ty,
+                &[ElementArray::read("operand"), ElementArray::write("result")],
+            ),
+            BuiltinKernel::Binary(op) | BuiltinKernel::Broadcast(op) => {
+                let template = match self {
+                    BuiltinKernel::Binary(_) => BINARY_TEMPLATE,
+                    _ => BROADCAST_TEMPLATE,
+                };
+                with_element_arrays(
+                    &template.replace("{{EXPR}}", op.expression(ty)),
+                    ty,
+                    &[
+                        ElementArray::read("lhs"),
+                        ElementArray::read("rhs"),
+                        ElementArray::write("result"),
+                    ],
+                )
+            }
+            BuiltinKernel::Gemm { tile } => GEMM_TEMPLATE
+                .replace("{{TILE_AREA}}", &(tile * tile).to_string())
+                .replace("{{TILE}}", &tile.to_string())
+                .replace("{{MUL}}", BinaryOp::Mul.expression(ty)),
+            BuiltinKernel::Reduce(reduction, partials) => {
+                let (input, output) = (ElementArray::read("input"), ElementArray::write("output"));
+                let arrays = match partials {
+                    Partials::Neither => [input, output],
+                    Partials::Output => [input, output.unpacked()],
+                    Partials::Input => [input.unpacked(), output],
+                };
+
+                let template = REDUCE_TEMPLATE
+                    .replace("{{IDENTITY}}", reduction.identity(ty))
+                    .replace("{{COMBINE}}", reduction.combine(ty))
+                    .replace("{{MEAN}}", reduction.mean(ty));
+                with_element_arrays(&template, ty, &arrays)
+            }
+            BuiltinKernel::Scan(op) | BuiltinKernel::ScanAdd(op) => {
+                let template = match self {
+                    BuiltinKernel::Scan(_) => SCAN_TEMPLATE,
+                    _ => SCAN_ADD_TEMPLATE,
+                };
+                template
+                    .replace("{{BLOCK}}", &(2 * WORKGROUP_SIZE).to_string())
+                    .replace("{{IDENTITY}}", op.identity())
+                    .replace("{{OP}}", op.expression())
+            }
+            BuiltinKernel::RadixCount | BuiltinKernel::RadixScatter => {
+                let template = match self {
+                    BuiltinKernel::RadixCount => RADIX_COUNT_TEMPLATE,
+                    _ => RADIX_SCATTER_TEMPLATE,
+                };
+                template.replace("{{KEY}}", radix_key(ty).unwrap_or("b"))
+            }
+            BuiltinKernel::CopyPacked => {
+                // NOTE: Other types are copied as whole words.
+                let bits = match emulation(ty) {
+                    Some(Emulation::Packed { bits, .. }) => bits,
+                    _ => 32,
+                };
+                COPY_PACKED_TEMPLATE.replace("{{BITS}}", &bits.to_string())
+            }
+            BuiltinKernel::Cast(to) => {
+                let (src_bits, read, _) = float_lanes(ty).expect("casts are between floats");
+                let (dst_bits, _, write) = float_lanes(to).expect("casts are between floats");
+                CAST_TEMPLATE
+                    .replace("{{SRC_BITS}}", &src_bits.to_string())
+                    .replace("{{DST_BITS}}", &dst_bits.to_string())
+                    .replace("{{READ}}", read)
+                    .replace("{{WRITE}}", write)
+            }
+            BuiltinKernel::ComplexPart(part) => {
+                COMPLEX_PART_TEMPLATE.replace("{{EXPR}}", part.expression())
+            }
+        };
+
+        ShaderTemplate::new(source)
+    }
+
+    /// Instantiates the template of the kernel for elements of type `ty`.
+    /// Packed integers and halves are read and written in place but computed
+    /// on as 32-bit scalars, the latter rounded with the half prelude, 64-bit
+    /// integers with the arithmetic of the wide prelude and complex numbers
+    /// with that of the complex prelude.
+    fn source(&self, ty: &str) -> String {
+        let template = self.template(ty);
+        let workgroup_size = (WORKGROUP_SIZE, 1, 1);
+
+        match emulation(ty) {
+            Some(emulation @ Emulation::Packed { .. }) => {
+                template.instantiate_as(emulation.unpacked_type(), workgroup_size)
+            }
+            Some(Emulation::Half { .. }) => format!(
+                "{}\n{}",
+                HALF_PRELUDE,
+                template.instantiate_as("f32", workgroup_size)
+            ),
+            Some(Emulation::Wide { signed }) => format!(
+                "{}\n{}",
+                WIDE_PRELUDE.replace("{{SIGNED}}", &signed.to_string()),
+                template.instantiate_as("vec2<u32>", workgroup_size)
+            ),
+            Some(Emulation::Complex) => format!(
+                "{}\n{}",
+                COMPLEX_PRELUDE,
+                template.instantiate_as("vec2<f32>", workgroup_size)
+            ),
+            // NOTE: Casts from `f32` still narrow to halves.
+            None => match self {
+                BuiltinKernel::Cast(to)
+                    if matches!(emulation(to), Some(Emulation::Half { .. })) =>
+                {
+                    format!(
+                        "{}\n{}",
+                        HALF_PRELUDE,
+                        template.instantiate_as(ty, workgroup_size)
+                    )
+                }
+                _ => template.instantiate_as(ty, workgroup_size),
+            },
+        }
+    }
+}
+
+/// Compiled kernel pipelines of a [`Context`], each compiled on first use.
+///
+/// Pipelines are built through the pipeline cache of the context, so this
+/// only saves instantiating the template of a kernel on every dispatch.
+#[derive(Debug, Default)]
+pub(crate) struct KernelCache {
+    pipelines: Mutex<HashMap<(BuiltinKernel, &'static str), ComputePipeline>>,
+}
+
+impl KernelCache {
+    fn get(
+        &self,
+        context: &Context,
+        kernel: BuiltinKernel,
+        ty: &'static str,
+    ) -> Result<ComputePipeline, KernelError> {
+        if let Some(pipeline) = self.pipelines.lock().get(&(kernel, ty)) {
+            return Ok(pipeline.clone());
+        }
+
+        log::debug!("Compiling {} kernel for {}", kernel.name(), ty);
+        let pipeline = ComputePipeline::builder(kernel.source(ty), "main")
+            .label(kernel.name())
+            .try_build(context)
+            .map_err(|e| KernelError::Compilation {
+                op: kernel.name(),
+                ty,
+                message: e.to_string(),
+            })?;
+
+        self.pipelines.lock().insert((kernel, ty), pipeline.clone());
+        Ok(pipeline)
+    }
+
+    /// The number of pipelines compiled so far.
+    pub(crate) fn len(&self) -> usize {
+        self.pipelines.lock().len()
+    }
+}
+
+/// Spreads the workgroups needed for `len` elements over the x and y
+/// dimensions, so that no dimension exceeds `max_per_dimension`.
+fn workgroups(len: usize, max_per_dimension: u32) -> (u32, u32, u32) {
+    let count = (len as u64).div_ceil(WORKGROUP_SIZE as u64);
+    let x = count.min(max_per_dimension as u64);
+
+    (x as u32, count.div_ceil(x.max(1)) as u32, 1)
+}
+
+impl CommandBatch<'_> {
+    /// Records `result = op(operand)`, element-wise.
+    pub fn unary<T: BufferType>(
+        &mut self,
+        op: UnaryOp,
+        operand: &Buffer<T>,
+        result: &mut Buffer<T>,
+    ) -> Result<&mut Self, KernelError> {
+        self.record_kernel(BuiltinKernel::Unary(op), &[operand], result)
+    }
+
+    /// Records `result = op(lhs, rhs)`, element-wise.
+    pub fn binary<T: BufferType>(
+        &mut self,
+        op: BinaryOp,
+        lhs: &Buffer<T>,
+        rhs: &Buffer<T>,
+        result: &mut Buffer<T>,
+    ) -> Result<&mut Self, KernelError> {
+        self.record_kernel(BuiltinKernel::Binary(op), &[lhs, rhs], result)
+    }
+
+    fn record_kernel<T: BufferType>(
+        &mut self,
+        kernel: BuiltinKernel,
+        operands: &[&Buffer<T>],
+        result: &Buffer<T>,
+    ) -> Result<&mut Self, KernelError> {
+        let ty = kernel_type::<T>(self.context().features())?;
+        if !kernel.supports(ty) {
+            return Err(KernelError::UnsupportedOp {
+                op: kernel.name(),
+                ty,
+            });
+        }
+
+        for buffer in operands.iter().copied().chain([result]) {
+            if !buffer.usage().contains(wgpu::BufferUsages::STORAGE) {
+                return Err(KernelError::InvalidBufferUsage(buffer.usage()));
+            }
+
+            if buffer.len() != result.len() {
+                return Err(KernelError::LengthMismatch {
+                    op: kernel.name(),
+                    expected: result.len(),
+                    found: buffer.len(),
+                });
+            }
+        }
+
+        // NOTE: Empty buffers cannot be bound, and there is nothing to compute.
+        if result.is_empty() {
+            return Ok(self);
+        }
+
+        for buffer in operands.iter().copied().chain([result]) {
+            self.keep_alive(buffer);
+        }
+
+        let resources = operands
+            .iter()
+            .copied()
+            .chain([result])
+            .map(Buffer::get_resource)
+            .collect();
+        self.dispatch_elementwise(kernel, ty, resources, result.len())
+    }
+
+    /// Binds `resources` in order and dispatches at least `len` invocations,
+    /// which is one per element for element-wise kernels.
+    fn dispatch_elementwise(
+        &mut self,
+        kernel: BuiltinKernel,
+        ty: &'static str,
+        resources: Vec<wgpu::BindingResource>,
+        len: usize,
+    ) -> Result<&mut Self, KernelError> {
+        let context = self.context();
+        let pipeline = context.kernels().get(context, kernel, ty)?;
+        let entries = resources
+            .into_iter()
+            .enumerate()
+            .map(|(binding, resource)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource,
+            })
+            .collect::<Vec<_>>();
+        let bind_group = context
+            .device()
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(kernel.name()),
+                layout: &pipeline.bind_group_layouts()[0],
+                entries: &entries,
+            });
+
+        let max_per_dimension = context.limits().max_compute_workgroups_per_dimension;
+        Ok(self.dispatch(
+            pipeline.pipeline(),
+            &[&bind_group],
+            workgroups(len, max_per_dimension),
+        ))
+    }
+}
+
+impl<T: BufferType> Buffer<T> {
+    /// Computes `op(self)` element-wise into a new buffer.
+    pub fn unary(&self, context: &Context, op: UnaryOp) -> Buffer<T> {
+        let unary_result = self.try_unary(context, op);
+
+        if let Err(e) = &unary_result {
+            log::error!("Failed at Buffer::{}: {}", op.name(), e);
+        }
+
+        unary_result.unwrap()
+    }
+
+    pub fn try_unary(&self, context: &Context, op: UnaryOp) -> Result<Buffer<T>, KernelError> {
+        let mut result = Buffer::with_len(context, RESULT_USAGE, self.len());
+
+        let mut batch = context.batch();
+        batch.unary(op, self, &mut result)?;
+        batch.submit();
+
+        Ok(result)
+    }
+
+    /// Computes `op(self, rhs)` element-wise into a new buffer.
+    pub fn binary(&self, context: &Context, op: BinaryOp, rhs: &Buffer<T>) -> Buffer<T> {
+        let binary_result = self.try_binary(context, op, rhs);
+
+        if let Err(e) = &binary_result {
+            log::error!("Failed at Buffer::{}: {}", op.name(), e);
+        }
+
+        binary_result.unwrap()
+    }
+
+    pub fn try_binary(
+        &self,
+        context: &Context,
+        op: BinaryOp,
+        rhs: &Buffer<T>,
+    ) -> Result<Buffer<T>, KernelError> {
+        let mut result = Buffer::with_len(context, RESULT_USAGE, self.len());
+
+        let mut batch = context.batch();
+        batch.binary(op, self, rhs, &mut result)?;
+        batch.submit();
+
+        Ok(result)
+    }
+}
+
+macro_rules! unary_methods {
+    ($($name:ident => $op:ident),+ $(,)?) => {
+        paste! {
+            impl<T: BufferType> Buffer<T> {
+                $(
+                    #[doc = concat!("Computes `", stringify!($name), "` element-wise into a new buffer.")]
+                    pub fn $name(&self, context: &Context) -> Buffer<T> {
+                        self.unary(context, UnaryOp::$op)
+                    }
+
+                    pub fn [<try_ $name>](&self, context: &Context) -> Result<Buffer<T>, KernelError> {
+                        self.try_unary(context, UnaryOp::$op)
+                    }
+                )+
+            }
+        }
+    };
+}
+
+macro_rules! binary_methods {
+    ($($name:ident => $op:ident),+ $(,)?) => {
+        paste! {
+            impl<T: BufferType> Buffer<T> {
+                $(
+                    #[doc = concat!("Computes `", stringify!($name), "` element-wise into a new buffer.")]
+                    pub fn $name(&self, context: &Context, rhs: &Buffer<T>) -> Buffer<T> {
+                        self.binary(context, BinaryOp::$op, rhs)
+                    }
+
+                    pub fn [<try_ $name>](
+                        &self,
+                        context: &Context,
+                        rhs: &Buffer<T>,
+                    ) -> Result<Buffer<T>, KernelError> {
+                        self.try_binary(context, BinaryOp::$op, rhs)
+                    }
+                )+
+            }
+        }
+    };
+}
+
+unary_methods! {
+    neg => Neg,
+    conj => Conj,
+    exp => Exp,
+    log => Log,
+    sqrt => Sqrt,
+    sin => Sin,
+    cos => Cos,
+    tanh => Tanh,
+    sigmoid => Sigmoid,
+}
+
+/// Element types with an absolute value. Reals keep their type, while complex
+/// numbers have real absolute values, computed by [`ComplexPart::Norm`].
+pub trait Abs: BufferType {
+    type Output: BufferType;
+
+    /// Records `result = |operand|`, element-wise.
+    fn record_abs(
+        batch: &mut CommandBatch<'_>,
+        operand: &Buffer<Self>,
+        result: &mut Buffer<Self::Output>,
+    ) -> Result<(), KernelError>;
+}
+
+macro_rules! real_abs {
+    ($($ty:ty),+ $(,)?) => {
+        $(
+            impl Abs for $ty {
+                type Output = $ty;
+
+                fn record_abs(
+                    batch: &mut CommandBatch<'_>,
+                    operand: &Buffer<Self>,
+                    result: &mut Buffer<Self>,
+                ) -> Result<(), KernelError> {
+                    batch.unary(UnaryOp::Abs, operand, result)?;
+                    Ok(())
+                }
+            }
+        )+
+    };
+}
+
+real_abs!(
+    u8,
+    u16,
+    u32,
+    u64,
+    i8,
+    i16,
+    i32,
+    i64,
+    f32,
+    f64,
+    half::f16,
+    half::bf16
+);
+
+impl CommandBatch<'_> {
+    /// Records `result = |operand|`, element-wise, see [`Abs`].
+    pub fn abs<T: Abs>(
+        &mut self,
+        operand: &Buffer<T>,
+        result: &mut Buffer<T::Output>,
+    ) -> Result<&mut Self, KernelError> {
+        T::record_abs(self, operand, result)?;
+        Ok(self)
+    }
+}
+
+impl<T: Abs> Buffer<T> {
+    /// Computes `abs` element-wise into a new buffer, of reals for complex
+    /// elements.
+    pub fn abs(&self, context: &Context) -> Buffer<T::Output> {
+        let abs_result = self.try_abs(context);
+
+        if let Err(e) = &abs_result {
+            log::error!("Failed at Buffer::abs: {}", e);
+        }
+
+        abs_result.unwrap()
+    }
+
+    pub fn try_abs(&self, context: &Context) -> Result<Buffer<T::Output>, KernelError> {
+        let mut result = Buffer::with_len(context, RESULT_USAGE, self.len());
+
+        let mut batch = context.batch();
+        batch.abs(self, &mut result)?;
+        batch.submit();
+
+        Ok(result)
+    }
+}
+
+binary_methods! {
+    add => Add,
+    sub => Sub,
+    mul => Mul,
+    div => Div,
+    pow => Pow,
+    min => Min,
+    max => Max,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::{buffers::Buffer, device::test_context};
+
+    use super::{workgroups, BinaryOp, KernelError, UnaryOp, RESULT_USAGE};
+
+    fn assert_close(lhs: &[f32], rhs: &[f32]) {
+        assert_eq!(lhs.len(), rhs.len());
+        for (a, b) in lhs.iter().zip(rhs) {
+            assert!((a - b).abs() <= 1e-4 * b.abs().max(1.), "{} != {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_workgroups() {
+        assert_eq!(workgroups(0, 65535), (0, 0, 1));
+        assert_eq!(workgroups(1, 65535), (1, 1, 1));
+        assert_eq!(workgroups(65, 65535), (2, 1, 1));
+        assert_eq!(workgroups(64 * 10, 4), (4, 3, 1));
+    }
+
+    #[test]
+    fn test_binary_ops() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let a = vec![1.5f32, -2., 3., 0.25, 8.];
+        let b = vec![2f32, 4., 0.5, 3., 2.];
+        let x = Buffer::from_vec(&context, RESULT_USAGE, a.clone());
+        let x_abs = x.abs(&context);
+        let y = Buffer::from_vec(&context, RESULT_USAGE, b.clone());
+
+        for op in BinaryOp::ALL {
+            let f = |a: f32, b: f32| match op {
+                BinaryOp::Add => a + b,
+                BinaryOp::Sub => a - b,
+                BinaryOp::Mul => a * b,
+                BinaryOp::Div => a / b,
+                BinaryOp::Pow => a.abs().powf(b),
+                BinaryOp::Min => a.min(b),
+                BinaryOp::Max => a.max(b),
+            };
+            // NOTE: `pow` is undefined for negative bases in WGSL.
+            let x = match op {
+                BinaryOp::Pow => &x_abs,
+                _ => &x,
+            };
+            let expected = a.iter().zip(&b).map(|(&a, &b)| f(a, b)).collect::<Vec<_>>();
+
+            assert_close(&x.binary(&context, op, &y).read_to_vec(&context), &expected);
+        }
+    }
+
+    #[test]
+    fn test_unary_ops() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let a = vec![0.5f32, 1., 2., 4., 0.125];
+        let x = Buffer::from_vec(&context, RESULT_USAGE, a.clone());
+
+        for op in UnaryOp::ALL {
+            let f = |a: f32| match op {
+                UnaryOp::Neg => -a,
+                UnaryOp::Abs => a.abs(),
+                UnaryOp::Conj => a,
+                UnaryOp::Exp => a.exp(),
+                UnaryOp::Log => a.ln(),
+                UnaryOp::Sqrt => a.sqrt(),
+                UnaryOp::Sin => a.sin(),
+                UnaryOp::Cos => a.cos(),
+                UnaryOp::Tanh => a.tanh(),
+                UnaryOp::Sigmoid => 1. / (1. + (-a).exp()),
+            };
+            let expected = a.iter().map(|&a| f(a)).collect::<Vec<_>>();
+
+            assert_close(&x.unary(&context, op).read_to_vec(&context), &expected);
+        }
+    }
+
+    #[test]
+    fn test_integer_ops() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Buffer::from_vec(&context, RESULT_USAGE, vec![-3i32, 5, 7, -1]);
+        let y = Buffer::from_vec(&context, RESULT_USAGE, vec![2i32, -2, 7, 4]);
+
+        assert_eq!(
+            x.add(&context, &y).read_to_vec(&context),
+            vec![-1, 3, 14, 3]
+        );
+        assert_eq!(
+            x.div(&context, &y).read_to_vec(&context),
+            vec![-1, -2, 1, 0]
+        );
+        assert_eq!(x.max(&context, &y).read_to_vec(&context), vec![2, 5, 7, 4]);
+        assert_eq!(x.neg(&context).read_to_vec(&context), vec![3, -5, -7, 1]);
+
+        assert_eq!(
+            x.try_sqrt(&context).err(),
+            Some(KernelError::UnsupportedOp {
+                op: "sqrt",
+                ty: "i32"
+            })
+        );
+
+        let z = Buffer::from_vec(&context, RESULT_USAGE, vec![1u32, 2]);
+        assert!(matches!(
+            z.try_neg(&context),
+            Err(KernelError::UnsupportedOp { op: "neg", .. })
+        ));
+        // 8-bit integers are emulated rather than refused.
+        let w = Buffer::from_vec(&context, RESULT_USAGE, vec![1u8, 2]);
+        assert_eq!(w.abs(&context).read_to_vec(&context), vec![1, 2]);
+
+        // Without the feature, f64 kernels are refused before compiling.
+        if !context.features().contains(wgpu::Features::SHADER_F64) {
+            let v = Buffer::from_vec(&context, RESULT_USAGE, vec![1f64, 2.]);
+            assert!(matches!(
+                v.try_abs(&context),
+                Err(KernelError::MissingFeature { ty: "f64", .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_invalid_operands() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Buffer::from_vec(&context, RESULT_USAGE, vec![1f32, 2., 3.]);
+        let y = Buffer::from_vec(&context, RESULT_USAGE, vec![1f32, 2.]);
+        assert_eq!(
+            x.try_add(&context, &y).err(),
+            Some(KernelError::LengthMismatch {
+                op: "add",
+                expected: 3,
+                found: 2
+            })
+        );
+
+        let z = Buffer::from_vec(&context, wgpu::BufferUsages::COPY_SRC, vec![1f32, 2., 3.]);
+        assert!(matches!(
+            x.try_mul(&context, &z),
+            Err(KernelError::InvalidBufferUsage(_))
+        ));
+    }
+
+    #[test]
+    fn test_compiled_once() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Buffer::from_vec(&context, RESULT_USAGE, vec![1f32; 1000]);
+        let y = x.add(&context, &x).add(&context, &x).exp(&context);
+        assert_eq!(context.kernels().len(), 2);
+
+        let expected = vec![3f32.exp(); 1000];
+        assert_close(&y.read_to_vec(&context), &expected);
+    }
+}
@@ -0,0 +1,175 @@
+use super::template::{emulation, Emulation};
+
+/// Element-wise operations on two operands of equal length.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Min,
+    Max,
+}
+
+/// Element-wise operations on a single operand.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    /// The absolute value of real elements. Complex elements are rejected,
+    /// since their absolute values are reals, see [`Abs`](super::Abs).
+    Abs,
+    /// The complex conjugate, which leaves real elements unchanged.
+    Conj,
+    Exp,
+    Log,
+    Sqrt,
+    Sin,
+    Cos,
+    Tanh,
+    Sigmoid,
+}
+
+impl BinaryOp {
+    pub const ALL: [BinaryOp; 7] = [
+        BinaryOp::Add,
+        BinaryOp::Sub,
+        BinaryOp::Mul,
+        BinaryOp::Div,
+        BinaryOp::Pow,
+        BinaryOp::Min,
+        BinaryOp::Max,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "add",
+            BinaryOp::Sub => "sub",
+            BinaryOp::Mul => "mul",
+            BinaryOp::Div => "div",
+            BinaryOp::Pow => "pow",
+            BinaryOp::Min => "min",
+            BinaryOp::Max => "max",
+        }
+    }
+
+    /// The WGSL expression computing the result from `a` and `b`, of the
+    /// kernel type `ty`.
+    pub(crate) fn expression(&self, ty: &str) -> &'static str {
+        if let Some(Emulation::Wide { .. }) = emulation(ty) {
+            return match self {
+                BinaryOp::Add => "add64(a, b)",
+                BinaryOp::Sub => "sub64(a, b)",
+                BinaryOp::Mul => "mul64(a, b)",
+                BinaryOp::Div => "div64(a, b)",
+                BinaryOp::Min => "min64(a, b)",
+                BinaryOp::Max => "max64(a, b)",
+                BinaryOp::Pow => unreachable!("pow is only supported on f32"),
+            };
+        }
+
+        if let Some(Emulation::Complex) = emulation(ty) {
+            return match self {
+                BinaryOp::Add => "a + b",
+                BinaryOp::Sub => "a - b",
+                BinaryOp::Mul => "cmul(a, b)",
+                BinaryOp::Div => "cdiv(a, b)",
+                _ => unreachable!("{} is not supported on complex numbers", self.name()),
+            };
+        }
+
+        match self {
+            BinaryOp::Add => "a + b",
+            BinaryOp::Sub => "a - b",
+            BinaryOp::Mul => "a * b",
+            BinaryOp::Div => "a / b",
+            BinaryOp::Pow => "pow(a, b)",
+            BinaryOp::Min => "min(a, b)",
+            BinaryOp::Max => "max(a, b)",
+        }
+    }
+
+    /// Whether the operation is defined on the kernel type `ty`.
+    pub fn supports(&self, ty: &str) -> bool {
+        match self {
+            BinaryOp::Pow => matches!(ty, "f32" | "f16" | "bf16"),
+            // NOTE: Complex numbers are not ordered.
+            BinaryOp::Min | BinaryOp::Max => emulation(ty) != Some(Emulation::Complex),
+            _ => true,
+        }
+    }
+}
+
+impl UnaryOp {
+    pub const ALL: [UnaryOp; 10] = [
+        UnaryOp::Neg,
+        UnaryOp::Abs,
+        UnaryOp::Conj,
+        UnaryOp::Exp,
+        UnaryOp::Log,
+        UnaryOp::Sqrt,
+        UnaryOp::Sin,
+        UnaryOp::Cos,
+        UnaryOp::Tanh,
+        UnaryOp::Sigmoid,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            UnaryOp::Neg => "neg",
+            UnaryOp::Abs => "abs",
+            UnaryOp::Conj => "conj",
+            UnaryOp::Exp => "exp",
+            UnaryOp::Log => "log",
+            UnaryOp::Sqrt => "sqrt",
+            UnaryOp::Sin => "sin",
+            UnaryOp::Cos => "cos",
+            UnaryOp::Tanh => "tanh",
+            UnaryOp::Sigmoid => "sigmoid",
+        }
+    }
+
+    /// The WGSL expression computing the result from `a`, of the kernel type
+    /// `ty`.
+    pub(crate) fn expression(&self, ty: &str) -> &'static str {
+        if let Some(Emulation::Wide { .. }) = emulation(ty) {
+            return match self {
+                UnaryOp::Neg => "neg64(a)",
+                UnaryOp::Abs => "abs64(a)",
+                UnaryOp::Conj => "a",
+                _ => unreachable!("{} is only supported on f32", self.name()),
+            };
+        }
+
+        if let Some(Emulation::Complex) = emulation(ty) {
+            return match self {
+                UnaryOp::Neg => "-a",
+                UnaryOp::Conj => "conj(a)",
+                _ => unreachable!("{} is not supported on complex numbers", self.name()),
+            };
+        }
+
+        match self {
+            UnaryOp::Neg => "-a",
+            UnaryOp::Abs => "abs(a)",
+            UnaryOp::Conj => "a",
+            UnaryOp::Exp => "exp(a)",
+            UnaryOp::Log => "log(a)",
+            UnaryOp::Sqrt => "sqrt(a)",
+            UnaryOp::Sin => "sin(a)",
+            UnaryOp::Cos => "cos(a)",
+            UnaryOp::Tanh => "tanh(a)",
+            UnaryOp::Sigmoid => "1.0 / (1.0 + exp(-a))",
+        }
+    }
+
+    /// Whether the operation is defined on the kernel type `ty`.
+    pub fn supports(&self, ty: &str) -> bool {
+        match self {
+            UnaryOp::Neg => !ty.starts_with('u'),
+            UnaryOp::Abs => emulation(ty) != Some(Emulation::Complex),
+            UnaryOp::Conj => true,
+            _ => matches!(ty, "f32" | "f16" | "bf16"),
+        }
+    }
+}
@@ -0,0 +1,526 @@
+use std::ops::RangeBounds;
+
+use crate::{
+    backend::{
+        batch::CommandBatch, buffers::Buffer, device::Context, traits::BufferType,
+        util::materialize,
+    },
+    indexing::Layout,
+};
+
+use super::{BuiltinKernel, KernelError};
+
+impl CommandBatch<'_> {
+    /// Records a copy of the elements in `src_range` to `dst_range` of `dst`
+    /// with a kernel. Unlike [`CommandBatch::copy`], the ranges need not be
+    /// aligned to whole words, which matters for 8- and 16-bit elements, but
+    /// both buffers must be STORAGE buffers.
+    pub fn copy_elements<T: BufferType>(
+        &mut self,
+        src: &Buffer<T>,
+        src_range: impl RangeBounds<usize>,
+        dst: &mut Buffer<T>,
+        dst_range: impl RangeBounds<usize>,
+    ) -> Result<&mut Self, KernelError> {
+        let src_bound = materialize(src_range, src);
+        let dst_bound = materialize(dst_range, &*dst);
+        if src_bound.len() != dst_bound.len() {
+            return Err(KernelError::LengthMismatch {
+                op: "copy",
+                expected: src_bound.len(),
+                found: dst_bound.len(),
+            });
+        }
+
+        let src_layout =
+            Layout::contiguous(&[src.len()]).slice((src_bound.start()..src_bound.end(),))?;
+        self.dispatch_copy(src, &src_layout, dst, dst_bound.start())
+    }
+
+    /// Records a copy of the elements of `src`, read through `layout` in
+    /// row-major order, to the whole of `dst` with the kernel of
+    /// [`CommandBatch::copy_elements`]. This makes strided views contiguous.
+    pub fn gather<T: BufferType>(
+        &mut self,
+        (src, layout): (&Buffer<T>, &Layout),
+        dst: &mut Buffer<T>,
+    ) -> Result<&mut Self, KernelError> {
+        if layout.len() != dst.len() {
+            return Err(KernelError::LengthMismatch {
+                op: "copy",
+                expected: dst.len(),
+                found: layout.len(),
+            });
+        }
+
+        self.dispatch_copy(src, layout, dst, 0)
+    }
+
+    /// Records a copy of the elements of `src` read through `layout` to `dst`,
+    /// starting at element `dst_start`.
+    fn dispatch_copy<T: BufferType>(
+        &mut self,
+        src: &Buffer<T>,
+        layout: &Layout,
+        dst: &mut Buffer<T>,
+        dst_start: usize,
+    ) -> Result<&mut Self, KernelError> {
+        for buffer in [src, &*dst] {
+            if !buffer.usage().contains(wgpu::BufferUsages::STORAGE) {
+                return Err(KernelError::InvalidBufferUsage(buffer.usage()));
+            }
+        }
+
+        if layout.is_empty() {
+            return Ok(self);
+        }
+
+        // NOTE: Only the bits are copied, so elements of 32 bits or more are
+        // copied as several words, and the signedness of packed integers is
+        // irrelevant.
+        let (ty, unit) = match size_of::<T>() {
+            1 => ("u8", 1),
+            2 => ("u16", 2),
+            _ => ("u32", 4),
+        };
+        let scale = size_of::<T>() / unit;
+        let lanes = 4 / unit;
+
+        let dst_start = dst_start * scale;
+        let len = layout.len() * scale;
+        let first_word = dst_start / lanes;
+        let words = (dst_start + len - 1) / lanes - first_word + 1;
+
+        let mut geometry = vec![
+            layout.offset() as i32,
+            dst_start as i32,
+            len as i32,
+            first_word as i32,
+            scale as i32,
+            layout.ndim() as i32,
+        ];
+        geometry.extend(layout.shape().iter().map(|&len| len as i32));
+        geometry.extend(layout.strides().iter().map(|&stride| stride as i32));
+        let geometry = Buffer::from_vec(self.context(), wgpu::BufferUsages::STORAGE, geometry);
+
+        self.keep_alive(src).keep_alive(dst).keep_alive(&geometry);
+        let resources = vec![
+            src.get_resource(),
+            dst.get_resource(),
+            geometry.get_resource(),
+        ];
+        self.dispatch_elementwise(BuiltinKernel::CopyPacked, ty, resources, words)
+    }
+}
+
+impl<T: BufferType> Buffer<T> {
+    /// Copies the elements in `src_range` to `dst_range` of `buffer` with a
+    /// kernel, see [`CommandBatch::copy_elements`].
+    pub fn copy_elements_to(
+        &self,
+        context: &Context,
+        src_range: impl RangeBounds<usize>,
+        buffer: &mut Buffer<T>,
+        dst_range: impl RangeBounds<usize>,
+    ) {
+        let copy_result = self.try_copy_elements_to(context, src_range, buffer, dst_range);
+
+        if let Err(e) = &copy_result {
+            log::error!("Failed at Buffer::copy_elements_to: {}", e);
+        }
+
+        copy_result.unwrap()
+    }
+
+    pub fn try_copy_elements_to(
+        &self,
+        context: &Context,
+        src_range: impl RangeBounds<usize>,
+        buffer: &mut Buffer<T>,
+        dst_range: impl RangeBounds<usize>,
+    ) -> Result<(), KernelError> {
+        let mut batch = context.batch();
+        batch.copy_elements(self, src_range, buffer, dst_range)?;
+        batch.submit();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use half::{bf16, f16};
+
+    use crate::{
+        backend::{buffers::Buffer, device::test_context, kernels::RESULT_USAGE},
+        indexing::Layout,
+    };
+
+    use super::KernelError;
+    use crate::backend::kernels::{ArgReduceOp, BinaryOp, ReduceOp, ScanOp};
+
+    /// A binary op with the Rust function it should match.
+    type Case<T> = (BinaryOp, fn(T, T) -> T);
+
+    #[test]
+    fn test_packed_ops() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let a = vec![250u8, 3, 128, 0, 7];
+        let b = vec![10u8, 5, 2, 9, 0];
+        let x = Buffer::from_vec(&context, RESULT_USAGE, a.clone());
+        let y = Buffer::from_vec(&context, RESULT_USAGE, b.clone());
+
+        let wrapped = a.iter().zip(&b).map(|(a, b)| a.wrapping_add(*b));
+        assert_eq!(
+            x.add(&context, &y).read_to_vec(&context),
+            wrapped.collect::<Vec<_>>()
+        );
+        let products = a.iter().zip(&b).map(|(a, b)| a.wrapping_mul(*b));
+        assert_eq!(
+            x.mul(&context, &y).read_to_vec(&context),
+            products.collect::<Vec<_>>()
+        );
+        assert!(matches!(
+            x.try_neg(&context),
+            Err(KernelError::UnsupportedOp {
+                op: "neg",
+                ty: "u8"
+            })
+        ));
+
+        let a = vec![-128i8, -7, 100, 3, -1, 64];
+        let b = vec![-1i8, 2, -3, 3, 5, -100];
+        let x = Buffer::from_vec(&context, RESULT_USAGE, a.clone());
+        let y = Buffer::from_vec(&context, RESULT_USAGE, b.clone());
+
+        let quotients = a.iter().zip(&b).map(|(a, b)| a.wrapping_div(*b));
+        assert_eq!(
+            x.div(&context, &y).read_to_vec(&context),
+            quotients.collect::<Vec<_>>()
+        );
+        let maxima = a.iter().zip(&b).map(|(a, b)| *a.max(b));
+        assert_eq!(
+            x.max(&context, &y).read_to_vec(&context),
+            maxima.collect::<Vec<_>>()
+        );
+        assert_eq!(
+            x.abs(&context).read_to_vec(&context),
+            a.iter().map(|a| a.wrapping_abs()).collect::<Vec<_>>()
+        );
+
+        let x = Buffer::from_vec(&context, RESULT_USAGE, vec![-300i16, 32767, 5]);
+        let y = Buffer::from_vec(&context, RESULT_USAGE, vec![-400i16, 1, -9]);
+        assert_eq!(
+            x.add(&context, &y).read_to_vec(&context),
+            vec![-700, -32768, -4]
+        );
+        assert_eq!(x.neg(&context).read_to_vec(&context), vec![300, -32767, -5]);
+
+        let x = Buffer::from_vec(&context, RESULT_USAGE, vec![60000u16, 2, 3]);
+        assert_eq!(
+            x.min(&context, &x.add(&context, &x)).read_to_vec(&context),
+            vec![54464, 2, 3]
+        );
+    }
+
+    #[test]
+    fn test_packed_broadcast() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Buffer::from_vec(&context, RESULT_USAGE, vec![1i8, -2, 3, -4, 5, -6]);
+        let y = Buffer::from_vec(&context, RESULT_USAGE, vec![10i8, 20, 30]);
+        let mut result = Buffer::<i8>::with_len(&context, RESULT_USAGE, 6);
+
+        // The transpose of `x` plus a column.
+        let mut batch = context.batch();
+        batch
+            .binary_broadcast(
+                BinaryOp::Add,
+                (
+                    &x,
+                    &Layout::contiguous(&[2, 3]).permute_axes(&[1, 0]).unwrap(),
+                ),
+                (&y, &Layout::contiguous(&[3, 1])),
+                &mut result,
+            )
+            .unwrap();
+        batch.submit();
+
+        assert_eq!(result.read_to_vec(&context), vec![11, 6, 18, 25, 33, 24]);
+    }
+
+    #[test]
+    fn test_packed_reduce() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Buffer::from_vec(&context, RESULT_USAGE, vec![100i8, 100, -5, 7, -128, 3]);
+        let layout = Layout::contiguous(&[2, 3]);
+
+        let mut sums = Buffer::<i8>::with_len(&context, RESULT_USAGE, 2);
+        let mut means = Buffer::<i8>::with_len(&context, RESULT_USAGE, 2);
+        let mut minima = Buffer::<i8>::with_len(&context, RESULT_USAGE, 3);
+        let mut argmax = Buffer::<u32>::with_len(&context, RESULT_USAGE, 2);
+
+        let mut batch = context.batch();
+        batch
+            .reduce(ReduceOp::Sum, (&x, &layout), &[1], &mut sums)
+            .unwrap();
+        batch
+            .reduce(ReduceOp::Mean, (&x, &layout), &[1], &mut means)
+            .unwrap();
+        batch
+            .reduce(ReduceOp::Min, (&x, &layout), &[0], &mut minima)
+            .unwrap();
+        batch
+            .arg_reduce(ArgReduceOp::ArgMax, (&x, &layout), &[1], &mut argmax)
+            .unwrap();
+        batch.submit();
+
+        // Sums wrap around, but means are computed without overflow.
+        assert_eq!(sums.read_to_vec(&context), vec![-61, -118]);
+        assert_eq!(means.read_to_vec(&context), vec![65, -39]);
+        assert_eq!(minima.read_to_vec(&context), vec![7, -128, -5]);
+        assert_eq!(argmax.read_to_vec(&context), vec![0, 0]);
+
+        let y = Buffer::from_vec(&context, RESULT_USAGE, vec![1u16; 3]);
+        let mut batch = context.batch();
+        let mut scanned = Buffer::<u16>::with_len(&context, RESULT_USAGE, 3);
+        assert!(matches!(
+            batch.scan(
+                ScanOp::Sum,
+                (&y, &Layout::contiguous(&[3])),
+                0,
+                false,
+                &mut scanned
+            ),
+            Err(KernelError::UnsupportedOp { ty: "u16", .. })
+        ));
+    }
+
+    #[test]
+    fn test_wide_ops() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let a = vec![u64::MAX, 1 << 40, 0xffff_ffff, 12345678901234, 7];
+        let b = vec![2u64, 3 << 20, 1, 1000003, 0];
+        let x = Buffer::from_vec(&context, RESULT_USAGE, a.clone());
+        let y = Buffer::from_vec(&context, RESULT_USAGE, b.clone());
+
+        let cases: [Case<u64>; 6] = [
+            (BinaryOp::Add, u64::wrapping_add),
+            (BinaryOp::Sub, u64::wrapping_sub),
+            (BinaryOp::Mul, u64::wrapping_mul),
+            // Like 32-bit integers, division by zero yields the dividend.
+            (BinaryOp::Div, |a, b| a.checked_div(b).unwrap_or(a)),
+            (BinaryOp::Min, u64::min),
+            (BinaryOp::Max, u64::max),
+        ];
+        for (op, f) in cases {
+            let expected = a.iter().zip(&b).map(|(&a, &b)| f(a, b));
+            assert_eq!(
+                x.binary(&context, op, &y).read_to_vec(&context),
+                expected.collect::<Vec<_>>(),
+                "{:?}",
+                op
+            );
+        }
+
+        let a = vec![i64::MIN, -5_000_000_000, 42, -1, 9];
+        let b = vec![-1i64, 3, -5, 1 << 35, -2];
+        let x = Buffer::from_vec(&context, RESULT_USAGE, a.clone());
+        let y = Buffer::from_vec(&context, RESULT_USAGE, b.clone());
+
+        let cases: [Case<i64>; 4] = [
+            (BinaryOp::Mul, i64::wrapping_mul),
+            (BinaryOp::Div, i64::wrapping_div),
+            (BinaryOp::Min, i64::min),
+            (BinaryOp::Max, i64::max),
+        ];
+        for (op, f) in cases {
+            let expected = a.iter().zip(&b).map(|(&a, &b)| f(a, b));
+            assert_eq!(
+                x.binary(&context, op, &y).read_to_vec(&context),
+                expected.collect::<Vec<_>>(),
+                "{:?}",
+                op
+            );
+        }
+        assert_eq!(
+            x.neg(&context).read_to_vec(&context),
+            a.iter().map(|a| a.wrapping_neg()).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            x.abs(&context).read_to_vec(&context),
+            a.iter().map(|a| a.wrapping_abs()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_wide_reduce() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let data = vec![3_000_000_000i64, -7, 5_000_000_000, -2, 11, -4_000_000_000];
+        let x = Buffer::from_vec(&context, RESULT_USAGE, data);
+        let layout = Layout::contiguous(&[2, 3]);
+
+        let mut sums = Buffer::<i64>::with_len(&context, RESULT_USAGE, 2);
+        let mut products = Buffer::<i64>::with_len(&context, RESULT_USAGE, 3);
+        let mut means = Buffer::<i64>::with_len(&context, RESULT_USAGE, 2);
+        let mut argmin = Buffer::<u32>::with_len(&context, RESULT_USAGE, 3);
+
+        let mut batch = context.batch();
+        batch
+            .reduce(ReduceOp::Sum, (&x, &layout), &[1], &mut sums)
+            .unwrap();
+        batch
+            .reduce(ReduceOp::Prod, (&x, &layout), &[0], &mut products)
+            .unwrap();
+        batch
+            .reduce(ReduceOp::Mean, (&x, &layout), &[1], &mut means)
+            .unwrap();
+        batch
+            .arg_reduce(ArgReduceOp::ArgMin, (&x, &layout), &[0], &mut argmin)
+            .unwrap();
+        batch.submit();
+
+        assert_eq!(
+            sums.read_to_vec(&context),
+            vec![7_999_999_993, -3_999_999_991]
+        );
+        assert_eq!(
+            products.read_to_vec(&context),
+            vec![-6_000_000_000, -77, -20_000_000_000_000_000_000i128 as i64]
+        );
+        assert_eq!(
+            means.read_to_vec(&context),
+            vec![2_666_666_664, -1_333_333_330]
+        );
+        assert_eq!(argmin.read_to_vec(&context), vec![1, 0, 1]);
+
+        let y = Buffer::from_vec(&context, RESULT_USAGE, vec![u64::MAX, 1 << 63, 5]);
+        let mut maximum = Buffer::<u64>::with_len(&context, RESULT_USAGE, 1);
+        let mut batch = context.batch();
+        batch
+            .reduce(
+                ReduceOp::Max,
+                (&y, &Layout::contiguous(&[3])),
+                &[0],
+                &mut maximum,
+            )
+            .unwrap();
+        batch.submit();
+        assert_eq!(maximum.read_to_vec(&context), vec![u64::MAX]);
+    }
+
+    #[test]
+    fn test_half_ops() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let a = [1.5f32, -2.25, 4., 0.125, 100.];
+        let b = [2.25f32, 0.5, -4., 8., -0.5];
+        let halves = |values: &[f32]| values.iter().map(|&v| f16::from_f32(v)).collect();
+        let x = Buffer::from_vec(&context, RESULT_USAGE, halves(&a));
+        let y = Buffer::from_vec(&context, RESULT_USAGE, halves(&b));
+
+        let sums = a.iter().zip(&b).map(|(a, b)| a + b).collect::<Vec<_>>();
+        assert_eq!(x.add(&context, &y).read_to_vec(&context), halves(&sums));
+        let products = a.iter().zip(&b).map(|(a, b)| a * b).collect::<Vec<_>>();
+        assert_eq!(x.mul(&context, &y).read_to_vec(&context), halves(&products));
+        assert_eq!(
+            x.abs(&context).read_to_vec(&context),
+            halves(&a.map(f32::abs))
+        );
+        assert_eq!(
+            x.mul(&context, &x).sqrt(&context).read_to_vec(&context),
+            halves(&a.map(f32::abs))
+        );
+
+        // Results round back to the nearest bfloat16.
+        let x = Buffer::from_vec(&context, RESULT_USAGE, vec![bf16::from_f32(1.); 2]);
+        let y = Buffer::from_vec(&context, RESULT_USAGE, vec![bf16::from_f32(0.01); 2]);
+        assert_eq!(
+            x.add(&context, &y).read_to_vec(&context),
+            vec![bf16::from_f32(1.01); 2]
+        );
+    }
+
+    #[test]
+    fn test_half_reduce() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let data = [0.5f32, -3., 2.5, 8., 1., -0.25];
+        let x = Buffer::from_vec(
+            &context,
+            RESULT_USAGE,
+            data.iter().map(|&v| f16::from_f32(v)).collect(),
+        );
+        let layout = Layout::contiguous(&[2, 3]);
+
+        let mut sums = Buffer::<f16>::with_len(&context, RESULT_USAGE, 2);
+        let mut means = Buffer::<f16>::with_len(&context, RESULT_USAGE, 3);
+        let mut argmax = Buffer::<u32>::with_len(&context, RESULT_USAGE, 2);
+
+        let mut batch = context.batch();
+        batch
+            .reduce(ReduceOp::Sum, (&x, &layout), &[1], &mut sums)
+            .unwrap();
+        batch
+            .reduce(ReduceOp::Mean, (&x, &layout), &[0], &mut means)
+            .unwrap();
+        batch
+            .arg_reduce(ArgReduceOp::ArgMax, (&x, &layout), &[1], &mut argmax)
+            .unwrap();
+        batch.submit();
+
+        let halves = |values: &[f32]| values.iter().map(|&v| f16::from_f32(v)).collect::<Vec<_>>();
+        assert_eq!(sums.read_to_vec(&context), halves(&[0., 8.75]));
+        assert_eq!(means.read_to_vec(&context), halves(&[4.25, -1., 1.125]));
+        assert_eq!(argmax.read_to_vec(&context), vec![2, 0]);
+    }
+
+    #[test]
+    fn test_copy_elements() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Buffer::from_vec(&context, RESULT_USAGE, (1..=9).collect::<Vec<u8>>());
+        let mut y = Buffer::from_vec(&context, RESULT_USAGE, vec![0u8; 7]);
+        x.copy_elements_to(&context, 1..6, &mut y, 2..7);
+        assert_eq!(y.read_to_vec(&context), vec![0, 0, 2, 3, 4, 5, 6]);
+
+        let x = Buffer::from_vec(&context, RESULT_USAGE, vec![-1i16, -2, -3]);
+        let mut y = Buffer::from_vec(&context, RESULT_USAGE, vec![7i16; 5]);
+        x.copy_elements_to(&context, .., &mut y, 1..4);
+        assert_eq!(y.read_to_vec(&context), vec![7, -1, -2, -3, 7]);
+
+        let x = Buffer::from_vec(&context, RESULT_USAGE, vec![u64::MAX, 1, 2]);
+        let mut y = Buffer::from_vec(&context, RESULT_USAGE, vec![0u64; 3]);
+        x.copy_elements_to(&context, ..2, &mut y, 1..);
+        assert_eq!(y.read_to_vec(&context), vec![0, u64::MAX, 1]);
+
+        assert_eq!(
+            x.try_copy_elements_to(&context, .., &mut y, 1..),
+            Err(KernelError::LengthMismatch {
+                op: "copy",
+                expected: 3,
+                found: 2
+            })
+        );
+    }
+}
@@ -0,0 +1,675 @@
+use crate::{
+    backend::{batch::CommandBatch, buffers::Buffer, traits::BufferType},
+    indexing::Layout,
+};
+
+use super::{
+    kernel_type,
+    template::{emulation, Emulation},
+    BuiltinKernel, KernelError, RESULT_USAGE, WORKGROUP_SIZE,
+};
+
+/// The most inner elements one workgroup reduces in a first pass. Longer
+/// reductions are split into parts that a second pass combines, with one
+/// workgroup reducing all the parts of an output.
+#[cfg(not(test))]
+const PART_LEN: usize = 64 * WORKGROUP_SIZE as usize;
+// NOTE: Small enough for tests to split reductions into more parts than this.
+#[cfg(test)]
+const PART_LEN: usize = 2 * WORKGROUP_SIZE as usize;
+
+/// Reductions producing values of the element type.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum ReduceOp {
+    Sum,
+    /// The sum divided by the count, which truncates for integer types.
+    Mean,
+    Prod,
+    Max,
+    Min,
+}
+
+/// Reductions producing the position of the selected element, counted in
+/// row-major order over the reduced axes. Ties resolve to the first position.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum ArgReduceOp {
+    ArgMax,
+    ArgMin,
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub(crate) enum Reduction {
+    Value(ReduceOp),
+    Arg(ArgReduceOp),
+}
+
+/// Which values of a reduction pass are the partial results of a reduction
+/// split into parts. Elements packed in words keep them unpacked, so that
+/// means are exact and halves accumulate in `f32`.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub(crate) enum Partials {
+    Neither,
+    Output,
+    Input,
+}
+
+impl ReduceOp {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReduceOp::Sum => "sum",
+            ReduceOp::Mean => "mean",
+            ReduceOp::Prod => "prod",
+            ReduceOp::Max => "max",
+            ReduceOp::Min => "min",
+        }
+    }
+}
+
+impl ArgReduceOp {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ArgReduceOp::ArgMax => "argmax",
+            ArgReduceOp::ArgMin => "argmin",
+        }
+    }
+}
+
+impl Reduction {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Reduction::Value(op) => op.name(),
+            Reduction::Arg(op) => op.name(),
+        }
+    }
+
+    /// Whether the reduction is defined on the kernel type `ty`.
+    pub(crate) fn supports(&self, ty: &str) -> bool {
+        // NOTE: Complex numbers are not ordered.
+        match emulation(ty) {
+            Some(Emulation::Complex) => matches!(
+                self,
+                Reduction::Value(ReduceOp::Sum | ReduceOp::Mean | ReduceOp::Prod)
+            ),
+            _ => true,
+        }
+    }
+
+    /// The WGSL identity of the reduction on elements of type `ty`.
+    pub(crate) fn identity(&self, ty: &str) -> &'static str {
+        let greatest = matches!(
+            self,
+            Reduction::Value(ReduceOp::Min) | Reduction::Arg(ArgReduceOp::ArgMin)
+        );
+
+        if let Some(Emulation::Wide { signed }) = emulation(ty) {
+            return match (self, signed, greatest) {
+                (Reduction::Value(ReduceOp::Sum | ReduceOp::Mean), _, _) => "vec2(0u)",
+                (Reduction::Value(ReduceOp::Prod), _, _) => "vec2(1u, 0u)",
+                (_, true, true) => "vec2(0xffffffffu, 0x7fffffffu)",
+                (_, true, false) => "vec2(0u, 0x80000000u)",
+                (_, false, true) => "vec2(0xffffffffu)",
+                (_, false, false) => "vec2(0u)",
+            };
+        }
+
+        if let Some(Emulation::Complex) = emulation(ty) {
+            return match self {
+                Reduction::Value(ReduceOp::Prod) => "vec2(1.0, 0.0)",
+                _ => "vec2(0.0)",
+            };
+        }
+
+        // Packed elements are reduced as their unpacked type.
+        let ty = match emulation(ty) {
+            Some(emulation @ (Emulation::Packed { .. } | Emulation::Half { .. })) => {
+                emulation.unpacked_type()
+            }
+            _ => ty,
+        };
+
+        match self {
+            Reduction::Value(ReduceOp::Sum | ReduceOp::Mean) => "{{T}}(0)",
+            Reduction::Value(ReduceOp::Prod) => "{{T}}(1)",
+            _ => match (ty, greatest) {
+                ("f32", true) => "3.40282347e+38f",
+                ("f32", false) => "-3.40282347e+38f",
+                ("f64", true) => "1.7976931348623157e+308lf",
+                ("f64", false) => "-1.7976931348623157e+308lf",
+                ("i32", true) => "2147483647i",
+                ("i32", false) => "-2147483647i - 1i",
+                (_, true) => "4294967295u",
+                (_, false) => "0u",
+            },
+        }
+    }
+
+    /// The WGSL body of `combine(a, ia, b, ib)` on elements of type `ty`.
+    pub(crate) fn combine(&self, ty: &str) -> &'static str {
+        if let Some(Emulation::Wide { .. }) = emulation(ty) {
+            return match self {
+                Reduction::Value(ReduceOp::Sum | ReduceOp::Mean) => "return Pair(add64(a, b), 0u);",
+                Reduction::Value(ReduceOp::Prod) => "return Pair(mul64(a, b), 0u);",
+                Reduction::Value(ReduceOp::Max) => "return Pair(max64(a, b), 0u);",
+                Reduction::Value(ReduceOp::Min) => "return Pair(min64(a, b), 0u);",
+                Reduction::Arg(ArgReduceOp::ArgMax) => {
+                    "if gt64(a, b) || (eq64(a, b) && ia < ib) { return Pair(a, ia); } return Pair(b, ib);"
+                }
+                Reduction::Arg(ArgReduceOp::ArgMin) => {
+                    "if lt64(a, b) || (eq64(a, b) && ia < ib) { return Pair(a, ia); } return Pair(b, ib);"
+                }
+            };
+        }
+
+        match self {
+            Reduction::Value(ReduceOp::Sum | ReduceOp::Mean) => "return Pair(a + b, 0u);",
+            Reduction::Value(ReduceOp::Prod) if emulation(ty) == Some(Emulation::Complex) => {
+                "return Pair(cmul(a, b), 0u);"
+            }
+            Reduction::Value(ReduceOp::Prod) => "return Pair(a * b, 0u);",
+            Reduction::Value(ReduceOp::Max) => "return Pair(max(a, b), 0u);",
+            Reduction::Value(ReduceOp::Min) => "return Pair(min(a, b), 0u);",
+            Reduction::Arg(ArgReduceOp::ArgMax) => {
+                "if a > b || (a == b && ia < ib) { return Pair(a, ia); } return Pair(b, ib);"
+            }
+            Reduction::Arg(ArgReduceOp::ArgMin) => {
+                "if a < b || (a == b && ia < ib) { return Pair(a, ia); } return Pair(b, ib);"
+            }
+        }
+    }
+
+    /// The WGSL expression dividing the sum `value` of elements of type `ty`
+    /// by the count of reduced elements.
+    pub(crate) fn mean(&self, ty: &str) -> &'static str {
+        match emulation(ty) {
+            Some(Emulation::Wide { .. }) => "div64(value, vec2(u32(geometry[MEAN_COUNT]), 0u))",
+            Some(Emulation::Complex) => "value / f32(geometry[MEAN_COUNT])",
+            _ => "value / {{T}}(geometry[MEAN_COUNT])",
+        }
+    }
+}
+
+/// The shape left by reducing `axes` of `layout`, where reduced axes either
+/// disappear or, with `keepdims`, remain with a length of one.
+pub(crate) fn reduced_shape(layout: &Layout, axes: &[usize], keepdims: bool) -> Vec<usize> {
+    (0..layout.ndim())
+        .filter_map(|axis| match (axes.contains(&axis), keepdims) {
+            (false, _) => Some(layout.shape()[axis]),
+            (true, true) => Some(1),
+            (true, false) => None,
+        })
+        .collect()
+}
+
+impl CommandBatch<'_> {
+    /// Records the reduction of `axes` of the operand, read through `layout`,
+    /// into `result`, which holds the kept axes contiguously.
+    pub fn reduce<T: BufferType>(
+        &mut self,
+        op: ReduceOp,
+        (input, layout): (&Buffer<T>, &Layout),
+        axes: &[usize],
+        result: &mut Buffer<T>,
+    ) -> Result<&mut Self, KernelError> {
+        self.record_reduce(
+            Reduction::Value(op),
+            (input, layout),
+            axes,
+            Some(result),
+            None,
+        )
+    }
+
+    /// Records the positions selected by `op` along `axes` of the operand,
+    /// read through `layout`, into `result`.
+    pub fn arg_reduce<T: BufferType>(
+        &mut self,
+        op: ArgReduceOp,
+        (input, layout): (&Buffer<T>, &Layout),
+        axes: &[usize],
+        result: &mut Buffer<u32>,
+    ) -> Result<&mut Self, KernelError> {
+        self.record_reduce(
+            Reduction::Arg(op),
+            (input, layout),
+            axes,
+            None,
+            Some(result),
+        )
+    }
+
+    pub(super) fn record_reduce<T: BufferType>(
+        &mut self,
+        reduction: Reduction,
+        (input, layout): (&Buffer<T>, &Layout),
+        axes: &[usize],
+        values: Option<&Buffer<T>>,
+        indices: Option<&Buffer<u32>>,
+    ) -> Result<&mut Self, KernelError> {
+        let ty = kernel_type::<T>(self.context().features())?;
+        if !reduction.supports(ty) {
+            return Err(KernelError::UnsupportedOp {
+                op: reduction.name(),
+                ty,
+            });
+        }
+
+        let (outer, inner) = layout.split_axes(axes)?;
+        let outputs = outer.len();
+
+        let result_len = values
+            .map(Buffer::len)
+            .or(indices.map(Buffer::len))
+            .unwrap_or(outputs);
+        if result_len != outputs {
+            return Err(KernelError::LengthMismatch {
+                op: reduction.name(),
+                expected: outputs,
+                found: result_len,
+            });
+        }
+
+        for usage in [
+            Some(input.usage()),
+            values.map(Buffer::usage),
+            indices.map(Buffer::usage),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if !usage.contains(wgpu::BufferUsages::STORAGE) {
+                return Err(KernelError::InvalidBufferUsage(usage));
+            }
+        }
+
+        if outputs == 0 {
+            return Ok(self);
+        }
+
+        // NOTE: Only sums and products are defined over no elements.
+        if inner.is_empty() {
+            let identity = match reduction {
+                Reduction::Value(ReduceOp::Sum) => T::zeroed(),
+                Reduction::Value(ReduceOp::Prod) => T::ONE,
+                _ => return Err(KernelError::EmptyOperand(reduction.name())),
+            };
+
+            if let Some(values) = values {
+                self.write(values, 0, &vec![identity; outputs])?;
+            }
+            return Ok(self);
+        }
+
+        let context = self.context();
+        let mean_count = match reduction {
+            Reduction::Value(ReduceOp::Mean) => inner.len(),
+            _ => 0,
+        };
+        let is_arg = matches!(reduction, Reduction::Arg(_));
+
+        // Outputs that are not requested are still written by the kernel.
+        let scratch_values;
+        let values = match values {
+            Some(values) => values,
+            None => {
+                scratch_values = Buffer::<T>::with_len(context, RESULT_USAGE, outputs);
+                &scratch_values
+            }
+        };
+        let scratch_indices;
+        let indices = match indices {
+            Some(indices) => indices,
+            None => {
+                scratch_indices = Buffer::<u32>::with_len(context, RESULT_USAGE, 1);
+                &scratch_indices
+            }
+        };
+        // Bound as the unread `input_index` of first passes.
+        let no_indices = Buffer::<u32>::with_len(context, RESULT_USAGE, 1);
+
+        let parts = inner.len().div_ceil(PART_LEN);
+        if parts == 1 {
+            let pass = ReducePass {
+                layouts: (&outer, &inner),
+                outputs,
+                parts: 1,
+                part_len: PART_LEN,
+                read_index: false,
+                write_index: is_arg,
+                mean_count,
+            };
+            return self.dispatch_reduce(
+                BuiltinKernel::Reduce(reduction, Partials::Neither),
+                ty,
+                &pass,
+                (input, &no_indices),
+                (values, indices),
+            );
+        }
+
+        // The first pass reduces each part into a `[outputs, parts]` scratch
+        // buffer, and the second reduces the parts.
+        let partial_indices = Buffer::<u32>::with_len(context, RESULT_USAGE, outputs * parts);
+        let first = ReducePass {
+            layouts: (&outer, &inner),
+            outputs,
+            parts,
+            part_len: PART_LEN,
+            read_index: false,
+            write_index: is_arg,
+            mean_count: 0,
+        };
+
+        let partial = Layout::contiguous(&[outputs, parts]);
+        let (partial_outer, partial_inner) = partial.split_axes(&[1])?;
+        let second = ReducePass {
+            layouts: (&partial_outer, &partial_inner),
+            outputs,
+            parts: 1,
+            part_len: parts,
+            read_index: is_arg,
+            write_index: is_arg,
+            mean_count,
+        };
+
+        let first_kernel = BuiltinKernel::Reduce(reduction, Partials::Output);
+        let second_kernel = BuiltinKernel::Reduce(reduction, Partials::Input);
+        match emulation(ty) {
+            // NOTE: Unpacked elements are 32-bit words.
+            Some(Emulation::Packed { .. } | Emulation::Half { .. }) => {
+                let partial_values =
+                    Buffer::<u32>::with_len(context, RESULT_USAGE, outputs * parts);
+                self.dispatch_reduce(
+                    first_kernel,
+                    ty,
+                    &first,
+                    (input, &no_indices),
+                    (&partial_values, &partial_indices),
+                )?
+                .dispatch_reduce(
+                    second_kernel,
+                    ty,
+                    &second,
+                    (&partial_values, &partial_indices),
+                    (values, indices),
+                )
+            }
+            _ => {
+                let partial_values = Buffer::<T>::with_len(context, RESULT_USAGE, outputs * parts);
+                self.dispatch_reduce(
+                    first_kernel,
+                    ty,
+                    &first,
+                    (input, &no_indices),
+                    (&partial_values, &partial_indices),
+                )?
+                .dispatch_reduce(
+                    second_kernel,
+                    ty,
+                    &second,
+                    (&partial_values, &partial_indices),
+                    (values, indices),
+                )
+            }
+        }
+    }
+
+    /// Records one pass of a reduction, whose values are either elements of
+    /// the kernel type `ty` or, for packed elements, unpacked partial results.
+    fn dispatch_reduce<A: BufferType, B: BufferType>(
+        &mut self,
+        kernel: BuiltinKernel,
+        ty: &'static str,
+        pass: &ReducePass,
+        (input, input_index): (&Buffer<A>, &Buffer<u32>),
+        (output, output_index): (&Buffer<B>, &Buffer<u32>),
+    ) -> Result<&mut Self, KernelError> {
+        let (outer, inner) = pass.layouts;
+        let mut geometry = vec![
+            outer.ndim() as i32,
+            inner.ndim() as i32,
+            outer.offset() as i32,
+            pass.outputs as i32,
+            inner.len() as i32,
+            pass.parts as i32,
+            pass.part_len as i32,
+            pass.read_index as i32,
+            pass.write_index as i32,
+            pass.mean_count as i32,
+        ];
+        for layout in [outer, inner] {
+            geometry.extend(layout.shape().iter().map(|&len| len as i32));
+            geometry.extend(layout.strides().iter().map(|&stride| stride as i32));
+        }
+        let geometry = Buffer::from_vec(self.context(), wgpu::BufferUsages::STORAGE, geometry);
+
+        self.keep_alive(input)
+            .keep_alive(input_index)
+            .keep_alive(output)
+            .keep_alive(output_index)
+            .keep_alive(&geometry);
+
+        let resources = vec![
+            input.get_resource(),
+            input_index.get_resource(),
+            output.get_resource(),
+            output_index.get_resource(),
+            geometry.get_resource(),
+        ];
+
+        // NOTE: Each workgroup computes a single output part.
+        let invocations = pass.outputs * pass.parts * WORKGROUP_SIZE as usize;
+        self.dispatch_elementwise(kernel, ty, resources, invocations)
+    }
+}
+
+/// The geometry of one dispatch of the reduction kernel.
+struct ReducePass<'a> {
+    layouts: (&'a Layout, &'a Layout),
+    outputs: usize,
+    parts: usize,
+    part_len: usize,
+    read_index: bool,
+    write_index: bool,
+    mean_count: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backend::{
+            buffers::{Buffer, BufferWriteError},
+            device::test_context,
+            kernels::RESULT_USAGE,
+        },
+        indexing::Layout,
+    };
+
+    use super::{ArgReduceOp, KernelError, ReduceOp, PART_LEN};
+
+    #[test]
+    fn test_reduce_axes() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Buffer::from_vec(&context, RESULT_USAGE, (0..24).collect::<Vec<i32>>());
+        let layout = Layout::contiguous(&[2, 3, 4]);
+
+        let cases: [(ReduceOp, &[usize], Vec<i32>); 5] = [
+            (ReduceOp::Sum, &[0, 1, 2], vec![276]),
+            (ReduceOp::Sum, &[1], vec![12, 15, 18, 21, 48, 51, 54, 57]),
+            (ReduceOp::Max, &[0, 2], vec![15, 19, 23]),
+            (ReduceOp::Min, &[2], vec![0, 4, 8, 12, 16, 20]),
+            (
+                ReduceOp::Mean,
+                &[0],
+                vec![6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17],
+            ),
+        ];
+
+        for (op, axes, expected) in cases {
+            let mut result = Buffer::<i32>::with_len(&context, RESULT_USAGE, expected.len());
+            let mut batch = context.batch();
+            batch.reduce(op, (&x, &layout), axes, &mut result).unwrap();
+            batch.submit();
+
+            assert_eq!(
+                result.read_to_vec(&context),
+                expected,
+                "{:?} {:?}",
+                op,
+                axes
+            );
+        }
+
+        let mut result = Buffer::<i32>::with_len(&context, RESULT_USAGE, 3);
+        let mut batch = context.batch();
+        assert_eq!(
+            batch
+                .reduce(ReduceOp::Sum, (&x, &layout), &[1], &mut result)
+                .err(),
+            Some(KernelError::LengthMismatch {
+                op: "sum",
+                expected: 8,
+                found: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_reduce_parts() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        // Long enough to be split into parts and reduced in two passes.
+        let len = 2 * PART_LEN + 7;
+        let data = (0..len)
+            .map(|i| ((i * 31) % 1000) as f32)
+            .collect::<Vec<_>>();
+        let x = Buffer::from_vec(&context, RESULT_USAGE, data.clone());
+        let layout = Layout::contiguous(&[len]);
+
+        let mut sum = Buffer::<f32>::with_len(&context, RESULT_USAGE, 1);
+        let mut prod = Buffer::<f32>::with_len(&context, RESULT_USAGE, 1);
+        let mut argmax = Buffer::<u32>::with_len(&context, RESULT_USAGE, 1);
+        let mut argmin = Buffer::<u32>::with_len(&context, RESULT_USAGE, 1);
+
+        let ones = Buffer::from_vec(&context, RESULT_USAGE, vec![1f32; len]);
+        let mut batch = context.batch();
+        batch
+            .reduce(ReduceOp::Sum, (&x, &layout), &[0], &mut sum)
+            .unwrap();
+        batch
+            .reduce(ReduceOp::Prod, (&ones, &layout), &[0], &mut prod)
+            .unwrap();
+        batch
+            .arg_reduce(ArgReduceOp::ArgMax, (&x, &layout), &[0], &mut argmax)
+            .unwrap();
+        batch
+            .arg_reduce(ArgReduceOp::ArgMin, (&x, &layout), &[0], &mut argmin)
+            .unwrap();
+        batch.submit();
+
+        let expected: f64 = data.iter().map(|&v| v as f64).sum();
+        assert!((sum.read_to_vec(&context)[0] as f64 - expected).abs() < expected * 1e-5);
+        assert_eq!(prod.read_to_vec(&context), vec![1.]);
+
+        let max = data.iter().copied().fold(f32::MIN, f32::max);
+        let first_max = data.iter().position(|&v| v == max).unwrap() as u32;
+        assert_eq!(argmax.read_to_vec(&context), vec![first_max]);
+        assert_eq!(argmin.read_to_vec(&context), vec![0]);
+
+        // Packed elements keep their partial results unpacked, so that the
+        // partial sums of a mean do not wrap around.
+        let x = Buffer::from_vec(&context, RESULT_USAGE, vec![100i8; len]);
+        let mut mean = Buffer::<i8>::with_len(&context, RESULT_USAGE, 1);
+        let halves = Buffer::from_vec(&context, RESULT_USAGE, vec![half::f16::ONE; len]);
+        let mut half_sum = Buffer::<half::f16>::with_len(&context, RESULT_USAGE, 1);
+
+        let mut batch = context.batch();
+        batch
+            .reduce(ReduceOp::Mean, (&x, &layout), &[0], &mut mean)
+            .unwrap();
+        batch
+            .reduce(ReduceOp::Sum, (&halves, &layout), &[0], &mut half_sum)
+            .unwrap();
+        batch.submit();
+
+        assert_eq!(mean.read_to_vec(&context), vec![100]);
+        assert_eq!(
+            half_sum.read_to_vec(&context),
+            vec![half::f16::from_f32(len as f32)]
+        );
+    }
+
+    #[test]
+    fn test_reduce_many_parts() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        // More parts than one part holds, which the second pass reduces at once.
+        let len = PART_LEN * (PART_LEN + 3) + 5;
+        let data = (0..len).map(|i| (i % 7) as i32).collect::<Vec<_>>();
+        let x = Buffer::from_vec(&context, RESULT_USAGE, data.clone());
+        let layout = Layout::contiguous(&[len]);
+
+        let mut sum = Buffer::<i32>::with_len(&context, RESULT_USAGE, 1);
+        let mut argmin = Buffer::<u32>::with_len(&context, RESULT_USAGE, 1);
+        let tail = Buffer::from_vec(
+            &context,
+            RESULT_USAGE,
+            (0..len).map(|i| -(i as i32)).collect::<Vec<_>>(),
+        );
+
+        let mut batch = context.batch();
+        batch
+            .reduce(ReduceOp::Sum, (&x, &layout), &[0], &mut sum)
+            .unwrap();
+        batch
+            .arg_reduce(ArgReduceOp::ArgMin, (&tail, &layout), &[0], &mut argmin)
+            .unwrap();
+        batch.submit();
+
+        assert_eq!(sum.read_to_vec(&context), vec![data.iter().sum::<i32>()]);
+        assert_eq!(argmin.read_to_vec(&context), vec![len as u32 - 1]);
+    }
+
+    #[test]
+    fn test_empty_reduction() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Buffer::<u32>::with_len(&context, RESULT_USAGE, 0);
+        let layout = Layout::contiguous(&[2, 0]);
+        let mut result = Buffer::<u32>::with_len(&context, RESULT_USAGE, 2);
+
+        let mut batch = context.batch();
+        batch
+            .reduce(ReduceOp::Prod, (&x, &layout), &[1], &mut result)
+            .unwrap();
+        assert_eq!(
+            batch
+                .reduce(ReduceOp::Max, (&x, &layout), &[1], &mut result)
+                .err(),
+            Some(KernelError::EmptyOperand("max"))
+        );
+        batch.submit();
+
+        assert_eq!(result.read_to_vec(&context), vec![1, 1]);
+
+        // Identities are written into the result, which must allow it.
+        let mut storage = Buffer::<u32>::with_len(&context, wgpu::BufferUsages::STORAGE, 2);
+        let mut batch = context.batch();
+        assert_eq!(
+            batch
+                .reduce(ReduceOp::Sum, (&x, &layout), &[1], &mut storage)
+                .err(),
+            Some(KernelError::Write(BufferWriteError::InvalidBufferUsage(
+                wgpu::BufferUsages::STORAGE
+            )))
+        );
+    }
+}
@@ -0,0 +1,323 @@
+use crate::{
+    backend::{batch::CommandBatch, buffers::Buffer, device::Context, traits::BufferType},
+    indexing::Layout,
+};
+
+use super::{kernel_type, BuiltinKernel, KernelError, RESULT_USAGE, WORKGROUP_SIZE};
+
+/// The elements one workgroup scans, two per invocation. Longer rows are
+/// scanned block by block, and the scanned block totals added back.
+const BLOCK_LEN: usize = 2 * WORKGROUP_SIZE as usize;
+
+/// Prefix scans, accumulating every element with those before it.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum ScanOp {
+    Sum,
+    Prod,
+}
+
+impl ScanOp {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScanOp::Sum => "cumsum",
+            ScanOp::Prod => "cumprod",
+        }
+    }
+
+    /// The WGSL identity of the scan, which is the first element of an
+    /// exclusive scan.
+    pub(crate) fn identity(&self) -> &'static str {
+        match self {
+            ScanOp::Sum => "{{T}}(0)",
+            ScanOp::Prod => "{{T}}(1)",
+        }
+    }
+
+    /// The WGSL expression accumulating `a` and `b`.
+    pub(crate) fn expression(&self) -> &'static str {
+        match self {
+            ScanOp::Sum => "a + b",
+            ScanOp::Prod => "a * b",
+        }
+    }
+}
+
+impl CommandBatch<'_> {
+    /// Records the prefix scan along `axis` of the operand, read through
+    /// `layout`, into `result`, which holds the operand shape contiguously.
+    /// An exclusive scan leaves each element out of its own result, so that
+    /// it starts with the identity of `op`.
+    pub fn scan<T: BufferType>(
+        &mut self,
+        op: ScanOp,
+        (input, layout): (&Buffer<T>, &Layout),
+        axis: usize,
+        exclusive: bool,
+        result: &mut Buffer<T>,
+    ) -> Result<&mut Self, KernelError> {
+        let ty = kernel_type::<T>(self.context().features())?;
+        if !BuiltinKernel::Scan(op).supports(ty) {
+            return Err(KernelError::UnsupportedOp { op: op.name(), ty });
+        }
+
+        let (outer, inner) = layout.split_axes(&[axis])?;
+        let (output_outer, output_inner) =
+            Layout::contiguous(layout.shape()).split_axes(&[axis])?;
+
+        if result.len() != layout.len() {
+            return Err(KernelError::LengthMismatch {
+                op: op.name(),
+                expected: layout.len(),
+                found: result.len(),
+            });
+        }
+
+        for buffer in [input, &*result] {
+            if !buffer.usage().contains(wgpu::BufferUsages::STORAGE) {
+                return Err(KernelError::InvalidBufferUsage(buffer.usage()));
+            }
+        }
+
+        if result.is_empty() {
+            return Ok(self);
+        }
+
+        let pass = ScanPass {
+            outer: (&outer, output_outer.strides()),
+            len: inner.len(),
+            strides: (inner.strides()[0], output_inner.strides()[0]),
+            exclusive,
+        };
+        self.record_scan(op, ty, &pass, input, result)
+    }
+
+    /// Scans every row in blocks, then, for rows longer than one block, scans
+    /// the `[rows, blocks]` block totals exclusively and adds them back.
+    fn record_scan<T: BufferType>(
+        &mut self,
+        op: ScanOp,
+        ty: &'static str,
+        pass: &ScanPass,
+        input: &Buffer<T>,
+        output: &Buffer<T>,
+    ) -> Result<&mut Self, KernelError> {
+        let context = self.context();
+        let rows = pass.outer.0.len();
+        let blocks = pass.len.div_ceil(BLOCK_LEN);
+
+        let sums = Buffer::<T>::with_len(context, RESULT_USAGE, rows * blocks);
+        let geometry = pass.geometry(context, blocks);
+
+        self.keep_alive(input)
+            .keep_alive(output)
+            .keep_alive(&sums)
+            .keep_alive(&geometry);
+
+        let resources = vec![
+            input.get_resource(),
+            output.get_resource(),
+            sums.get_resource(),
+            geometry.get_resource(),
+        ];
+        // NOTE: Each workgroup scans a single block.
+        let invocations = rows * blocks * WORKGROUP_SIZE as usize;
+        self.dispatch_elementwise(BuiltinKernel::Scan(op), ty, resources, invocations)?;
+
+        if blocks == 1 {
+            return Ok(self);
+        }
+
+        let offsets = Buffer::<T>::with_len(context, RESULT_USAGE, rows * blocks);
+        let (totals, _) = Layout::contiguous(&[rows, blocks]).split_axes(&[1])?;
+        let totals_pass = ScanPass {
+            outer: (&totals, totals.strides()),
+            len: blocks,
+            strides: (1, 1),
+            exclusive: true,
+        };
+        self.record_scan(op, ty, &totals_pass, &sums, &offsets)?;
+
+        self.keep_alive(&offsets);
+        let resources = vec![
+            offsets.get_resource(),
+            output.get_resource(),
+            geometry.get_resource(),
+        ];
+        self.dispatch_elementwise(BuiltinKernel::ScanAdd(op), ty, resources, rows * pass.len)
+    }
+}
+
+/// The geometry of one level of a scan, where rows are laid out by `outer`
+/// and its output strides, and scanned over `len` elements with `strides`.
+struct ScanPass<'a> {
+    outer: (&'a Layout, &'a [isize]),
+    len: usize,
+    strides: (isize, isize),
+    exclusive: bool,
+}
+
+impl ScanPass<'_> {
+    fn geometry(&self, context: &Context, blocks: usize) -> Buffer<i32> {
+        let (outer, output_strides) = self.outer;
+        let mut geometry = vec![
+            outer.ndim() as i32,
+            outer.offset() as i32,
+            self.len as i32,
+            self.strides.0 as i32,
+            self.strides.1 as i32,
+            outer.len() as i32,
+            blocks as i32,
+            self.exclusive as i32,
+        ];
+        geometry.extend(outer.shape().iter().map(|&len| len as i32));
+        geometry.extend(outer.strides().iter().map(|&stride| stride as i32));
+        geometry.extend(output_strides.iter().map(|&stride| stride as i32));
+
+        Buffer::from_vec(context, wgpu::BufferUsages::STORAGE, geometry)
+    }
+}
+
+impl<T: BufferType> Buffer<T> {
+    /// Computes the prefix scan of the buffer into a new buffer.
+    pub fn scan(&self, context: &Context, op: ScanOp, exclusive: bool) -> Buffer<T> {
+        let scan_result = self.try_scan(context, op, exclusive);
+
+        if let Err(e) = &scan_result {
+            log::error!("Failed at Buffer::{}: {}", op.name(), e);
+        }
+
+        scan_result.unwrap()
+    }
+
+    pub fn try_scan(
+        &self,
+        context: &Context,
+        op: ScanOp,
+        exclusive: bool,
+    ) -> Result<Buffer<T>, KernelError> {
+        let mut result = Buffer::with_len(context, RESULT_USAGE, self.len());
+
+        let mut batch = context.batch();
+        batch.scan(
+            op,
+            (self, &Layout::contiguous(&[self.len()])),
+            0,
+            exclusive,
+            &mut result,
+        )?;
+        batch.submit();
+
+        Ok(result)
+    }
+
+    /// Computes the inclusive cumulative sum of the buffer into a new buffer.
+    pub fn cumsum(&self, context: &Context) -> Buffer<T> {
+        self.scan(context, ScanOp::Sum, false)
+    }
+
+    pub fn try_cumsum(&self, context: &Context) -> Result<Buffer<T>, KernelError> {
+        self.try_scan(context, ScanOp::Sum, false)
+    }
+
+    /// Computes the inclusive cumulative product of the buffer into a new
+    /// buffer.
+    pub fn cumprod(&self, context: &Context) -> Buffer<T> {
+        self.scan(context, ScanOp::Prod, false)
+    }
+
+    pub fn try_cumprod(&self, context: &Context) -> Result<Buffer<T>, KernelError> {
+        self.try_scan(context, ScanOp::Prod, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backend::{buffers::Buffer, device::test_context, kernels::RESULT_USAGE},
+        indexing::{IndexError, Layout},
+    };
+
+    use super::{KernelError, ScanOp, BLOCK_LEN};
+
+    #[test]
+    fn test_scan_levels() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        // Long enough for the block totals to be scanned in blocks as well.
+        let len = BLOCK_LEN * BLOCK_LEN * 2 + 11;
+        let data = (0..len).map(|i| (i % 7) as u32).collect::<Vec<_>>();
+        let x = Buffer::from_vec(&context, RESULT_USAGE, data.clone());
+
+        let inclusive = data
+            .iter()
+            .scan(0, |sum, &v| {
+                *sum += v;
+                Some(*sum)
+            })
+            .collect::<Vec<_>>();
+        let exclusive = [0].into_iter().chain(inclusive[..len - 1].iter().copied());
+
+        assert_eq!(x.cumsum(&context).read_to_vec(&context), inclusive);
+        assert_eq!(
+            x.scan(&context, ScanOp::Sum, true).read_to_vec(&context),
+            exclusive.collect::<Vec<_>>()
+        );
+
+        let y = Buffer::from_vec(&context, RESULT_USAGE, vec![1.5f32, -2., 0.5, 4.]);
+        assert_eq!(
+            y.cumprod(&context).read_to_vec(&context),
+            vec![1.5, -3., -1.5, -6.]
+        );
+        assert_eq!(
+            y.scan(&context, ScanOp::Prod, true).read_to_vec(&context),
+            vec![1., 1.5, -3., -1.5]
+        );
+    }
+
+    #[test]
+    fn test_scan_axis() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let x = Buffer::from_vec(&context, RESULT_USAGE, (0..12).collect::<Vec<i32>>());
+        let layout = Layout::contiguous(&[3, 4]);
+
+        let cases = [
+            (
+                layout.clone(),
+                0,
+                vec![0, 1, 2, 3, 4, 6, 8, 10, 12, 15, 18, 21],
+            ),
+            (
+                layout.clone(),
+                1,
+                vec![0, 1, 3, 6, 4, 9, 15, 22, 8, 17, 27, 38],
+            ),
+            // The transposed operand is scanned into a contiguous [4, 3] result.
+            (layout.t(), 1, vec![0, 4, 12, 1, 6, 15, 2, 8, 18, 3, 10, 21]),
+        ];
+
+        for (layout, axis, expected) in cases {
+            let mut result = Buffer::<i32>::with_len(&context, RESULT_USAGE, 12);
+            let mut batch = context.batch();
+            batch
+                .scan(ScanOp::Sum, (&x, &layout), axis, false, &mut result)
+                .unwrap();
+            batch.submit();
+
+            assert_eq!(result.read_to_vec(&context), expected, "axis {}", axis);
+        }
+
+        let mut result = Buffer::<i32>::with_len(&context, RESULT_USAGE, 12);
+        let mut batch = context.batch();
+        assert_eq!(
+            batch
+                .scan(ScanOp::Sum, (&x, &layout), 2, false, &mut result)
+                .err(),
+            Some(KernelError::Shape(IndexError::InvalidAxes(vec![2], 2)))
+        );
+    }
+}
@@ -0,0 +1,323 @@
+use crate::{
+    backend::{batch::CommandBatch, buffers::Buffer, device::Context, traits::BufferType},
+    indexing::Layout,
+};
+
+use super::{kernel_type, BuiltinKernel, KernelError, ScanOp, RESULT_USAGE, WORKGROUP_SIZE};
+
+/// The bits of the key sorted by each pass.
+const RADIX_BITS: u32 = 4;
+const RADIX: usize = 1 << RADIX_BITS;
+/// The number of passes over 32-bit keys. Being even, the last pass writes
+/// back to where the first one read from.
+const PASSES: u32 = 32 / RADIX_BITS;
+
+/// The WGSL expression mapping the bits `b` of a key of type `ty` to a `u32`
+/// ordered like the keys, or `None` if such keys cannot be sorted.
+///
+/// Signed integers flip their sign bit. Floats flip their sign bit when
+/// positive and every bit when negative, which orders negative floats in
+/// reverse, before positive ones.
+pub(crate) fn radix_key(ty: &str) -> Option<&'static str> {
+    match ty {
+        "u32" => Some("b"),
+        "i32" => Some("b ^ 0x80000000u"),
+        "f32" => Some("select(b ^ 0x80000000u, ~b, (b >> 31u) == 1u)"),
+        _ => None,
+    }
+}
+
+impl CommandBatch<'_> {
+    /// Records a stable sort of `keys` in ascending order, in place. When
+    /// given, `values` are permuted along with their keys.
+    pub fn sort<T: BufferType>(
+        &mut self,
+        keys: &mut Buffer<T>,
+        values: Option<&mut Buffer<u32>>,
+    ) -> Result<&mut Self, KernelError> {
+        let values = values
+            .map(|values| &*values)
+            .map(|values| (Some(values), values));
+        self.record_sort(keys, keys, values)
+    }
+
+    /// Records the positions that sort `keys` stably in ascending order into
+    /// `indices`, leaving `keys` unchanged.
+    pub fn argsort<T: BufferType>(
+        &mut self,
+        keys: &Buffer<T>,
+        indices: &mut Buffer<u32>,
+    ) -> Result<&mut Self, KernelError> {
+        let sorted_keys = Buffer::<T>::with_len(self.context(), RESULT_USAGE, keys.len());
+        self.record_sort(keys, &sorted_keys, Some((None, indices)))
+    }
+
+    /// Sorts `input` into `output` over passes alternating between a scratch
+    /// buffer and `output`. Values are read from the first buffer of `values`,
+    /// or initialized to the key positions, and written to the second.
+    fn record_sort<T: BufferType>(
+        &mut self,
+        input: &Buffer<T>,
+        output: &Buffer<T>,
+        values: Option<(Option<&Buffer<u32>>, &Buffer<u32>)>,
+    ) -> Result<&mut Self, KernelError> {
+        let ty = kernel_type::<T>(self.context().features())?;
+        if radix_key(ty).is_none() {
+            return Err(KernelError::UnsupportedOp { op: "sort", ty });
+        }
+
+        let len = input.len();
+        let (values_in, values_out) = values.unzip();
+        let values_in = values_in.flatten();
+        let lengths = [values_in, values_out].map(|values| values.map(Buffer::len));
+        for found in [Some(output.len())].into_iter().chain(lengths).flatten() {
+            if found != len {
+                return Err(KernelError::LengthMismatch {
+                    op: "sort",
+                    expected: len,
+                    found,
+                });
+            }
+        }
+
+        let usages = [Some(input.usage()), Some(output.usage())]
+            .into_iter()
+            .chain([values_in, values_out].map(|values| values.map(Buffer::usage)));
+        for usage in usages.flatten() {
+            if !usage.contains(wgpu::BufferUsages::STORAGE) {
+                return Err(KernelError::InvalidBufferUsage(usage));
+            }
+        }
+
+        if len == 0 {
+            return Ok(self);
+        }
+
+        let context = self.context();
+        let blocks = len.div_ceil(WORKGROUP_SIZE as usize);
+        let scratch_keys = Buffer::<T>::with_len(context, RESULT_USAGE, len);
+        let keys = [&scratch_keys, output];
+
+        // Without values, the kernel still binds buffers it never touches.
+        let scratch_len = if values.is_some() { len } else { 1 };
+        let scratch_values = Buffer::<u32>::with_len(context, RESULT_USAGE, scratch_len);
+        let no_values = Buffer::<u32>::with_len(context, RESULT_USAGE, 1);
+        let values = [&scratch_values, values_out.unwrap_or(&no_values)];
+
+        let counts = Buffer::<u32>::with_len(context, RESULT_USAGE, RADIX * blocks);
+        let mut offsets = Buffer::<u32>::with_len(context, RESULT_USAGE, RADIX * blocks);
+        let counts_layout = Layout::contiguous(&[RADIX * blocks]);
+
+        for pass in 0..PASSES {
+            let (src_keys, src_values) = match pass {
+                0 => (input, values_in.unwrap_or(&no_values)),
+                _ => (
+                    keys[(pass as usize + 1) % 2],
+                    values[(pass as usize + 1) % 2],
+                ),
+            };
+            let (dst_keys, dst_values) = (keys[pass as usize % 2], values[pass as usize % 2]);
+
+            let geometry = vec![
+                len as i32,
+                blocks as i32,
+                (pass * RADIX_BITS) as i32,
+                (pass > 0 || values_in.is_some()) as i32,
+                values_out.is_some() as i32,
+            ];
+            let geometry = Buffer::from_vec(context, wgpu::BufferUsages::STORAGE, geometry);
+
+            self.keep_alive(src_keys)
+                .keep_alive(src_values)
+                .keep_alive(dst_keys)
+                .keep_alive(dst_values)
+                .keep_alive(&counts)
+                .keep_alive(&geometry);
+
+            // NOTE: Each workgroup handles a single block of keys.
+            let invocations = blocks * WORKGROUP_SIZE as usize;
+            let resources = vec![
+                src_keys.get_resource(),
+                counts.get_resource(),
+                geometry.get_resource(),
+            ];
+            self.dispatch_elementwise(BuiltinKernel::RadixCount, ty, resources, invocations)?;
+
+            self.scan(
+                ScanOp::Sum,
+                (&counts, &counts_layout),
+                0,
+                true,
+                &mut offsets,
+            )?;
+
+            let resources = vec![
+                src_keys.get_resource(),
+                src_values.get_resource(),
+                dst_keys.get_resource(),
+                dst_values.get_resource(),
+                offsets.get_resource(),
+                geometry.get_resource(),
+            ];
+            self.dispatch_elementwise(BuiltinKernel::RadixScatter, ty, resources, invocations)?;
+        }
+
+        Ok(self)
+    }
+}
+
+impl<T: BufferType> Buffer<T> {
+    /// Sorts the buffer stably in ascending order, in place.
+    pub fn sort(&mut self, context: &Context) {
+        let sort_result = self.try_sort(context);
+
+        if let Err(e) = &sort_result {
+            log::error!("Failed at Buffer::sort: {}", e);
+        }
+
+        sort_result.unwrap()
+    }
+
+    pub fn try_sort(&mut self, context: &Context) -> Result<(), KernelError> {
+        let mut batch = context.batch();
+        batch.sort(self, None)?;
+        batch.submit();
+
+        Ok(())
+    }
+
+    /// Sorts the buffer stably in ascending order, in place, applying the
+    /// same permutation to `values`.
+    pub fn sort_pairs(&mut self, context: &Context, values: &mut Buffer<u32>) {
+        let sort_result = self.try_sort_pairs(context, values);
+
+        if let Err(e) = &sort_result {
+            log::error!("Failed at Buffer::sort_pairs: {}", e);
+        }
+
+        sort_result.unwrap()
+    }
+
+    pub fn try_sort_pairs(
+        &mut self,
+        context: &Context,
+        values: &mut Buffer<u32>,
+    ) -> Result<(), KernelError> {
+        let mut batch = context.batch();
+        batch.sort(self, Some(values))?;
+        batch.submit();
+
+        Ok(())
+    }
+
+    /// Computes the positions that sort the buffer stably in ascending order
+    /// into a new buffer.
+    pub fn argsort(&self, context: &Context) -> Buffer<u32> {
+        let argsort_result = self.try_argsort(context);
+
+        if let Err(e) = &argsort_result {
+            log::error!("Failed at Buffer::argsort: {}", e);
+        }
+
+        argsort_result.unwrap()
+    }
+
+    pub fn try_argsort(&self, context: &Context) -> Result<Buffer<u32>, KernelError> {
+        let mut indices = Buffer::with_len(context, RESULT_USAGE, self.len());
+
+        let mut batch = context.batch();
+        batch.argsort(self, &mut indices)?;
+        batch.submit();
+
+        Ok(indices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::{buffers::Buffer, device::test_context, kernels::RESULT_USAGE};
+
+    use super::KernelError;
+
+    #[test]
+    fn test_sort() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        // Spans many blocks, with plenty of repeated keys.
+        let data = (0..5000u32)
+            .map(|i| i.wrapping_mul(2654435761) >> 7)
+            .collect::<Vec<_>>();
+        let mut x = Buffer::from_vec(&context, RESULT_USAGE, data.clone());
+        x.sort(&context);
+
+        let mut expected = data.clone();
+        expected.sort();
+        assert_eq!(x.read_to_vec(&context), expected);
+
+        let data = vec![3i32, -7, 0, i32::MIN, 12, -1, i32::MAX, 3];
+        let mut y = Buffer::from_vec(&context, RESULT_USAGE, data);
+        y.sort(&context);
+        assert_eq!(
+            y.read_to_vec(&context),
+            vec![i32::MIN, -7, -1, 0, 3, 3, 12, i32::MAX]
+        );
+
+        let data = vec![0.5f32, -2., f32::INFINITY, -0.25, 8., f32::NEG_INFINITY, 0.];
+        let mut z = Buffer::from_vec(&context, RESULT_USAGE, data);
+        z.sort(&context);
+        assert_eq!(
+            z.read_to_vec(&context),
+            vec![f32::NEG_INFINITY, -2., -0.25, 0., 0.5, 8., f32::INFINITY]
+        );
+    }
+
+    #[test]
+    fn test_argsort() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let data = (0..1000)
+            .map(|i| ((i * 37) % 101) as f32 - 50.)
+            .collect::<Vec<_>>();
+        let x = Buffer::from_vec(&context, RESULT_USAGE, data.clone());
+
+        // Ties keep their original order, like the stable sort of the host.
+        let mut expected = (0..data.len() as u32).collect::<Vec<_>>();
+        expected.sort_by(|&a, &b| data[a as usize].total_cmp(&data[b as usize]));
+        assert_eq!(x.argsort(&context).read_to_vec(&context), expected);
+        assert_eq!(x.read_to_vec(&context), data);
+
+        let mut keys = Buffer::from_vec(&context, RESULT_USAGE, vec![3u32, 1, 2, 1]);
+        let mut values = Buffer::from_vec(&context, RESULT_USAGE, vec![10u32, 11, 12, 13]);
+        keys.sort_pairs(&context, &mut values);
+        assert_eq!(keys.read_to_vec(&context), vec![1, 1, 2, 3]);
+        assert_eq!(values.read_to_vec(&context), vec![11, 13, 12, 10]);
+    }
+
+    #[test]
+    fn test_sort_errors() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let mut x = Buffer::from_vec(&context, RESULT_USAGE, vec![1u8, 2]);
+        assert!(matches!(
+            x.try_sort(&context),
+            Err(KernelError::UnsupportedOp { op: "sort", .. })
+        ));
+
+        let mut keys = Buffer::from_vec(&context, RESULT_USAGE, vec![1f32, 2., 3.]);
+        let mut values = Buffer::from_vec(&context, RESULT_USAGE, vec![0u32; 2]);
+        assert_eq!(
+            keys.try_sort_pairs(&context, &mut values).err(),
+            Some(KernelError::LengthMismatch {
+                op: "sort",
+                expected: 3,
+                found: 2
+            })
+        );
+    }
+}
@@ -0,0 +1,480 @@
+use std::{borrow::Cow, io::Read};
+
+use super::KernelError;
+use crate::backend::{device::Context, traits::BufferType};
+
+/// A WGSL source instantiated per element type and workgroup size.
+///
+/// The placeholders replaced in the source are:
+///
+/// - `{{T}}`, the WGSL type of the elements.
+/// - `{{WORKGROUP_SIZE}}`, the arguments of `@workgroup_size`, like `8, 8, 1`.
+/// - `{{WORKGROUP_SIZE_X}}`, `{{WORKGROUP_SIZE_Y}}` and `{{WORKGROUP_SIZE_Z}}`,
+///   each dimension of the workgroup size as an unsuffixed integer literal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShaderTemplate {
+    source: Cow<'static, str>,
+}
+
+impl ShaderTemplate {
+    pub fn new(source: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            source: source.into(),
+        }
+    }
+
+    pub fn load(template_file: &str) -> Result<Self, std::io::Error> {
+        let mut source = String::new();
+        std::fs::File::open(template_file)?.read_to_string(&mut source)?;
+
+        Ok(Self::new(source))
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Instantiates the template for elements of type `T`, for dispatches
+    /// with `workgroup_size` invocations per workgroup.
+    pub fn instantiate<T: BufferType>(
+        &self,
+        context: &Context,
+        workgroup_size: (u32, u32, u32),
+    ) -> String {
+        let instantiate_result = self.try_instantiate::<T>(context, workgroup_size);
+
+        if let Err(e) = &instantiate_result {
+            log::error!("Failed at ShaderTemplate::instantiate: {}", e);
+        }
+
+        instantiate_result.unwrap()
+    }
+
+    pub fn try_instantiate<T: BufferType>(
+        &self,
+        context: &Context,
+        workgroup_size: (u32, u32, u32),
+    ) -> Result<String, KernelError> {
+        let ty = wgsl_type::<T>(context.features())?;
+        if !valid_workgroup_size(&context.limits(), workgroup_size) {
+            return Err(KernelError::InvalidWorkgroupSize(workgroup_size));
+        }
+
+        Ok(self.instantiate_as(ty, workgroup_size))
+    }
+
+    /// Instantiates the template for the WGSL type `ty`, without checking it
+    /// against a device.
+    pub(crate) fn instantiate_as(&self, ty: &str, workgroup_size: (u32, u32, u32)) -> String {
+        let (x, y, z) = workgroup_size;
+
+        self.source
+            .replace("{{T}}", ty)
+            .replace("{{WORKGROUP_SIZE}}", &format!("{}, {}, {}", x, y, z))
+            .replace("{{WORKGROUP_SIZE_X}}", &x.to_string())
+            .replace("{{WORKGROUP_SIZE_Y}}", &y.to_string())
+            .replace("{{WORKGROUP_SIZE_Z}}", &z.to_string())
+    }
+}
+
+/// The WGSL type elements of type `T` are stored as, on a device with
+/// `features`.
+pub(crate) fn wgsl_type<T: BufferType>(
+    features: wgpu::Features,
+) -> Result<&'static str, KernelError> {
+    let ty = T::WGSL_TYPE.ok_or(KernelError::UnsupportedType(std::any::type_name::<T>()))?;
+
+    let feature = required_features(ty);
+    if !features.contains(feature) {
+        return Err(KernelError::MissingFeature { ty, feature });
+    }
+
+    Ok(ty)
+}
+
+/// The type kernels generated by the crate compute elements of type `T` as,
+/// which is either a WGSL type or an [`Emulation`] of one.
+pub(crate) fn kernel_type<T: BufferType>(
+    features: wgpu::Features,
+) -> Result<&'static str, KernelError> {
+    match T::EMULATED_TYPE {
+        Some(ty) => Ok(ty),
+        None => wgsl_type::<T>(features),
+    }
+}
+
+/// How kernels store elements that WGSL has no usable scalar type for.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub(crate) enum Emulation {
+    /// `32 / bits` elements per `u32`, first element in the lowest bits.
+    /// Kernels widen them to `i32` or `u32` as they read them in place, and
+    /// narrow results back as they write them.
+    Packed { bits: u32, signed: bool },
+    /// Two IEEE binary16 or bfloat16 floats per `u32`, first element in the
+    /// lowest bits. Kernels widen them to `f32`, like packed integers, and
+    /// round results back to the nearest, ties to even.
+    Half { bfloat: bool },
+    /// One `vec2<u32>` per element, low word first, with the arithmetic of
+    /// `wide.wgsl`.
+    Wide { signed: bool },
+    /// One `vec2<f32>` per complex number, real part first, with the
+    /// arithmetic of `complex.wgsl`.
+    Complex,
+}
+
+impl Emulation {
+    /// The 32-bit WGSL type packed elements are widened to.
+    pub(crate) fn unpacked_type(&self) -> &'static str {
+        match self {
+            Emulation::Packed { signed: true, .. } => "i32",
+            Emulation::Half { .. } => "f32",
+            Emulation::Complex => "vec2<f32>",
+            _ => "u32",
+        }
+    }
+
+    /// The bits of the `u32` each packed element is stored in.
+    pub(crate) fn bits(&self) -> u32 {
+        match self {
+            Emulation::Packed { bits, .. } => *bits,
+            Emulation::Half { .. } => 16,
+            Emulation::Wide { .. } | Emulation::Complex => 64,
+        }
+    }
+
+    /// The WGSL expression widening the packed element at bit `offset` of the
+    /// `u32` `word` to the unpacked type.
+    pub(crate) fn unpack_expression(&self) -> &'static str {
+        match self {
+            Emulation::Packed { .. } => "extractBits(bitcast<{{T}}>(word), offset, BITS)",
+            Emulation::Half { bfloat: false } => "unpack2x16float(word >> offset).x",
+            Emulation::Half { bfloat: true } => {
+                "bitcast<f32>(extractBits(word, offset, 16u) << 16u)"
+            }
+            Emulation::Wide { .. } | Emulation::Complex => {
+                unreachable!("only 8- and 16-bit elements are packed")
+            }
+        }
+    }
+
+    /// The WGSL expression narrowing `value`, of the unpacked type, to a
+    /// `u32` holding the packed element in its lowest bits. Halves are
+    /// narrowed by the functions of the half prelude.
+    pub(crate) fn pack_expression(&self) -> &'static str {
+        match self {
+            Emulation::Packed { .. } => "bitcast<u32>(value)",
+            Emulation::Half { bfloat: false } => "pack_f16(value)",
+            Emulation::Half { bfloat: true } => "pack_bf16(value)",
+            Emulation::Wide { .. } | Emulation::Complex => {
+                unreachable!("only 8- and 16-bit elements are packed")
+            }
+        }
+    }
+}
+
+/// The emulation of the kernel type `ty`, or `None` for WGSL types.
+pub(crate) fn emulation(ty: &str) -> Option<Emulation> {
+    let emulation = match ty {
+        "u8" => Emulation::Packed {
+            bits: 8,
+            signed: false,
+        },
+        "i8" => Emulation::Packed {
+            bits: 8,
+            signed: true,
+        },
+        "u16" => Emulation::Packed {
+            bits: 16,
+            signed: false,
+        },
+        "i16" => Emulation::Packed {
+            bits: 16,
+            signed: true,
+        },
+        "f16" => Emulation::Half { bfloat: false },
+        "bf16" => Emulation::Half { bfloat: true },
+        "u64" => Emulation::Wide { signed: false },
+        "i64" => Emulation::Wide { signed: true },
+        "complex<f32>" => Emulation::Complex,
+        _ => return None,
+    };
+
+    Some(emulation)
+}
+
+/// An array of elements bound by a kernel template, declared with the type
+/// `array<{{NAME}}>` for its name in capitals. The kernel reads it with
+/// `load_{name}(index)`, writes it with `store_{name}(index, value)` and gets
+/// its length in elements with `len_{name}()`.
+///
+/// Packed elements are read and written in place. Their writes clear and set
+/// the bits of the element atomically, since other invocations may write the
+/// rest of the word, and the length counts the padding of the last word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ElementArray {
+    name: &'static str,
+    writable: bool,
+    /// Whether packed elements are stored one per word instead, which keeps
+    /// partial results from rounding or wrapping around.
+    unpacked: bool,
+}
+
+const LOAD_ACCESSOR: &str = "
+fn load_{{NAME}}(index: u32) -> {{T}} {
+    return {{NAME}}[index];
+}
+";
+
+const STORE_ACCESSOR: &str = "
+fn store_{{NAME}}(index: u32, value: {{T}}) {
+    {{NAME}}[index] = value;
+}
+";
+
+const LEN_ACCESSOR: &str = "
+fn len_{{NAME}}() -> u32 {
+    return arrayLength(&{{NAME}});
+}
+";
+
+const PACKED_LOAD_ACCESSOR: &str = "
+fn load_{{NAME}}(index: u32) -> {{T}} {
+    let word = {{NAME}}[index / LANES];
+    let offset = (index % LANES) * BITS;
+    return {{UNPACK}};
+}
+";
+
+const PACKED_STORE_ACCESSOR: &str = "
+fn store_{{NAME}}(index: u32, value: {{T}}) {
+    let offset = (index % LANES) * BITS;
+    atomicAnd(&{{NAME}}[index / LANES], ~insertBits(0u, 0xffffffffu, offset, BITS));
+    atomicOr(&{{NAME}}[index / LANES], insertBits(0u, {{PACK}}, offset, BITS));
+}
+";
+
+const PACKED_LEN_ACCESSOR: &str = "
+fn len_{{NAME}}() -> u32 {
+    return arrayLength(&{{NAME}}) * LANES;
+}
+";
+
+impl ElementArray {
+    pub(crate) fn read(name: &'static str) -> Self {
+        Self {
+            name,
+            writable: false,
+            unpacked: false,
+        }
+    }
+
+    pub(crate) fn write(name: &'static str) -> Self {
+        Self {
+            name,
+            writable: true,
+            unpacked: false,
+        }
+    }
+
+    /// Stores packed elements one per word, as their unpacked type.
+    pub(crate) fn unpacked(self) -> Self {
+        Self {
+            unpacked: true,
+            ..self
+        }
+    }
+
+    /// The emulation the array packs elements of the kernel type `ty` with.
+    fn packing(&self, ty: &str) -> Option<Emulation> {
+        match emulation(ty) {
+            Some(emulation @ (Emulation::Packed { .. } | Emulation::Half { .. }))
+                if !self.unpacked =>
+            {
+                Some(emulation)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Replaces the type placeholder of each of `arrays` in `source`, and
+/// appends their accessors for elements of the kernel type `ty`.
+pub(crate) fn with_element_arrays(source: &str, ty: &str, arrays: &[ElementArray]) -> String {
+    let mut source = source.to_string();
+    let mut accessors = String::new();
+
+    for array in arrays {
+        let placeholder = format!("{{{{{}}}}}", array.name.to_uppercase());
+        let (stored, snippets) = match (array.packing(ty), array.writable) {
+            (Some(_), false) => ("u32", [PACKED_LOAD_ACCESSOR, PACKED_LEN_ACCESSOR]),
+            (Some(_), true) => ("atomic<u32>", [PACKED_STORE_ACCESSOR, PACKED_LEN_ACCESSOR]),
+            (None, false) => ("{{T}}", [LOAD_ACCESSOR, LEN_ACCESSOR]),
+            (None, true) => ("{{T}}", [STORE_ACCESSOR, LEN_ACCESSOR]),
+        };
+
+        source = source.replace(&placeholder, stored);
+        for snippet in snippets {
+            accessors.push_str(&snippet.replace("{{NAME}}", array.name));
+        }
+    }
+
+    if let Some(emulation) = arrays.iter().find_map(|array| array.packing(ty)) {
+        accessors = format!(
+            "const BITS: u32 = {}u;\nconst LANES: u32 = 32u / BITS;\n{}",
+            emulation.bits(),
+            accessors
+                .replace("{{UNPACK}}", emulation.unpack_expression())
+                .replace("{{PACK}}", emulation.pack_expression())
+        );
+    }
+
+    format!("{}\n{}", source, accessors)
+}
+
+/// The device features shaders need to use the WGSL type `ty`.
+fn required_features(ty: &str) -> wgpu::Features {
+    match ty {
+        "f64" => wgpu::Features::SHADER_F64,
+        _ => wgpu::Features::empty(),
+    }
+}
+
+fn valid_workgroup_size(limits: &wgpu::Limits, (x, y, z): (u32, u32, u32)) -> bool {
+    let invocations = x as u64 * y as u64 * z as u64;
+
+    invocations > 0
+        && x <= limits.max_compute_workgroup_size_x
+        && y <= limits.max_compute_workgroup_size_y
+        && z <= limits.max_compute_workgroup_size_z
+        && invocations <= limits.max_compute_invocations_per_workgroup as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::{
+        buffers::Buffer,
+        device::test_context,
+        pipeline::{ComputePipeline, KernelArgument},
+    };
+
+    use super::{
+        emulation, kernel_type, valid_workgroup_size, wgsl_type, Emulation, KernelError,
+        ShaderTemplate,
+    };
+
+    const SCALE_TEMPLATE: &str = r#"
+        @group(0) @binding(0) var<storage, read_write> values: array<{{T}}>;
+
+        @compute @workgroup_size({{WORKGROUP_SIZE}})
+        fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+            let index = global_id.x + global_id.y * {{WORKGROUP_SIZE_X}}u;
+            if index < arrayLength(&values) {
+                values[index] = values[index] * {{T}}(3);
+            }
+        }
+    "#;
+
+    #[test]
+    fn test_wgsl_types() {
+        let none = wgpu::Features::empty();
+        assert_eq!(wgsl_type::<f32>(none), Ok("f32"));
+        assert_eq!(wgsl_type::<i32>(none), Ok("i32"));
+        assert_eq!(wgsl_type::<u32>(none), Ok("u32"));
+
+        assert_eq!(
+            wgsl_type::<f64>(none),
+            Err(KernelError::MissingFeature {
+                ty: "f64",
+                feature: wgpu::Features::SHADER_F64
+            })
+        );
+        assert_eq!(wgsl_type::<f64>(wgpu::Features::SHADER_F64), Ok("f64"));
+        assert_eq!(
+            wgsl_type::<u64>(none),
+            Err(KernelError::UnsupportedType("u64"))
+        );
+
+        // Kernels of the crate emulate the integers WGSL lacks.
+        assert_eq!(kernel_type::<u64>(none), Ok("u64"));
+        assert_eq!(kernel_type::<i8>(none), Ok("i8"));
+        assert_eq!(kernel_type::<f32>(none), Ok("f32"));
+        assert_eq!(
+            emulation("i16"),
+            Some(Emulation::Packed {
+                bits: 16,
+                signed: true
+            })
+        );
+        assert_eq!(emulation("i64"), Some(Emulation::Wide { signed: true }));
+        assert_eq!(emulation("i32"), None);
+
+        // Halves are always packed, whatever the device features.
+        assert_eq!(
+            wgsl_type::<half::f16>(wgpu::Features::SHADER_F16),
+            Err(KernelError::UnsupportedType("half::binary16::f16"))
+        );
+        assert_eq!(kernel_type::<half::f16>(none), Ok("f16"));
+        assert_eq!(kernel_type::<half::bf16>(none), Ok("bf16"));
+        assert_eq!(emulation("bf16"), Some(Emulation::Half { bfloat: true }));
+
+        // Complex numbers are vectors in WGSL, with their own arithmetic.
+        type Complex = num_complex::Complex<f32>;
+        assert_eq!(wgsl_type::<Complex>(none), Ok("vec2<f32>"));
+        assert_eq!(kernel_type::<Complex>(none), Ok("complex<f32>"));
+        assert_eq!(emulation("complex<f32>"), Some(Emulation::Complex));
+    }
+
+    #[test]
+    fn test_workgroup_sizes() {
+        let limits = wgpu::Limits::default();
+
+        assert!(valid_workgroup_size(&limits, (64, 1, 1)));
+        assert!(valid_workgroup_size(&limits, (16, 16, 1)));
+        assert!(!valid_workgroup_size(&limits, (0, 1, 1)));
+        assert!(!valid_workgroup_size(&limits, (1, 1, 128)));
+        assert!(!valid_workgroup_size(&limits, (32, 32, 1)));
+    }
+
+    #[test]
+    fn test_instantiate() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let template = ShaderTemplate::new(SCALE_TEMPLATE);
+        let source = template.instantiate::<i32>(&context, (8, 8, 1));
+        assert!(source.contains("array<i32>"));
+        assert!(source.contains("@workgroup_size(8, 8, 1)"));
+        assert!(source.contains("global_id.y * 8u"));
+
+        assert_eq!(
+            template.try_instantiate::<u8>(&context, (64, 1, 1)),
+            Err(KernelError::UnsupportedType("u8"))
+        );
+        assert_eq!(
+            template.try_instantiate::<f32>(&context, (4096, 1, 1)),
+            Err(KernelError::InvalidWorkgroupSize((4096, 1, 1)))
+        );
+
+        let usage = wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC;
+        let values = Buffer::from_vec(&context, usage, (0..100).collect::<Vec<u32>>());
+        for (workgroup_size, workgroups) in [((64, 1, 1), (2, 1, 1)), ((8, 8, 1), (1, 2, 1))] {
+            let pipeline = ComputePipeline::builder(
+                template.instantiate::<u32>(&context, workgroup_size),
+                "main",
+            )
+            .build(&context);
+
+            let mut batch = context.batch();
+            pipeline
+                .dispatch(&mut batch, &[&[&values as &dyn KernelArgument]], workgroups)
+                .unwrap();
+            batch.submit();
+        }
+
+        assert_eq!(
+            values.read_to_vec(&context),
+            (0..100).map(|x| x * 9).collect::<Vec<u32>>()
+        );
+    }
+}
@@ -3,8 +3,28 @@
 //! over on their GitHub and website! A lot of techniques used in this module are
 //! heavily inspired by their repo [sotrh/learn-wgpu](https://github.com/sotrh/learn-wgpu).
 
-mod buffers;
-mod device;
+pub(crate) mod adapter;
+pub(crate) mod batch;
+pub(crate) mod buffers;
+pub(crate) mod device;
+pub(crate) mod kernels;
 mod pipeline;
-mod traits;
-mod util;
+pub(crate) mod poller;
+pub(crate) mod traits;
+pub(crate) mod util;
+
+pub use self::{
+    adapter::{AdapterDescription, AdapterSelector},
+    batch::{CommandBatch, SubmissionFence},
+    buffers::PoolStats,
+    device::{Context, ContextBuilder, ContextError},
+    kernels::{
+        Abs, ArgReduceOp, BinaryOp, ComplexPart, Gemm, KernelError, ReduceOp, ScanOp,
+        ShaderTemplate, UnaryOp,
+    },
+    pipeline::{
+        ComputePipeline, ComputePipelineBuilder, Kernel, KernelArgument, PipelineConfiguration,
+        PipelineExecutionError, PipelineLoadingError,
+    },
+    traits::BufferType,
+};
@@ -0,0 +1,144 @@
+use std::{collections::HashMap, sync::Arc};
+
+use parking_lot::Mutex;
+
+use super::{config::PipelineConfiguration, CompiledPipeline};
+
+/// Identifies a pipeline by everything that affects its compilation, so that
+/// identical pipelines are only compiled once.
+///
+/// The source itself is part of the key, rather than a hash of it, so that
+/// distinct shaders can never share a pipeline.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct PipelineKey {
+    source: Arc<str>,
+    entry_point: String,
+    /// The configured layouts of the bind groups, or `None` when they are
+    /// inferred from the source.
+    layouts: Option<Vec<Vec<wgpu::BindGroupLayoutEntry>>>,
+    /// The overridden constants, sorted by name, with their values as bits.
+    constants: Vec<(String, u64)>,
+}
+
+impl PipelineKey {
+    pub(crate) fn new(
+        source: &str,
+        entry_point: &str,
+        configs: &[PipelineConfiguration],
+        constants: &HashMap<String, f64>,
+    ) -> Self {
+        let layouts = match configs.is_empty() {
+            true => None,
+            false => Some(
+                configs
+                    .iter()
+                    .map(|config| config.entries().to_vec())
+                    .collect(),
+            ),
+        };
+
+        let mut constants = constants
+            .iter()
+            .map(|(name, value)| (name.clone(), value.to_bits()))
+            .collect::<Vec<_>>();
+        constants.sort();
+
+        Self {
+            source: source.into(),
+            entry_point: entry_point.to_string(),
+            layouts,
+            constants,
+        }
+    }
+}
+
+/// The pipelines compiled on a [`Context`](crate::Context), by key.
+///
+/// Pipelines only live as long as the context. wgpu 0.20 can neither persist
+/// compiled pipelines nor be handed serialized shader IR, so every process
+/// compiles its pipelines again.
+#[derive(Debug, Default)]
+pub(crate) struct PipelineCache {
+    pipelines: Mutex<HashMap<PipelineKey, Arc<CompiledPipeline>>>,
+}
+
+impl PipelineCache {
+    pub(crate) fn get(&self, key: &PipelineKey) -> Option<Arc<CompiledPipeline>> {
+        self.pipelines.lock().get(key).cloned()
+    }
+
+    /// Caches `pipeline`, unless an identical one was compiled meanwhile, and
+    /// returns the cached pipeline.
+    pub(crate) fn insert(
+        &self,
+        key: PipelineKey,
+        pipeline: Arc<CompiledPipeline>,
+    ) -> Arc<CompiledPipeline> {
+        self.pipelines.lock().entry(key).or_insert(pipeline).clone()
+    }
+
+    /// The number of pipelines compiled so far.
+    pub(crate) fn len(&self) -> usize {
+        self.pipelines.lock().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::backend::{
+        device::test_context,
+        pipeline::{ComputePipeline, PipelineConfiguration},
+    };
+
+    const ADD_SHADER: &str = include_str!("../../shaders/add.wgsl");
+    const SCALE_SHADER: &str = r#"
+        override scale: f32 = 1.0;
+        @group(0) @binding(0) var<storage, read_write> values: array<f32>;
+
+        @compute @workgroup_size(1)
+        fn main() {
+            values[0] = values[0] * scale;
+        }
+    "#;
+
+    #[test]
+    fn test_identical_pipelines() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        // Handles share the compiled pipeline, but keep their own labels.
+        let first = ComputePipeline::builder(ADD_SHADER, "add").build(&context);
+        let second = ComputePipeline::builder(ADD_SHADER.to_string(), "add")
+            .label("other")
+            .build(&context);
+        assert!(Arc::ptr_eq(&first.inner, &second.inner));
+        assert_eq!(first.label(), "add");
+        assert_eq!(second.label(), "other");
+        assert_eq!(context.pipelines().len(), 1);
+
+        // Configured layouts and constants are part of the key.
+        let config = PipelineConfiguration::load("src/shaders/add.hjson").unwrap();
+        ComputePipeline::builder(ADD_SHADER, "add")
+            .bind_group(config)
+            .build(&context);
+        assert_eq!(context.pipelines().len(), 2);
+
+        ComputePipeline::builder(SCALE_SHADER, "main").build(&context);
+        ComputePipeline::builder(SCALE_SHADER, "main")
+            .constant("scale", 2.)
+            .build(&context);
+        ComputePipeline::builder(SCALE_SHADER, "main")
+            .constant("scale", 2.)
+            .build(&context);
+        assert_eq!(context.pipelines().len(), 4);
+
+        // Sources differing in a single character are distinct.
+        let other_source = SCALE_SHADER.replace("1.0", "3.0");
+        let third = ComputePipeline::builder(other_source, "main").build(&context);
+        assert_eq!(context.pipelines().len(), 5);
+        assert!(!Arc::ptr_eq(&first.inner, &third.inner));
+    }
+}
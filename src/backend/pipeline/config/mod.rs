@@ -1,9 +1,11 @@
-use std::io::Read;
+use std::{io::Read, num::NonZeroU32};
 
 use self::inner_types::BindGroupLayoutEntry;
 use serde::Deserialize;
 
 mod inner_types;
+mod reflect;
+mod write;
 
 #[derive(Debug, thiserror::Error)]
 pub enum PipelineLoadingError {
@@ -11,9 +13,33 @@ pub enum PipelineLoadingError {
     IOError(#[from] std::io::Error),
     #[error("Failed to Deserialize: {0}")]
     DeserializeError(#[from] deser_hjson::Error),
+    #[error("Failed to parse WGSL: {0}")]
+    ParseError(#[from] wgpu::naga::front::wgsl::ParseError),
+    #[error("Failed to compile the pipeline: {0}")]
+    CompilationError(String),
+    #[error("Binding {binding} of group {group} has a type that cannot be put in a layout.")]
+    UnsupportedBinding { group: u32, binding: u32 },
+    #[error("Binding {binding} of group {group} is configured, but not declared by the shader.")]
+    MissingBinding { group: u32, binding: u32 },
+    #[error("Binding {binding} of group {group} is declared by the shader, but not configured.")]
+    UnconfiguredBinding { group: u32, binding: u32 },
+    #[error("Binding {binding} of group {group} is configured as {configured:?}, but declared as {declared:?}.")]
+    BindingMismatch {
+        group: u32,
+        binding: u32,
+        configured: wgpu::BindingType,
+        declared: wgpu::BindingType,
+    },
+    #[error("Binding {binding} of group {group} is configured with a count of {configured:?}, but declared with {declared:?}.")]
+    CountMismatch {
+        group: u32,
+        binding: u32,
+        configured: Option<NonZeroU32>,
+        declared: Option<NonZeroU32>,
+    },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PipelineConfiguration {
     layout_entries: Vec<wgpu::BindGroupLayoutEntry>,
 }
@@ -41,7 +67,255 @@ impl PipelineConfiguration {
         Ok(config)
     }
 
+    pub fn from_entries(layout_entries: Vec<wgpu::BindGroupLayoutEntry>) -> Self {
+        Self { layout_entries }
+    }
+
+    /// Writes the layout in the hjson format read by
+    /// [`PipelineConfiguration::load`].
+    pub fn to_hjson(&self) -> String {
+        write::write_entries(&self.layout_entries)
+    }
+
+    /// Infers the layout of bind group `group` from the `@group` and
+    /// `@binding` attributes of a WGSL module, instead of loading it.
+    pub fn reflect(source: &str, group: u32) -> Result<Self, PipelineLoadingError> {
+        let layout_entries = reflect::reflect_entries(source, group)?;
+
+        Ok(Self { layout_entries })
+    }
+
+    /// Infers the layouts of every bind group up to the last one used by a
+    /// WGSL module, see [`PipelineConfiguration::reflect`].
+    pub fn reflect_groups(source: &str) -> Result<Vec<Self>, PipelineLoadingError> {
+        (0..reflect::group_count(source)?)
+            .map(|group| Self::reflect(source, group))
+            .collect()
+    }
+
+    /// Checks the layout against bind group `group` of a WGSL module, so that
+    /// every configured binding is declared with the same type and the other
+    /// way around.
+    pub fn validate(&self, source: &str, group: u32) -> Result<(), PipelineLoadingError> {
+        let declared = reflect::reflect_entries(source, group)?;
+
+        for entry in &self.layout_entries {
+            let Some(reflected) = declared.iter().find(|d| d.binding == entry.binding) else {
+                return Err(PipelineLoadingError::MissingBinding {
+                    group,
+                    binding: entry.binding,
+                });
+            };
+
+            if !reflect::compatible(&entry.ty, &reflected.ty) {
+                return Err(PipelineLoadingError::BindingMismatch {
+                    group,
+                    binding: entry.binding,
+                    configured: entry.ty,
+                    declared: reflected.ty,
+                });
+            }
+
+            if entry.count != reflected.count {
+                return Err(PipelineLoadingError::CountMismatch {
+                    group,
+                    binding: entry.binding,
+                    configured: entry.count,
+                    declared: reflected.count,
+                });
+            }
+        }
+
+        if let Some(unconfigured) = declared.iter().find(|d| {
+            !self
+                .layout_entries
+                .iter()
+                .any(|entry| entry.binding == d.binding)
+        }) {
+            return Err(PipelineLoadingError::UnconfiguredBinding {
+                group,
+                binding: unconfigured.binding,
+            });
+        }
+
+        Ok(())
+    }
+
     pub fn entries(&self) -> &[wgpu::BindGroupLayoutEntry] {
         &self.layout_entries
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{PipelineConfiguration, PipelineLoadingError};
+
+    const ADD_SHADER: &str = include_str!("../../../shaders/add.wgsl");
+
+    #[test]
+    fn test_reflect_add() {
+        let loaded = PipelineConfiguration::load("src/shaders/add.hjson").unwrap();
+        let reflected = PipelineConfiguration::reflect(ADD_SHADER, 0).unwrap();
+
+        assert_eq!(reflected.entries(), loaded.entries());
+        loaded.validate(ADD_SHADER, 0).unwrap();
+
+        assert!(PipelineConfiguration::reflect(ADD_SHADER, 1)
+            .unwrap()
+            .entries()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_reflect_resources() {
+        let source = r#"
+            @group(0) @binding(3) var<uniform> scale: vec4<f32>;
+            @group(0) @binding(0) var colors: texture_2d<f32>;
+            @group(0) @binding(1) var depths: texture_depth_2d_array;
+            @group(0) @binding(2) var samples: sampler_comparison;
+            @group(0) @binding(4) var output: texture_storage_2d<rgba8unorm, write>;
+            @group(1) @binding(0) var<storage, read_write> counts: array<u32>;
+
+            @compute @workgroup_size(1)
+            fn main() {
+                let size = textureDimensions(colors) + textureDimensions(depths);
+                textureStore(output, size, scale * textureSampleCompareLevel(depths, samples, vec2(0.), 0, 0.));
+                counts[0] = 1u;
+            }
+        "#;
+
+        let entries = PipelineConfiguration::reflect(source, 0)
+            .unwrap()
+            .entries()
+            .to_vec();
+        assert_eq!(
+            entries.iter().map(|entry| entry.ty).collect::<Vec<_>>(),
+            vec![
+                wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Depth,
+                    view_dimension: wgpu::TextureViewDimension::D2Array,
+                    multisampled: false,
+                },
+                wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                wgpu::BindingType::StorageTexture {
+                    access: wgpu::StorageTextureAccess::WriteOnly,
+                    format: wgpu::TextureFormat::Rgba8Unorm,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                },
+            ]
+        );
+        assert!(entries
+            .iter()
+            .all(|entry| entry.visibility == wgpu::ShaderStages::COMPUTE));
+
+        let counts = PipelineConfiguration::reflect(source, 1).unwrap();
+        assert_eq!(
+            counts.entries()[0].ty,
+            wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_hjson_round_trip() {
+        let loaded = PipelineConfiguration::load("src/shaders/add.hjson").unwrap();
+        let written: PipelineConfiguration = deser_hjson::from_str(&loaded.to_hjson()).unwrap();
+        assert_eq!(written, loaded);
+
+        let entries = vec![
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::COMPUTE | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::Cube,
+                    multisampled: false,
+                },
+                count: std::num::NonZeroU32::new(4),
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::StorageTexture {
+                    access: wgpu::StorageTextureAccess::ReadWrite,
+                    format: wgpu::TextureFormat::R32Float,
+                    view_dimension: wgpu::TextureViewDimension::D3,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: std::num::NonZeroU64::new(16),
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 4,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::NonFiltering),
+                count: None,
+            },
+        ];
+        let config = PipelineConfiguration::from_entries(entries);
+        let written: PipelineConfiguration = deser_hjson::from_str(&config.to_hjson()).unwrap();
+        assert_eq!(written, config);
+    }
+
+    #[test]
+    fn test_validate_mismatches() {
+        let config = PipelineConfiguration::load("src/shaders/add.hjson").unwrap();
+
+        // The output is declared read-only.
+        let read_only = ADD_SHADER.replace("read_write", "read");
+        assert!(matches!(
+            config.validate(&read_only, 0),
+            Err(PipelineLoadingError::BindingMismatch { binding: 2, .. })
+        ));
+
+        let moved = ADD_SHADER.replace("@binding(2)", "@binding(3)");
+        assert!(matches!(
+            config.validate(&moved, 0),
+            Err(PipelineLoadingError::MissingBinding {
+                group: 0,
+                binding: 2
+            })
+        ));
+
+        let reflected = PipelineConfiguration::reflect(&moved, 0).unwrap();
+        assert!(matches!(
+            reflected.validate(ADD_SHADER, 0),
+            Err(PipelineLoadingError::MissingBinding { binding: 3, .. })
+        ));
+
+        let extra = format!(
+            "{}\n@group(0) @binding(5) var<uniform> unused: u32;",
+            ADD_SHADER
+        );
+        assert!(matches!(
+            config.validate(&extra, 0),
+            Err(PipelineLoadingError::UnconfiguredBinding { binding: 5, .. })
+        ));
+
+        assert!(matches!(
+            config.validate("fn broken(", 0),
+            Err(PipelineLoadingError::ParseError(_))
+        ));
+    }
+}
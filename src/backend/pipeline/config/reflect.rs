@@ -0,0 +1,212 @@
+use std::num::NonZeroU32;
+
+use wgpu::naga;
+
+use super::PipelineLoadingError;
+
+/// Infers the layout entries of bind group `group` from the global variables
+/// of a WGSL module, sorted by binding. Entries are visible to the stages of
+/// every entry point in the module.
+///
+/// WGSL does not tell whether float textures and samplers filter, so they are
+/// assumed to, nor whether offsets are dynamic, so they are assumed not to be.
+pub(super) fn reflect_entries(
+    source: &str,
+    group: u32,
+) -> Result<Vec<wgpu::BindGroupLayoutEntry>, PipelineLoadingError> {
+    let module = naga::front::wgsl::parse_str(source)?;
+
+    let visibility =
+        module
+            .entry_points
+            .iter()
+            .fold(wgpu::ShaderStages::NONE, |stages, entry_point| {
+                stages
+                    | match entry_point.stage {
+                        naga::ShaderStage::Vertex => wgpu::ShaderStages::VERTEX,
+                        naga::ShaderStage::Fragment => wgpu::ShaderStages::FRAGMENT,
+                        naga::ShaderStage::Compute => wgpu::ShaderStages::COMPUTE,
+                    }
+            });
+
+    let mut entries = module
+        .global_variables
+        .iter()
+        .filter_map(|(_, global)| global.binding.as_ref().map(|binding| (global, binding)))
+        .filter(|(_, binding)| binding.group == group)
+        .map(|(global, binding)| {
+            let (ty, count) = binding_type(&module, global.space, global.ty).ok_or(
+                PipelineLoadingError::UnsupportedBinding {
+                    group,
+                    binding: binding.binding,
+                },
+            )?;
+
+            Ok(wgpu::BindGroupLayoutEntry {
+                binding: binding.binding,
+                visibility,
+                ty,
+                count,
+            })
+        })
+        .collect::<Result<Vec<_>, PipelineLoadingError>>()?;
+
+    entries.sort_by_key(|entry| entry.binding);
+    Ok(entries)
+}
+
+/// The number of bind groups a WGSL module uses, which is one more than the
+/// highest group of its bindings.
+pub(super) fn group_count(source: &str) -> Result<u32, PipelineLoadingError> {
+    let module = naga::front::wgsl::parse_str(source)?;
+
+    Ok(module
+        .global_variables
+        .iter()
+        .filter_map(|(_, global)| global.binding.as_ref())
+        .map(|binding| binding.group + 1)
+        .max()
+        .unwrap_or(0))
+}
+
+/// Whether a configured binding type can be bound where a shader declares
+/// `reflected`, ignoring what WGSL cannot express.
+pub(super) fn compatible(configured: &wgpu::BindingType, reflected: &wgpu::BindingType) -> bool {
+    normalize(configured) == normalize(reflected)
+}
+
+fn normalize(ty: &wgpu::BindingType) -> wgpu::BindingType {
+    match *ty {
+        wgpu::BindingType::Buffer { ty, .. } => wgpu::BindingType::Buffer {
+            ty,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        wgpu::BindingType::Sampler(wgpu::SamplerBindingType::NonFiltering) => {
+            wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering)
+        }
+        wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { .. },
+            view_dimension,
+            multisampled,
+        } => wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension,
+            multisampled,
+        },
+        ty => ty,
+    }
+}
+
+fn binding_type(
+    module: &naga::Module,
+    space: naga::AddressSpace,
+    ty: naga::Handle<naga::Type>,
+) -> Option<(wgpu::BindingType, Option<NonZeroU32>)> {
+    let buffer = |ty| wgpu::BindingType::Buffer {
+        ty,
+        has_dynamic_offset: false,
+        min_binding_size: None,
+    };
+
+    match space {
+        naga::AddressSpace::Uniform => {
+            return Some((buffer(wgpu::BufferBindingType::Uniform), None));
+        }
+        naga::AddressSpace::Storage { access } => {
+            let read_only = !access.contains(naga::StorageAccess::STORE);
+            return Some((buffer(wgpu::BufferBindingType::Storage { read_only }), None));
+        }
+        naga::AddressSpace::Handle => {}
+        _ => return None,
+    }
+
+    match module.types[ty].inner {
+        naga::TypeInner::BindingArray {
+            base,
+            size: naga::ArraySize::Constant(size),
+        } => {
+            let (ty, _) = binding_type(module, space, base)?;
+            Some((ty, Some(size)))
+        }
+        naga::TypeInner::Sampler { comparison } => {
+            let ty = match comparison {
+                true => wgpu::SamplerBindingType::Comparison,
+                false => wgpu::SamplerBindingType::Filtering,
+            };
+            Some((wgpu::BindingType::Sampler(ty), None))
+        }
+        naga::TypeInner::Image {
+            dim,
+            arrayed,
+            class,
+        } => {
+            let view_dimension = match (dim, arrayed) {
+                (naga::ImageDimension::D1, false) => wgpu::TextureViewDimension::D1,
+                (naga::ImageDimension::D2, false) => wgpu::TextureViewDimension::D2,
+                (naga::ImageDimension::D2, true) => wgpu::TextureViewDimension::D2Array,
+                (naga::ImageDimension::D3, false) => wgpu::TextureViewDimension::D3,
+                (naga::ImageDimension::Cube, false) => wgpu::TextureViewDimension::Cube,
+                (naga::ImageDimension::Cube, true) => wgpu::TextureViewDimension::CubeArray,
+                _ => return None,
+            };
+
+            let ty = match class {
+                naga::ImageClass::Sampled { kind, multi } => wgpu::BindingType::Texture {
+                    sample_type: match kind {
+                        naga::ScalarKind::Float => {
+                            wgpu::TextureSampleType::Float { filterable: true }
+                        }
+                        naga::ScalarKind::Sint => wgpu::TextureSampleType::Sint,
+                        naga::ScalarKind::Uint => wgpu::TextureSampleType::Uint,
+                        _ => return None,
+                    },
+                    view_dimension,
+                    multisampled: multi,
+                },
+                naga::ImageClass::Depth { multi } => wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Depth,
+                    view_dimension,
+                    multisampled: multi,
+                },
+                naga::ImageClass::Storage { format, access } => {
+                    let load = access.contains(naga::StorageAccess::LOAD);
+                    let store = access.contains(naga::StorageAccess::STORE);
+                    wgpu::BindingType::StorageTexture {
+                        access: match (load, store) {
+                            (true, false) => wgpu::StorageTextureAccess::ReadOnly,
+                            (false, true) => wgpu::StorageTextureAccess::WriteOnly,
+                            _ => wgpu::StorageTextureAccess::ReadWrite,
+                        },
+                        format: texture_format(format),
+                        view_dimension,
+                    }
+                }
+            };
+            Some((ty, None))
+        }
+        _ => None,
+    }
+}
+
+macro_rules! texture_formats {
+    ($($format:ident),+ $(,)?) => {
+        fn texture_format(format: naga::StorageFormat) -> wgpu::TextureFormat {
+            match format {
+                $(naga::StorageFormat::$format => wgpu::TextureFormat::$format),+
+            }
+        }
+    };
+}
+
+// NOTE: Storage formats are named like the texture formats they map to.
+texture_formats! {
+    R8Unorm, R8Snorm, R8Uint, R8Sint,
+    R16Uint, R16Sint, R16Float, Rg8Unorm, Rg8Snorm, Rg8Uint, Rg8Sint,
+    R32Uint, R32Sint, R32Float, Rg16Uint, Rg16Sint, Rg16Float,
+    Rgba8Unorm, Rgba8Snorm, Rgba8Uint, Rgba8Sint, Bgra8Unorm,
+    Rgb10a2Uint, Rgb10a2Unorm, Rg11b10Float,
+    Rg32Uint, Rg32Sint, Rg32Float, Rgba16Uint, Rgba16Sint, Rgba16Float,
+    Rgba32Uint, Rgba32Sint, Rgba32Float,
+    R16Unorm, R16Snorm, Rg16Unorm, Rg16Snorm, Rgba16Unorm, Rgba16Snorm,
+}
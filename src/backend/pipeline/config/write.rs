@@ -0,0 +1,81 @@
+//! Writes layout entries in the hjson format read by
+//! [`PipelineConfiguration::load`](super::PipelineConfiguration::load).
+
+use std::fmt::Write;
+
+pub(super) fn write_entries(entries: &[wgpu::BindGroupLayoutEntry]) -> String {
+    let mut hjson = String::from("[\n");
+
+    for entry in entries {
+        let visibility = entry.visibility;
+        let _ = writeln!(
+            hjson,
+            "    {{\n        \"binding\": {},\n        \"visibility\": {{ \"compute\": {}, \"vertex\": {}, \"fragment\": {} }},\n        \"ty\": {},",
+            entry.binding,
+            visibility.contains(wgpu::ShaderStages::COMPUTE),
+            visibility.contains(wgpu::ShaderStages::VERTEX),
+            visibility.contains(wgpu::ShaderStages::FRAGMENT),
+            binding_type(&entry.ty),
+        );
+        if let Some(count) = entry.count {
+            let _ = writeln!(hjson, "        \"count\": {},", count);
+        }
+        hjson.push_str("    },\n");
+    }
+
+    hjson.push(']');
+    hjson
+}
+
+fn binding_type(ty: &wgpu::BindingType) -> String {
+    match *ty {
+        wgpu::BindingType::Buffer {
+            ty,
+            has_dynamic_offset,
+            min_binding_size,
+        } => {
+            let ty = match ty {
+                wgpu::BufferBindingType::Uniform => "\"Uniform\"".to_string(),
+                wgpu::BufferBindingType::Storage { read_only } => {
+                    format!("{{ \"Storage\": {{ \"read_only\": {} }} }}", read_only)
+                }
+            };
+            let min_binding_size = min_binding_size
+                .map(|size| format!(", \"min_binding_size\": {}", size))
+                .unwrap_or_default();
+
+            format!(
+                "{{ \"Buffer\": {{ \"ty\": {}, \"has_dynamic_offset\": {}{} }} }}",
+                ty, has_dynamic_offset, min_binding_size
+            )
+        }
+        wgpu::BindingType::Sampler(ty) => format!("{{ \"Sampler\": \"{:?}\" }}", ty),
+        wgpu::BindingType::Texture {
+            sample_type,
+            view_dimension,
+            multisampled,
+        } => {
+            let sample_type = match sample_type {
+                wgpu::TextureSampleType::Float { filterable } => {
+                    format!("{{ \"Float\": {{ \"filterable\": {} }} }}", filterable)
+                }
+                ty => format!("\"{:?}\"", ty),
+            };
+
+            format!(
+                "{{ \"Texture\": {{ \"sample_type\": {}, \"view_dimension\": \"{:?}\", \"multisampled\": {} }} }}",
+                sample_type, view_dimension, multisampled
+            )
+        }
+        wgpu::BindingType::StorageTexture {
+            access,
+            format,
+            view_dimension,
+        } => format!(
+            "{{ \"StorageTexture\": {{ \"access\": \"{:?}\", \"format\": \"{:?}\", \"view_dimension\": \"{:?}\" }} }}",
+            access, format, view_dimension
+        ),
+        // NOTE: Acceleration structures cannot be read back, see `inner_types`.
+        wgpu::BindingType::AccelerationStructure => "\"AccelerationStructure\"".to_string(),
+    }
+}
@@ -0,0 +1,231 @@
+use std::{borrow::Cow, io::Read};
+
+use super::{
+    config::{PipelineConfiguration, PipelineLoadingError},
+    ComputePipeline, KernelArgument, PipelineExecutionError,
+};
+use crate::backend::{batch::CommandBatch, device::Context};
+
+/// A compute shader entry point along with the layout of the buffers it
+/// binds. The pipeline is compiled on the first dispatch on each [`Context`],
+/// and reused by later dispatches of identical kernels.
+#[derive(Debug, Clone)]
+pub struct Kernel {
+    label: String,
+    source: Cow<'static, str>,
+    entry_point: String,
+    config: PipelineConfiguration,
+}
+
+impl Kernel {
+    pub fn new(
+        label: &str,
+        source: impl Into<Cow<'static, str>>,
+        entry_point: &str,
+        config: PipelineConfiguration,
+    ) -> Self {
+        Self {
+            label: label.to_string(),
+            source: source.into(),
+            entry_point: entry_point.to_string(),
+            config,
+        }
+    }
+
+    /// Infers the layout of the kernel from the bindings of group 0 declared
+    /// in `source`, see [`PipelineConfiguration::reflect`].
+    pub fn reflect(
+        label: &str,
+        source: impl Into<Cow<'static, str>>,
+        entry_point: &str,
+    ) -> Result<Self, PipelineLoadingError> {
+        let source = source.into();
+        let config = PipelineConfiguration::reflect(&source, 0)?;
+
+        Ok(Self::new(label, source, entry_point, config))
+    }
+
+    /// Loads the WGSL source of a kernel, labelled after the shader file. Its
+    /// layout is loaded from the hjson `config_file` and checked against the
+    /// shader, or inferred from the shader when `None`.
+    pub fn load(
+        shader_file: &str,
+        entry_point: &str,
+        config_file: Option<&str>,
+    ) -> Result<Self, PipelineLoadingError> {
+        let mut source = String::new();
+        std::fs::File::open(shader_file)?.read_to_string(&mut source)?;
+
+        let label = std::path::Path::new(shader_file)
+            .file_stem()
+            .map_or(shader_file.into(), |stem| stem.to_string_lossy());
+
+        match config_file {
+            Some(config_file) => {
+                let config = PipelineConfiguration::load(config_file)?;
+                config.validate(&source, 0)?;
+                Ok(Self::new(&label, source, entry_point, config))
+            }
+            None => Self::reflect(&label, source, entry_point),
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn entry_point(&self) -> &str {
+        &self.entry_point
+    }
+
+    pub fn config(&self) -> &PipelineConfiguration {
+        &self.config
+    }
+
+    /// Dispatches the kernel with `arguments` bound to the entries of its
+    /// layout in order, and submits it right away.
+    pub fn dispatch(
+        &self,
+        context: &Context,
+        arguments: &[&dyn KernelArgument],
+        workgroups: (u32, u32, u32),
+    ) {
+        let dispatch_result = self.try_dispatch(context, arguments, workgroups);
+
+        if let Err(e) = &dispatch_result {
+            log::error!("Failed at Kernel::dispatch: {}", e);
+        }
+
+        dispatch_result.unwrap()
+    }
+
+    pub fn try_dispatch(
+        &self,
+        context: &Context,
+        arguments: &[&dyn KernelArgument],
+        workgroups: (u32, u32, u32),
+    ) -> Result<(), PipelineExecutionError> {
+        let mut batch = context.batch();
+        self.record(&mut batch, arguments, workgroups)?;
+        batch.submit();
+
+        Ok(())
+    }
+
+    /// Records a dispatch of the kernel into `batch`, see [`Kernel::dispatch`].
+    pub fn record(
+        &self,
+        batch: &mut CommandBatch<'_>,
+        arguments: &[&dyn KernelArgument],
+        workgroups: (u32, u32, u32),
+    ) -> Result<(), PipelineExecutionError> {
+        let pipeline = ComputePipeline::builder(self.source.clone(), &self.entry_point)
+            .label(&self.label)
+            .bind_group(self.config.clone())
+            .try_build(batch.context())
+            .map_err(|e| PipelineExecutionError::Compilation {
+                pipeline: self.label.clone(),
+                message: e.to_string(),
+            })?;
+
+        pipeline.dispatch(batch, &[arguments], workgroups)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::{buffers::Buffer, device::test_context};
+
+    use super::{Kernel, PipelineExecutionError};
+    type Use = wgpu::BufferUsages;
+
+    fn add_kernel() -> Kernel {
+        Kernel::load("src/shaders/add.wgsl", "add", Some("src/shaders/add.hjson"))
+            .expect("Failed to load the add kernel")
+    }
+
+    #[test]
+    fn test_kernel_dispatch() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let kernel = add_kernel();
+        assert_eq!(kernel.label(), "add");
+
+        // Without hjson, the same layout is inferred from the shader.
+        let reflected = Kernel::load("src/shaders/add.wgsl", "add", None).unwrap();
+        assert_eq!(reflected.config().entries(), kernel.config().entries());
+
+        let x = Buffer::from_vec(
+            &context,
+            Use::STORAGE,
+            vec![f32::from_bits(2), f32::from_bits(2), 1., 2., 3., 4.],
+        );
+        let y = Buffer::from_vec(
+            &context,
+            Use::STORAGE,
+            vec![f32::from_bits(2), f32::from_bits(2), 3., 1., 6., 2.],
+        );
+        let z = Buffer::<f32>::with_len(&context, Use::STORAGE | Use::COPY_SRC, x.len());
+
+        kernel.dispatch(&context, &[&x, &y, &z], (1, 1, 1));
+        // Identical kernels share the compiled pipeline.
+        add_kernel().dispatch(&context, &[&x, &y, &z], (1, 1, 1));
+        assert_eq!(context.pipelines().len(), 1);
+
+        assert_eq!(
+            z.read_to_vec(&context),
+            vec![f32::from_bits(2), f32::from_bits(2), 4., 3., 9., 6.]
+        );
+    }
+
+    #[test]
+    fn test_kernel_validation() {
+        let Some(context) = test_context() else {
+            return;
+        };
+
+        let kernel = add_kernel();
+        let x = Buffer::from_vec(&context, Use::STORAGE, vec![0f32; 4]);
+        let y = Buffer::from_vec(&context, Use::COPY_SRC, vec![0f32; 4]);
+
+        assert_eq!(
+            kernel.try_dispatch(&context, &[&x, &x], (1, 1, 1)),
+            Err(PipelineExecutionError::ArgumentCount {
+                pipeline: "add".into(),
+                group: 0,
+                expected: 3,
+                found: 2
+            })
+        );
+        assert_eq!(
+            kernel.try_dispatch(&context, &[&x, &y, &x], (1, 1, 1)),
+            Err(PipelineExecutionError::InvalidBufferUsage {
+                pipeline: "add".into(),
+                group: 0,
+                binding: 1,
+                required: Use::STORAGE,
+                found: Use::COPY_SRC
+            })
+        );
+        assert!(matches!(
+            kernel.try_dispatch(&context, &[&x, &x, &x], (u32::MAX, 1, 1)),
+            Err(PipelineExecutionError::DispatchTooLarge { .. })
+        ));
+        assert_eq!(context.pipelines().len(), 1);
+
+        // Kernels that fail to compile are reported, and not cached.
+        let missing = Kernel::new(
+            "missing",
+            kernel.source.clone(),
+            "missing",
+            kernel.config.clone(),
+        );
+        assert!(matches!(
+            missing.try_dispatch(&context, &[&x, &x, &x], (1, 1, 1)),
+            Err(PipelineExecutionError::Compilation { .. })
+        ));
+        assert_eq!(context.pipelines().len(), 1);
+    }
+}
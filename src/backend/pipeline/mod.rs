@@ -1,157 +1,577 @@
-use super::device::Context;
+use std::{borrow::Cow, collections::HashMap, sync::Arc};
 
+use super::{batch::CommandBatch, buffers::Buffer, device::Context, traits::BufferType};
+
+mod cache;
 pub mod config;
+mod kernel;
+
+pub(crate) use self::cache::PipelineCache;
+use self::cache::PipelineKey;
+pub use self::{
+    config::{PipelineConfiguration, PipelineLoadingError},
+    kernel::Kernel,
+};
 
+/// A compiled compute pipeline along with the layouts of its bind groups.
+/// Clones, and pipelines built identically on the same [`Context`], share the
+/// compiled pipeline, but each keeps the label it was built with.
+#[derive(Debug, Clone)]
 pub struct ComputePipeline {
-    compute_pipeline: Option<wgpu::ComputePipeline>,
-    layout: Option<wgpu::PipelineLayout>,
+    inner: Arc<CompiledPipeline>,
+    label: String,
 }
 
-#[derive(Debug, Clone, thiserror::Error)]
+#[derive(Debug)]
+pub(crate) struct CompiledPipeline {
+    pipeline: wgpu::ComputePipeline,
+    configs: Vec<PipelineConfiguration>,
+    bind_group_layouts: Vec<wgpu::BindGroupLayout>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
 pub enum PipelineExecutionError {
-    #[error("The pipeline has not yet been initialized. Please run `ComputePipeline::pipeline`")]
-    UninitializedPipeline,
+    #[error("The {pipeline} pipeline was given {found} bind groups, but has {expected}.")]
+    BindGroupCount {
+        pipeline: String,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("Group {group} of the {pipeline} pipeline binds {expected} buffers, but {found} were provided.")]
+    ArgumentCount {
+        pipeline: String,
+        group: u32,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("Binding {binding} of group {group} of the {pipeline} pipeline requires {required:?} buffers, but was given {found:?}.")]
+    InvalidBufferUsage {
+        pipeline: String,
+        group: u32,
+        binding: u32,
+        required: wgpu::BufferUsages,
+        found: wgpu::BufferUsages,
+    },
+
+    #[error(
+        "Binding {binding} of group {group} of the {pipeline} pipeline is not a buffer binding."
+    )]
+    UnsupportedBinding {
+        pipeline: String,
+        group: u32,
+        binding: u32,
+    },
+
+    #[error("The {pipeline} pipeline was dispatched with {workgroups:?} workgroups, but at most {limit} are allowed per dimension.")]
+    DispatchTooLarge {
+        pipeline: String,
+        workgroups: (u32, u32, u32),
+        limit: u32,
+    },
+
+    #[error("Invalid Buffer Usage: {usage:?}, indirect dispatches of the {pipeline} pipeline must read INDIRECT buffers.")]
+    InvalidIndirectBuffer {
+        pipeline: String,
+        usage: wgpu::BufferUsages,
+    },
+
+    #[error("Indirect dispatches of the {pipeline} pipeline read 12 aligned bytes at offset {offset}, but the buffer holds {size}.")]
+    IndirectOffset {
+        pipeline: String,
+        offset: u64,
+        size: u64,
+    },
+
+    #[error("Failed to compile the {pipeline} pipeline: {message}")]
+    Compilation { pipeline: String, message: String },
+}
+
+/// A buffer that can be bound to a [`ComputePipeline`], whatever its element
+/// type.
+pub trait KernelArgument {
+    fn usage(&self) -> wgpu::BufferUsages;
+
+    fn resource(&self) -> wgpu::BindingResource<'_>;
+
+    /// Keeps the buffer alive until `batch` is submitted.
+    fn keep_alive(&self, batch: &mut CommandBatch<'_>);
+}
+
+impl<T: BufferType> KernelArgument for Buffer<T> {
+    fn usage(&self) -> wgpu::BufferUsages {
+        Buffer::usage(self)
+    }
+
+    fn resource(&self) -> wgpu::BindingResource<'_> {
+        self.get_resource()
+    }
+
+    fn keep_alive(&self, batch: &mut CommandBatch<'_>) {
+        batch.keep_alive(self);
+    }
+}
+
+pub struct ComputePipelineBuilder {
+    label: Option<String>,
+    source: Cow<'static, str>,
+    entry_point: String,
+    configs: Vec<PipelineConfiguration>,
+    constants: HashMap<String, f64>,
 }
 
 impl ComputePipeline {
-    pub fn construct() -> Self {
-        Self {
-            compute_pipeline: None,
-            layout: None,
+    /// Starts building a pipeline running `entry_point` of the WGSL `source`.
+    pub fn builder(
+        source: impl Into<Cow<'static, str>>,
+        entry_point: &str,
+    ) -> ComputePipelineBuilder {
+        ComputePipelineBuilder {
+            label: None,
+            source: source.into(),
+            entry_point: entry_point.to_string(),
+            configs: Vec::new(),
+            constants: HashMap::new(),
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn pipeline(&self) -> &wgpu::ComputePipeline {
+        &self.inner.pipeline
+    }
+
+    pub fn configs(&self) -> &[PipelineConfiguration] {
+        &self.inner.configs
+    }
+
+    pub fn bind_group_layouts(&self) -> &[wgpu::BindGroupLayout] {
+        &self.inner.bind_group_layouts
+    }
+
+    /// Records a dispatch of `workgroups` into `batch`, where the buffers of
+    /// `bind_groups` are bound to the entries of each group in order.
+    pub fn dispatch(
+        &self,
+        batch: &mut CommandBatch<'_>,
+        bind_groups: &[&[&dyn KernelArgument]],
+        workgroups: (u32, u32, u32),
+    ) -> Result<(), PipelineExecutionError> {
+        let limit = batch
+            .context()
+            .limits()
+            .max_compute_workgroups_per_dimension;
+        if [workgroups.0, workgroups.1, workgroups.2]
+            .iter()
+            .any(|&count| count > limit)
+        {
+            return Err(PipelineExecutionError::DispatchTooLarge {
+                pipeline: self.label.clone(),
+                workgroups,
+                limit,
+            });
+        }
+
+        let bind_groups = self.bind_groups(batch, bind_groups)?;
+        batch.dispatch(
+            &self.inner.pipeline,
+            &bind_groups.iter().collect::<Vec<_>>(),
+            workgroups,
+        );
+
+        Ok(())
+    }
+
+    /// Records a dispatch into `batch` whose workgroup counts are read on the
+    /// device from three `u32` at byte `offset` of `indirect`, see
+    /// [`ComputePipeline::dispatch`].
+    pub fn dispatch_indirect(
+        &self,
+        batch: &mut CommandBatch<'_>,
+        bind_groups: &[&[&dyn KernelArgument]],
+        indirect: &Buffer<u32>,
+        offset: u64,
+    ) -> Result<(), PipelineExecutionError> {
+        if !indirect.usage().contains(wgpu::BufferUsages::INDIRECT) {
+            return Err(PipelineExecutionError::InvalidIndirectBuffer {
+                pipeline: self.label.clone(),
+                usage: indirect.usage(),
+            });
+        }
+
+        if !offset.is_multiple_of(4) || offset + 12 > indirect.size() {
+            return Err(PipelineExecutionError::IndirectOffset {
+                pipeline: self.label.clone(),
+                offset,
+                size: indirect.size(),
+            });
+        }
+
+        let bind_groups = self.bind_groups(batch, bind_groups)?;
+        batch.dispatch_indirect(
+            &self.inner.pipeline,
+            &bind_groups.iter().collect::<Vec<_>>(),
+            indirect,
+            offset,
+        );
+
+        Ok(())
+    }
+
+    /// Validates the buffers of every group against its layout, and creates
+    /// the bind groups, keeping the buffers alive until `batch` is submitted.
+    fn bind_groups(
+        &self,
+        batch: &mut CommandBatch<'_>,
+        bind_groups: &[&[&dyn KernelArgument]],
+    ) -> Result<Vec<wgpu::BindGroup>, PipelineExecutionError> {
+        let label = &self.label;
+        if bind_groups.len() != self.inner.configs.len() {
+            return Err(PipelineExecutionError::BindGroupCount {
+                pipeline: label.clone(),
+                expected: self.inner.configs.len(),
+                found: bind_groups.len(),
+            });
+        }
+
+        for (group, (config, arguments)) in self.inner.configs.iter().zip(bind_groups).enumerate() {
+            validate_group(label, group as u32, config, arguments)?;
+        }
+
+        let device = batch.context().device();
+        let created = self
+            .inner
+            .bind_group_layouts
+            .iter()
+            .zip(&self.inner.configs)
+            .zip(bind_groups)
+            .map(|((layout, config), arguments)| {
+                let entries = config
+                    .entries()
+                    .iter()
+                    .zip(arguments.iter())
+                    .map(|(entry, argument)| wgpu::BindGroupEntry {
+                        binding: entry.binding,
+                        resource: argument.resource(),
+                    })
+                    .collect::<Vec<_>>();
+
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some(label),
+                    layout,
+                    entries: &entries,
+                })
+            })
+            .collect();
+
+        for argument in bind_groups.iter().flat_map(|arguments| arguments.iter()) {
+            argument.keep_alive(batch);
+        }
+
+        Ok(created)
+    }
+}
+
+fn validate_group(
+    label: &str,
+    group: u32,
+    config: &PipelineConfiguration,
+    arguments: &[&dyn KernelArgument],
+) -> Result<(), PipelineExecutionError> {
+    let entries = config.entries();
+    if arguments.len() != entries.len() {
+        return Err(PipelineExecutionError::ArgumentCount {
+            pipeline: label.to_string(),
+            group,
+            expected: entries.len(),
+            found: arguments.len(),
+        });
+    }
+
+    for (entry, argument) in entries.iter().zip(arguments) {
+        let required = match entry.ty {
+            wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { .. },
+                ..
+            } => wgpu::BufferUsages::STORAGE,
+            wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                ..
+            } => wgpu::BufferUsages::UNIFORM,
+            _ => {
+                return Err(PipelineExecutionError::UnsupportedBinding {
+                    pipeline: label.to_string(),
+                    group,
+                    binding: entry.binding,
+                })
+            }
+        };
+
+        if !argument.usage().contains(required) {
+            return Err(PipelineExecutionError::InvalidBufferUsage {
+                pipeline: label.to_string(),
+                group,
+                binding: entry.binding,
+                required,
+                found: argument.usage(),
+            });
         }
     }
 
-    pub fn pipeline_layout(
-        &mut self,
-        context: &Context,
-        layout_desc: &wgpu::PipelineLayoutDescriptor,
-    ) {
-        self.layout
-            .replace(context.device().create_pipeline_layout(layout_desc));
+    Ok(())
+}
+
+impl ComputePipelineBuilder {
+    pub fn label(mut self, label: &str) -> Self {
+        self.label.replace(label.to_string());
+        self
     }
 
-    pub fn pipeline(&mut self, context: &Context, desc: &wgpu::ComputePipelineDescriptor) {
-        self.compute_pipeline
-            .replace(context.device().create_compute_pipeline(desc));
+    /// Appends the layout of the next bind group, which is checked against
+    /// the shader. Without any, the layouts are inferred from the shader.
+    pub fn bind_group(mut self, config: PipelineConfiguration) -> Self {
+        self.configs.push(config);
+        self
     }
 
-    pub fn get_pipeline(&mut self) -> Option<wgpu::ComputePipeline> {
-        self.compute_pipeline.take()
+    /// Overrides the value of the pipeline-overridable constant `name`, which
+    /// is either its identifier or its `@id`.
+    pub fn constant(mut self, name: &str, value: f64) -> Self {
+        self.constants.insert(name.to_string(), value);
+        self
     }
 
-    pub fn get_layout(&mut self) -> Option<wgpu::PipelineLayout> {
-        self.layout.take()
+    /// Builds the pipeline, or reuses the one already built on `context` for
+    /// the same source, entry point, layouts and constants.
+    pub fn build(self, context: &Context) -> ComputePipeline {
+        let build_result = self.try_build(context);
+
+        if let Err(e) = &build_result {
+            log::error!("Failed at ComputePipelineBuilder::build: {}", e);
+        }
+
+        build_result.unwrap()
+    }
+
+    pub fn try_build(self, context: &Context) -> Result<ComputePipeline, PipelineLoadingError> {
+        let cache = context.pipelines();
+        let key = PipelineKey::new(
+            &self.source,
+            &self.entry_point,
+            &self.configs,
+            &self.constants,
+        );
+        let label = self.label.unwrap_or_else(|| self.entry_point.clone());
+
+        if let Some(inner) = cache.get(&key) {
+            return Ok(ComputePipeline { inner, label });
+        }
+
+        let configs = match self.configs.is_empty() {
+            true => PipelineConfiguration::reflect_groups(&self.source)?,
+            false => {
+                for (group, config) in self.configs.iter().enumerate() {
+                    config.validate(&self.source, group as u32)?;
+                }
+                self.configs
+            }
+        };
+
+        let device = context.device();
+
+        // NOTE: Invalid shaders and entry points are reported through the
+        // error scope instead of the uncaptured error handler, which panics.
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(&label),
+            source: wgpu::ShaderSource::Wgsl(self.source),
+        });
+        let bind_group_layouts = configs
+            .iter()
+            .map(|config| {
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some(&label),
+                    entries: config.entries(),
+                })
+            })
+            .collect::<Vec<_>>();
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(&label),
+            bind_group_layouts: &bind_group_layouts.iter().collect::<Vec<_>>(),
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(&label),
+            layout: Some(&layout),
+            module: &module,
+            entry_point: &self.entry_point,
+            compilation_options: wgpu::PipelineCompilationOptions {
+                constants: &self.constants,
+                ..Default::default()
+            },
+        });
+
+        if let Some(e) = smol::block_on(device.pop_error_scope()) {
+            return Err(PipelineLoadingError::CompilationError(e.to_string()));
+        }
+
+        let inner = Arc::new(CompiledPipeline {
+            pipeline,
+            configs,
+            bind_group_layouts,
+        });
+
+        Ok(ComputePipeline {
+            inner: cache.insert(key, inner),
+            label,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::backend::{buffers::Buffer, device::Context};
+    use crate::backend::{buffers::Buffer, device::test_context};
 
-    use super::{config::PipelineConfiguration, ComputePipeline};
+    use super::{
+        config::{PipelineConfiguration, PipelineLoadingError},
+        ComputePipeline, PipelineExecutionError,
+    };
     type Use = wgpu::BufferUsages;
 
+    const ADD_SHADER: &str = include_str!("../../shaders/add.wgsl");
+
     #[test]
     fn test_add_shader() {
         env_logger::builder()
             .filter_level(log::LevelFilter::Info)
             .init();
 
-        let context = Context::new();
-
-        let add_module = context
-            .device()
-            .create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: None,
-                source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!(
-                    "../../shaders/add.wgsl"
-                ))),
-            });
+        let Some(context) = test_context() else {
+            return;
+        };
 
         let config =
             PipelineConfiguration::load("src/shaders/add.hjson").expect("Failed to load add.hjson");
-        let mut pipeline = ComputePipeline::construct();
-
-        let bind_group_layout =
-            context
-                .device()
-                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    label: None,
-                    entries: config.entries(),
-                });
+        let pipeline = ComputePipeline::builder(ADD_SHADER, "add")
+            .label("add")
+            .bind_group(config)
+            .build(&context);
+        assert_eq!(pipeline.bind_group_layouts().len(), 1);
 
-        pipeline.pipeline_layout(
-            &context,
-            &wgpu::PipelineLayoutDescriptor {
-                label: None,
-                bind_group_layouts: &[&bind_group_layout],
-                push_constant_ranges: &[],
-            },
-        );
+        let x_vec = vec![f32::from_bits(4), f32::from_bits(1), 1., 2., 3., 4.];
+        let y_vec = vec![f32::from_bits(4), f32::from_bits(1), 3., 1., 6., 2.];
 
-        let layout = pipeline
-            .get_layout()
-            .expect("Could not get layout. Not initialized?");
+        let x = Buffer::from_vec(&context, Use::STORAGE, x_vec);
+        let y = Buffer::from_vec(&context, Use::STORAGE, y_vec);
 
-        let add_desc = wgpu::ComputePipelineDescriptor {
-            label: None,
-            layout: Some(&layout),
-            module: &add_module,
-            entry_point: "add",
-            compilation_options: Default::default(),
-        };
+        let z = Buffer::<f32>::with_len(&context, Use::STORAGE | Use::COPY_SRC, x.len());
+        let w = Buffer::<f32>::with_len(&context, Use::STORAGE | Use::COPY_SRC, x.len());
 
-        pipeline.pipeline(&context, &add_desc);
+        // The pipeline, and its clones, can be dispatched any number of times.
+        let mut batch = context.batch();
+        pipeline
+            .dispatch(&mut batch, &[&[&x, &y, &z]], (1, 1, 1))
+            .unwrap();
+        pipeline
+            .clone()
+            .dispatch(&mut batch, &[&[&z, &y, &w]], (1, 1, 1))
+            .unwrap();
+        batch.submit();
 
-        let pipeline = pipeline.get_pipeline();
-        let pipeline = pipeline.expect("Could not get pipeline. Not initialized?");
+        assert_eq!(
+            z.read_to_vec(&context),
+            vec![f32::from_bits(4), f32::from_bits(1), 4., 3., 9., 6.]
+        );
+        assert_eq!(
+            w.read_to_vec(&context),
+            vec![f32::from_bits(4), f32::from_bits(1), 7., 4., 15., 8.]
+        );
+    }
 
-        let x_vec = vec![f32::from_bits(4), f32::from_bits(1), 1., 2., 3., 4.];
-        let y_vec = vec![f32::from_bits(4), f32::from_bits(1), 3., 1., 6., 2.];
+    #[test]
+    fn test_dispatch_indirect() {
+        let Some(context) = test_context() else {
+            return;
+        };
 
-        let x = Buffer::from_vec(&context, Use::STORAGE, x_vec);
-        let y = Buffer::from_vec(&context, Use::STORAGE, y_vec);
+        // Without a configuration, the layout is inferred from the shader.
+        let pipeline = ComputePipeline::builder(ADD_SHADER, "add").build(&context);
+        assert_eq!(pipeline.label(), "add");
 
-        let z = Buffer::<f32>::new(&context, Use::STORAGE | Use::COPY_SRC, x.size());
-        let mut w = Buffer::<f32>::new(&context, Use::MAP_READ | Use::COPY_DST, z.size());
-
-        let bind_group = context
-            .device()
-            .create_bind_group(&wgpu::BindGroupDescriptor {
-                label: None,
-                layout: &bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: x.get_resource(),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: y.get_resource(),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 2,
-                        resource: z.get_resource(),
-                    },
-                ],
-            });
+        let x = Buffer::from_vec(
+            &context,
+            Use::STORAGE,
+            vec![f32::from_bits(1), f32::from_bits(2), 1., 2.],
+        );
+        let z = Buffer::<f32>::with_len(&context, Use::STORAGE | Use::COPY_SRC, x.len());
+        let indirect = Buffer::from_vec(&context, Use::INDIRECT, vec![0u32, 1, 1, 1]);
+        let not_indirect = Buffer::from_vec(&context, Use::STORAGE, vec![1u32, 1, 1]);
 
-        let mut encoder = context.command_encoder();
-        let mut compute_pass = encoder.begin_compute_pass(&Default::default());
+        let mut batch = context.batch();
+        pipeline
+            .dispatch_indirect(&mut batch, &[&[&x, &x, &z]], &indirect, 4)
+            .unwrap();
+        assert_eq!(
+            pipeline.dispatch_indirect(&mut batch, &[&[&x, &x, &z]], &indirect, 8),
+            Err(PipelineExecutionError::IndirectOffset {
+                pipeline: "add".into(),
+                offset: 8,
+                size: 16
+            })
+        );
+        assert!(matches!(
+            pipeline.dispatch_indirect(&mut batch, &[&[&x, &x, &z]], &not_indirect, 0),
+            Err(PipelineExecutionError::InvalidIndirectBuffer { .. })
+        ));
+        batch.submit();
 
-        compute_pass.set_pipeline(&pipeline);
-        compute_pass.set_bind_group(0, &bind_group, &[]);
-        compute_pass.dispatch_workgroups(1, 1, 1);
+        assert_eq!(
+            z.read_to_vec(&context),
+            vec![f32::from_bits(1), f32::from_bits(2), 2., 4.]
+        );
+    }
 
-        drop(compute_pass);
-        let command_buf = encoder.finish();
-        context.queue().submit([command_buf]);
+    #[test]
+    fn test_pipeline_misuse() {
+        let Some(context) = test_context() else {
+            return;
+        };
 
-        z.copy_to(&context, .., &mut w, ..);
+        let pipeline = ComputePipeline::builder(ADD_SHADER, "add").build(&context);
+        let x = Buffer::from_vec(&context, Use::STORAGE, vec![0f32; 4]);
+        let y = Buffer::from_vec(&context, Use::COPY_SRC, vec![0f32; 4]);
 
+        let mut batch = context.batch();
         assert_eq!(
-            w.slice(..).map(&context).to_vec(),
-            vec![f32::from_bits(4), f32::from_bits(1), 4., 3., 9., 6.]
+            pipeline.dispatch(&mut batch, &[], (1, 1, 1)),
+            Err(PipelineExecutionError::BindGroupCount {
+                pipeline: "add".into(),
+                expected: 1,
+                found: 0
+            })
+        );
+        assert_eq!(
+            pipeline.dispatch(&mut batch, &[&[&x, &y, &x]], (1, 1, 1)),
+            Err(PipelineExecutionError::InvalidBufferUsage {
+                pipeline: "add".into(),
+                group: 0,
+                binding: 1,
+                required: Use::STORAGE,
+                found: Use::COPY_SRC
+            })
         );
+        assert!(matches!(
+            pipeline.dispatch(&mut batch, &[&[&x, &x, &x]], (u32::MAX, 1, 1)),
+            Err(PipelineExecutionError::DispatchTooLarge { .. })
+        ));
+
+        assert!(matches!(
+            ComputePipeline::builder(ADD_SHADER, "missing").try_build(&context),
+            Err(PipelineLoadingError::CompilationError(_))
+        ));
     }
 }
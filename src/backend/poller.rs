@@ -0,0 +1,105 @@
+use std::{
+    sync::Arc,
+    thread::{self, JoinHandle},
+};
+
+use parking_lot::{Condvar, Mutex};
+
+#[derive(Debug, Default)]
+struct PollerState {
+    /// The number of live [`PollGuard`]s, that is futures waiting on callbacks.
+    watchers: usize,
+    /// Bumped by each [`DevicePoller::watch`], whose mapping may have been
+    /// requested after the last poll.
+    watches: u64,
+    shutdown: bool,
+}
+
+/// A thread owned by a [`Context`](super::Context) that drives the device
+/// while futures wait on its callbacks, such as those of `map_async`.
+///
+/// The thread sleeps while nothing is waiting, and between polls once the
+/// submission queue is empty, until a new future starts waiting.
+#[derive(Debug)]
+pub(crate) struct DevicePoller {
+    state: Arc<(Mutex<PollerState>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl DevicePoller {
+    pub(crate) fn new(device: Arc<wgpu::Device>) -> Self {
+        let state = Arc::new((Mutex::new(PollerState::default()), Condvar::new()));
+
+        let shared = state.clone();
+        let thread = thread::Builder::new()
+            .name("device-poller".into())
+            .spawn(move || {
+                let (state, condvar) = &*shared;
+                let mut polled = 0;
+                loop {
+                    let mut guard = state.lock();
+                    while (guard.watchers == 0 || guard.watches == polled) && !guard.shutdown {
+                        condvar.wait(&mut guard);
+                    }
+                    if guard.shutdown {
+                        return;
+                    }
+                    let watches = guard.watches;
+                    drop(guard);
+
+                    // NOTE: This returns once every submission so far is done,
+                    // after running the callbacks of the mappings they held up.
+                    // Once nothing is left in flight, only mappings requested
+                    // by later watchers can still complete.
+                    if device.poll(wgpu::MaintainBase::Wait).is_queue_empty() {
+                        polled = watches;
+                    }
+                }
+            })
+            .expect("Failed to spawn the device poller thread");
+
+        DevicePoller {
+            state,
+            thread: Some(thread),
+        }
+    }
+
+    /// Keeps the device polled until the returned guard is dropped.
+    pub(crate) fn watch(&self) -> PollGuard {
+        let (state, condvar) = &*self.state;
+        let mut guard = state.lock();
+        guard.watchers += 1;
+        guard.watches += 1;
+        drop(guard);
+        condvar.notify_one();
+
+        PollGuard {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl Drop for DevicePoller {
+    fn drop(&mut self) {
+        let (state, condvar) = &*self.state;
+        state.lock().shutdown = true;
+        condvar.notify_one();
+
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                log::error!("The device poller thread panicked.");
+            }
+        }
+    }
+}
+
+/// Returned by [`DevicePoller::watch`].
+pub(crate) struct PollGuard {
+    state: Arc<(Mutex<PollerState>, Condvar)>,
+}
+
+impl Drop for PollGuard {
+    fn drop(&mut self) {
+        self.state.0.lock().watchers -= 1;
+    }
+}
@@ -1,16 +1,74 @@
 use bytemuck::{Pod, Zeroable};
 
-pub trait BufferType: Zeroable + Pod + 'static {}
+pub trait BufferType: Zeroable + Pod + 'static {
+    /// The multiplicative identity, used by constructors like `Array::ones`.
+    const ONE: Self;
 
-impl BufferType for u8 {}
-impl BufferType for u16 {}
-impl BufferType for u32 {}
-impl BufferType for u64 {}
+    /// The name of the matching WGSL type, for types that kernels can be
+    /// generated for. Some of these types also need a device feature.
+    const WGSL_TYPE: Option<&'static str> = None;
 
-impl BufferType for i8 {}
-impl BufferType for i16 {}
-impl BufferType for i32 {}
-impl BufferType for i64 {}
+    /// The name kernels know the type by when WGSL has no scalar type for it,
+    /// and kernels emulate it on 32-bit words instead.
+    const EMULATED_TYPE: Option<&'static str> = None;
+}
 
-impl BufferType for f32 {}
-impl BufferType for f64 {}
+impl BufferType for u8 {
+    const ONE: Self = 1;
+    const EMULATED_TYPE: Option<&'static str> = Some("u8");
+}
+impl BufferType for u16 {
+    const ONE: Self = 1;
+    const EMULATED_TYPE: Option<&'static str> = Some("u16");
+}
+impl BufferType for u32 {
+    const ONE: Self = 1;
+    const WGSL_TYPE: Option<&'static str> = Some("u32");
+}
+impl BufferType for u64 {
+    const ONE: Self = 1;
+    const EMULATED_TYPE: Option<&'static str> = Some("u64");
+}
+
+impl BufferType for i8 {
+    const ONE: Self = 1;
+    const EMULATED_TYPE: Option<&'static str> = Some("i8");
+}
+impl BufferType for i16 {
+    const ONE: Self = 1;
+    const EMULATED_TYPE: Option<&'static str> = Some("i16");
+}
+impl BufferType for i32 {
+    const ONE: Self = 1;
+    const WGSL_TYPE: Option<&'static str> = Some("i32");
+}
+impl BufferType for i64 {
+    const ONE: Self = 1;
+    const EMULATED_TYPE: Option<&'static str> = Some("i64");
+}
+
+impl BufferType for f32 {
+    const ONE: Self = 1.;
+    const WGSL_TYPE: Option<&'static str> = Some("f32");
+}
+impl BufferType for f64 {
+    const ONE: Self = 1.;
+    const WGSL_TYPE: Option<&'static str> = Some("f64");
+}
+
+// NOTE: naga cannot parse `f16` yet, so halves have no WGSL type and kernels
+// compute them packed, like the integers WGSL lacks.
+impl BufferType for half::f16 {
+    const ONE: Self = half::f16::ONE;
+    const EMULATED_TYPE: Option<&'static str> = Some("f16");
+}
+impl BufferType for half::bf16 {
+    const ONE: Self = half::bf16::ONE;
+    const EMULATED_TYPE: Option<&'static str> = Some("bf16");
+}
+
+impl BufferType for num_complex::Complex<f32> {
+    const ONE: Self = num_complex::Complex::new(1., 0.);
+    const WGSL_TYPE: Option<&'static str> = Some("vec2<f32>");
+    const EMULATED_TYPE: Option<&'static str> = Some("complex<f32>");
+}
@@ -8,7 +8,7 @@ pub trait Lengthed {
 
 impl<T: BufferType> Lengthed for Buffer<T> {
     fn len(&self) -> usize {
-        self.size() as usize
+        Buffer::len(self)
     }
 }
 
@@ -37,27 +37,28 @@ pub struct MaterializedBound<T: Copy> {
 }
 
 pub fn materialize(b: impl RangeBounds<usize>, a: &impl Lengthed) -> MaterializedBound<usize> {
-    let start = 0;
-    let end = a.len();
+    materialize_len(b, a.len())
+}
 
-    let start = match b.start_bound() {
-        std::ops::Bound::Included(a) => start.max(*a),
-        std::ops::Bound::Excluded(b) => start.max(*b + 1), // watch out for overflow in the future
-        std::ops::Bound::Unbounded => start,
+/// Resolves `b` against a collection of length `len`, clamping both ends so
+/// that `start <= end <= len`.
+pub fn materialize_len(b: impl RangeBounds<usize>, len: usize) -> MaterializedBound<usize> {
+    let end = match b.end_bound() {
+        std::ops::Bound::Included(a) => len.min(a.saturating_add(1)),
+        std::ops::Bound::Excluded(b) => len.min(*b),
+        std::ops::Bound::Unbounded => len,
     };
 
-    let end = match b.start_bound() {
-        std::ops::Bound::Included(a) => end.min(*a),
-        std::ops::Bound::Excluded(b) => {
-            if *b == 0 {
-                log::error!("Encountered RangeBounds with end_bound: Excluded(0)")
-            }
-            end.min(*b - 1)
-        }
-        std::ops::Bound::Unbounded => end,
+    let start = match b.start_bound() {
+        std::ops::Bound::Included(a) => *a,
+        std::ops::Bound::Excluded(b) => b.saturating_add(1),
+        std::ops::Bound::Unbounded => 0,
     };
 
-    MaterializedBound::<usize> { start, end }
+    MaterializedBound::<usize> {
+        start: start.min(end),
+        end,
+    }
 }
 
 impl<T: Copy> MaterializedBound<T> {
@@ -1 +1,649 @@
-// use std::ops::{Range, RangeTo, RangeFrom, RangeFull, RangeInclusive, RangeToInclusive, RangeBounds, Bound};
+use std::ops::{
+    Bound, Range, RangeBounds, RangeFrom, RangeFull, RangeInclusive, RangeTo, RangeToInclusive,
+};
+
+use crate::backend::util::materialize_len;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum IndexError {
+    #[error("Too many indices: {given} indices were given for an array with {ndim} axes.")]
+    TooManyIndices { ndim: usize, given: usize },
+
+    #[error("Index {index} is out of bounds for axis {axis} with length {len}.")]
+    OutOfBounds {
+        index: isize,
+        axis: usize,
+        len: usize,
+    },
+
+    #[error("Slice step for axis {0} must be nonzero.")]
+    ZeroStep(usize),
+
+    #[error("Only ranges can be stepped, but a step of {step} was given for {elem:?}.")]
+    StepWithoutRange { elem: SliceElem, step: isize },
+
+    #[error("An index can contain at most one ellipsis.")]
+    MultipleEllipses,
+
+    #[error("{0:?} is not a permutation of the axes of an array with {1} axes.")]
+    InvalidPermutation(Vec<usize>, usize),
+
+    #[error("{0:?} are not distinct axes of an array with {1} axes.")]
+    InvalidAxes(Vec<usize>, usize),
+
+    #[error("Shapes {lhs:?} and {rhs:?} cannot be broadcast together.")]
+    BroadcastMismatch { lhs: Vec<usize>, rhs: Vec<usize> },
+}
+
+/// Computes the shape that `lhs` and `rhs` broadcast to, following NumPy:
+/// shapes are aligned at their last axis, and each pair of lengths must be
+/// equal or contain a one.
+pub fn broadcast_shapes(lhs: &[usize], rhs: &[usize]) -> Result<Vec<usize>, IndexError> {
+    let ndim = lhs.len().max(rhs.len());
+    let padded = |shape: &[usize], axis: usize| match axis + shape.len() >= ndim {
+        true => shape[axis + shape.len() - ndim],
+        false => 1,
+    };
+
+    (0..ndim)
+        .map(|axis| match (padded(lhs, axis), padded(rhs, axis)) {
+            (l, r) if l == r || r == 1 => Ok(l),
+            (1, r) => Ok(r),
+            _ => Err(IndexError::BroadcastMismatch {
+                lhs: lhs.to_vec(),
+                rhs: rhs.to_vec(),
+            }),
+        })
+        .collect()
+}
+
+/// A single entry of an index tuple.
+///
+/// Ranges select `start..end` along an axis before `step` is applied, so a
+/// negative step walks the selected elements backwards from the last one.
+/// Negative bounds and indices count from the end of the axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SliceElem {
+    Index(isize),
+    Range {
+        start: Bound<isize>,
+        end: Bound<isize>,
+        step: isize,
+    },
+    NewAxis,
+    Ellipsis,
+}
+
+/// Inserts an axis of length one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NewAxis;
+
+/// Expands to as many full ranges as needed to index every axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ellipsis;
+
+/// Selects every `step`-th element of `range`.
+pub fn step_by(range: impl Into<SliceElem>, step: isize) -> SliceElem {
+    let step_result = try_step_by(range, step);
+
+    if let Err(e) = &step_result {
+        log::error!("Failed at indexing::step_by: {}", e);
+    }
+
+    step_result.unwrap()
+}
+
+/// Like [`step_by`], but fails if `range` is not a range and `step` is not
+/// one, instead of panicking.
+pub fn try_step_by(range: impl Into<SliceElem>, step: isize) -> Result<SliceElem, IndexError> {
+    match range.into() {
+        SliceElem::Range { start, end, .. } => Ok(SliceElem::Range { start, end, step }),
+        elem if step == 1 => Ok(elem),
+        elem => Err(IndexError::StepWithoutRange { elem, step }),
+    }
+}
+
+impl From<NewAxis> for SliceElem {
+    fn from(_: NewAxis) -> Self {
+        SliceElem::NewAxis
+    }
+}
+
+impl From<Ellipsis> for SliceElem {
+    fn from(_: Ellipsis) -> Self {
+        SliceElem::Ellipsis
+    }
+}
+
+impl From<RangeFull> for SliceElem {
+    fn from(_: RangeFull) -> Self {
+        SliceElem::Range {
+            start: Bound::Unbounded,
+            end: Bound::Unbounded,
+            step: 1,
+        }
+    }
+}
+
+macro_rules! impl_slice_elem {
+    ($($t:ty),+ $(,)?) => {
+        $(
+            impl From<$t> for SliceElem {
+                fn from(value: $t) -> Self {
+                    SliceElem::Index(value as isize)
+                }
+            }
+
+            impl_slice_elem!(@range $t, Range<$t>, RangeFrom<$t>, RangeTo<$t>, RangeInclusive<$t>, RangeToInclusive<$t>);
+        )+
+    };
+    (@range $t:ty, $($r:ty),+) => {
+        $(
+            impl From<$r> for SliceElem {
+                fn from(value: $r) -> Self {
+                    let cast = |b: Bound<&$t>| match b {
+                        Bound::Included(&i) => Bound::Included(i as isize),
+                        Bound::Excluded(&i) => Bound::Excluded(i as isize),
+                        Bound::Unbounded => Bound::Unbounded,
+                    };
+
+                    SliceElem::Range {
+                        start: cast(value.start_bound()),
+                        end: cast(value.end_bound()),
+                        step: 1,
+                    }
+                }
+            }
+        )+
+    };
+}
+
+impl_slice_elem!(usize, isize, i32);
+
+/// Anything that can be used to index an array, such as tuples of ranges,
+/// integers, [`NewAxis`] and [`Ellipsis`].
+pub trait SliceArg {
+    fn into_elems(self) -> Vec<SliceElem>;
+}
+
+impl SliceArg for Vec<SliceElem> {
+    fn into_elems(self) -> Vec<SliceElem> {
+        self
+    }
+}
+
+impl SliceArg for &[SliceElem] {
+    fn into_elems(self) -> Vec<SliceElem> {
+        self.to_vec()
+    }
+}
+
+macro_rules! impl_slice_arg_tuple {
+    ($($t:ident),+) => {
+        impl<$($t: Into<SliceElem>),+> SliceArg for ($($t,)+) {
+            #[allow(non_snake_case)]
+            fn into_elems(self) -> Vec<SliceElem> {
+                let ($($t,)+) = self;
+                vec![$($t.into()),+]
+            }
+        }
+    };
+}
+
+impl_slice_arg_tuple!(A);
+impl_slice_arg_tuple!(A, B);
+impl_slice_arg_tuple!(A, B, C);
+impl_slice_arg_tuple!(A, B, C, D);
+impl_slice_arg_tuple!(A, B, C, D, E);
+impl_slice_arg_tuple!(A, B, C, D, E, F);
+
+/// Describes how a strided array maps onto a flat buffer. Strides and the
+/// offset are measured in elements.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Layout {
+    shape: Vec<usize>,
+    strides: Vec<isize>,
+    offset: usize,
+}
+
+impl Layout {
+    pub fn contiguous(shape: &[usize]) -> Self {
+        let mut strides = vec![1; shape.len()];
+        for i in (1..shape.len()).rev() {
+            strides[i - 1] = strides[i] * shape[i] as isize;
+        }
+
+        Layout {
+            shape: shape.to_vec(),
+            strides,
+            offset: 0,
+        }
+    }
+
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    pub fn strides(&self) -> &[isize] {
+        &self.strides
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn ndim(&self) -> usize {
+        self.shape.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether the elements are laid out in row-major order with no gaps,
+    /// regardless of `offset`.
+    pub fn is_contiguous(&self) -> bool {
+        let mut expected = 1;
+        for (&len, &stride) in self.shape.iter().zip(&self.strides).rev() {
+            if len != 1 && stride != expected {
+                return false;
+            }
+            expected *= len as isize;
+        }
+        true
+    }
+
+    pub fn slice(&self, info: impl SliceArg) -> Result<Layout, IndexError> {
+        let elems = info.into_elems();
+
+        let consuming = elems
+            .iter()
+            .filter(|e| matches!(e, SliceElem::Index(_) | SliceElem::Range { .. }))
+            .count();
+        let ellipses = elems
+            .iter()
+            .filter(|e| matches!(e, SliceElem::Ellipsis))
+            .count();
+
+        if ellipses > 1 {
+            return Err(IndexError::MultipleEllipses);
+        }
+        if consuming > self.ndim() {
+            return Err(IndexError::TooManyIndices {
+                ndim: self.ndim(),
+                given: consuming,
+            });
+        }
+
+        let mut shape = Vec::with_capacity(self.ndim());
+        let mut strides = Vec::with_capacity(self.ndim());
+        let mut offset = self.offset as isize;
+        let mut axis = 0;
+
+        let full = SliceElem::from(..);
+        let fill = std::iter::repeat_n(full, self.ndim() - consuming);
+        let expanded: Vec<SliceElem> = match elems.iter().position(|e| *e == SliceElem::Ellipsis) {
+            Some(i) => elems[..i]
+                .iter()
+                .copied()
+                .chain(fill)
+                .chain(elems[i + 1..].iter().copied())
+                .collect(),
+            None => elems.into_iter().chain(fill).collect(),
+        };
+
+        for elem in expanded {
+            match elem {
+                SliceElem::Index(index) => {
+                    let len = self.shape[axis];
+                    let resolved = if index < 0 {
+                        index + len as isize
+                    } else {
+                        index
+                    };
+
+                    if resolved < 0 || resolved >= len as isize {
+                        return Err(IndexError::OutOfBounds { index, axis, len });
+                    }
+
+                    offset += resolved * self.strides[axis];
+                    axis += 1;
+                }
+                SliceElem::Range { start, end, step } => {
+                    if step == 0 {
+                        return Err(IndexError::ZeroStep(axis));
+                    }
+
+                    let len = self.shape[axis];
+                    let bound = materialize_len(wrap((start, end), len), len);
+                    let selected = bound.len();
+                    let stride = self.strides[axis];
+
+                    if selected > 0 {
+                        let first = if step > 0 {
+                            bound.start()
+                        } else {
+                            bound.end() - 1
+                        };
+                        offset += first as isize * stride;
+                    }
+
+                    shape.push(selected.div_ceil(step.unsigned_abs()));
+                    strides.push(stride * step);
+                    axis += 1;
+                }
+                SliceElem::NewAxis => {
+                    shape.push(1);
+                    strides.push(0);
+                }
+                SliceElem::Ellipsis => unreachable!("Ellipses are expanded above"),
+            }
+        }
+
+        Ok(Layout {
+            shape,
+            strides,
+            offset: offset as usize,
+        })
+    }
+
+    /// Reverses the order of the axes.
+    pub fn t(&self) -> Layout {
+        Layout {
+            shape: self.shape.iter().rev().copied().collect(),
+            strides: self.strides.iter().rev().copied().collect(),
+            offset: self.offset,
+        }
+    }
+
+    pub fn permute_axes(&self, axes: &[usize]) -> Result<Layout, IndexError> {
+        let mut seen = vec![false; self.ndim()];
+        let valid = axes.len() == self.ndim()
+            && axes
+                .iter()
+                .all(|&a| a < self.ndim() && !std::mem::replace(&mut seen[a], true));
+
+        if !valid {
+            return Err(IndexError::InvalidPermutation(axes.to_vec(), self.ndim()));
+        }
+
+        Ok(Layout {
+            shape: axes.iter().map(|&a| self.shape[a]).collect(),
+            strides: axes.iter().map(|&a| self.strides[a]).collect(),
+            offset: self.offset,
+        })
+    }
+
+    /// Splits the layout into the axes not in `axes`, which keep the offset,
+    /// and the axes in `axes`, in increasing order.
+    pub fn split_axes(&self, axes: &[usize]) -> Result<(Layout, Layout), IndexError> {
+        let mut selected = vec![false; self.ndim()];
+        let valid = axes
+            .iter()
+            .all(|&a| a < self.ndim() && !std::mem::replace(&mut selected[a], true));
+
+        if !valid {
+            return Err(IndexError::InvalidAxes(axes.to_vec(), self.ndim()));
+        }
+
+        let pick = |keep: bool| {
+            let axes = (0..self.ndim()).filter(|&a| selected[a] != keep);
+            Layout {
+                shape: axes.clone().map(|a| self.shape[a]).collect(),
+                strides: axes.map(|a| self.strides[a]).collect(),
+                offset: 0,
+            }
+        };
+
+        let outer = Layout {
+            offset: self.offset,
+            ..pick(true)
+        };
+        Ok((outer, pick(false)))
+    }
+
+    /// Views the layout as having `shape`, by prepending axes and repeating
+    /// axes of length one with a stride of zero. No elements are copied.
+    pub fn broadcast_to(&self, shape: &[usize]) -> Result<Layout, IndexError> {
+        if broadcast_shapes(&self.shape, shape).as_deref() != Ok(shape) {
+            return Err(IndexError::BroadcastMismatch {
+                lhs: self.shape.clone(),
+                rhs: shape.to_vec(),
+            });
+        }
+
+        let new_axes = shape.len() - self.ndim();
+        let strides = shape
+            .iter()
+            .enumerate()
+            .map(|(axis, &len)| match axis.checked_sub(new_axes) {
+                Some(axis) if self.shape[axis] == len => self.strides[axis],
+                _ => 0,
+            })
+            .collect();
+
+        Ok(Layout {
+            shape: shape.to_vec(),
+            strides,
+            offset: self.offset,
+        })
+    }
+
+    /// The buffer offset of every element, in row-major order.
+    pub fn offsets(&self) -> Vec<usize> {
+        let mut offsets = Vec::with_capacity(self.len());
+        if self.is_empty() {
+            return offsets;
+        }
+
+        let mut index = vec![0; self.ndim()];
+        let mut offset = self.offset as isize;
+        loop {
+            offsets.push(offset as usize);
+
+            let mut axis = self.ndim();
+            loop {
+                if axis == 0 {
+                    return offsets;
+                }
+                axis -= 1;
+
+                index[axis] += 1;
+                offset += self.strides[axis];
+                if index[axis] < self.shape[axis] {
+                    break;
+                }
+
+                offset -= self.strides[axis] * self.shape[axis] as isize;
+                index[axis] = 0;
+            }
+        }
+    }
+}
+
+/// Resolves the negative bounds of a range along an axis of `len` elements.
+/// Bounds are turned into an inclusive start and an exclusive end before
+/// positions before the first element are clamped to it, so that an inclusive
+/// end before the first element selects nothing.
+fn wrap((start, end): (Bound<isize>, Bound<isize>), len: usize) -> (Bound<usize>, Bound<usize>) {
+    let resolve = |i: isize| if i < 0 { i + len as isize } else { i };
+    let clamp = |i: isize| i.max(0) as usize;
+
+    let start = match start {
+        Bound::Included(i) => Bound::Included(clamp(resolve(i))),
+        Bound::Excluded(i) => Bound::Included(clamp(resolve(i).saturating_add(1))),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+    let end = match end {
+        Bound::Included(i) => Bound::Excluded(clamp(resolve(i).saturating_add(1))),
+        Bound::Excluded(i) => Bound::Excluded(clamp(resolve(i))),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+
+    (start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        broadcast_shapes, step_by, try_step_by, Ellipsis, IndexError, Layout, NewAxis, SliceElem,
+    };
+
+    #[test]
+    fn test_slice_ranges() {
+        let layout = Layout::contiguous(&[4, 5]);
+
+        let block = layout.slice((1..3, 2..)).unwrap();
+        assert_eq!(block.shape(), &[2, 3]);
+        assert_eq!(block.strides(), &[5, 1]);
+        assert_eq!(block.offsets(), vec![7, 8, 9, 12, 13, 14]);
+
+        let column = layout.slice((.., -1)).unwrap();
+        assert_eq!(column.shape(), &[4]);
+        assert_eq!(column.offsets(), vec![4, 9, 14, 19]);
+
+        let inclusive = layout.slice((0..=1, ..=0)).unwrap();
+        assert_eq!(inclusive.offsets(), vec![0, 5]);
+
+        // Inclusive ends before the first element select nothing, while
+        // starts before it are clamped to it.
+        assert_eq!(layout.slice((..=-10,)).unwrap().shape(), &[0, 5]);
+        assert_eq!(layout.slice((-10..=-10,)).unwrap().shape(), &[0, 5]);
+        assert_eq!(
+            layout.slice((..=-4,)).unwrap().offsets(),
+            (0..5).collect::<Vec<_>>()
+        );
+        assert_eq!(layout.slice((-10..=-4,)).unwrap().shape(), &[1, 5]);
+        assert_eq!(layout.slice((.., -3..=-1)).unwrap().shape(), &[4, 3]);
+    }
+
+    #[test]
+    fn test_slice_steps() {
+        let layout = Layout::contiguous(&[6]);
+
+        assert_eq!(
+            layout.slice((step_by(.., 2),)).unwrap().offsets(),
+            vec![0, 2, 4]
+        );
+        assert_eq!(
+            layout.slice((step_by(1..5, -1),)).unwrap().offsets(),
+            vec![4, 3, 2, 1]
+        );
+        assert_eq!(
+            layout.slice((step_by(.., -4),)).unwrap().offsets(),
+            vec![5, 1]
+        );
+        assert_eq!(layout.slice((-2..2,)).unwrap().shape(), &[0]);
+    }
+
+    #[test]
+    fn test_new_axis_and_ellipsis() {
+        let layout = Layout::contiguous(&[2, 3, 4]);
+
+        let sliced = layout.slice((Ellipsis, 1)).unwrap();
+        assert_eq!(sliced.shape(), &[2, 3]);
+        assert_eq!(sliced.strides(), &[12, 4]);
+        assert_eq!(sliced.offset(), 1);
+
+        let expanded = layout.slice((NewAxis, 0, Ellipsis, NewAxis)).unwrap();
+        assert_eq!(expanded.shape(), &[1, 3, 4, 1]);
+        assert_eq!(expanded.strides(), &[0, 4, 1, 0]);
+        assert!(expanded.is_contiguous());
+    }
+
+    #[test]
+    fn test_transpose() {
+        let layout = Layout::contiguous(&[2, 3]);
+
+        let transposed = layout.t();
+        assert_eq!(transposed.shape(), &[3, 2]);
+        assert!(!transposed.is_contiguous());
+        assert_eq!(transposed.offsets(), vec![0, 3, 1, 4, 2, 5]);
+
+        assert_eq!(layout.permute_axes(&[1, 0]).unwrap(), transposed);
+        assert!(layout.permute_axes(&[0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_slice_errors() {
+        let layout = Layout::contiguous(&[2, 3]);
+
+        assert_eq!(
+            layout.slice((0, 0, 0)),
+            Err(IndexError::TooManyIndices { ndim: 2, given: 3 })
+        );
+        assert_eq!(
+            layout.slice((2,)),
+            Err(IndexError::OutOfBounds {
+                index: 2,
+                axis: 0,
+                len: 2
+            })
+        );
+        assert_eq!(
+            layout.slice((.., step_by(.., 0))),
+            Err(IndexError::ZeroStep(1))
+        );
+        assert_eq!(
+            layout.slice((Ellipsis, Ellipsis)),
+            Err(IndexError::MultipleEllipses)
+        );
+
+        // Only ranges can be stepped, so a step is not silently dropped.
+        assert_eq!(try_step_by(1, 1), Ok(SliceElem::Index(1)));
+        assert_eq!(
+            try_step_by(1, 2),
+            Err(IndexError::StepWithoutRange {
+                elem: SliceElem::Index(1),
+                step: 2
+            })
+        );
+        assert!(try_step_by(NewAxis, -1).is_err());
+    }
+
+    #[test]
+    fn test_broadcast() {
+        assert_eq!(broadcast_shapes(&[2, 3], &[3]), Ok(vec![2, 3]));
+        assert_eq!(broadcast_shapes(&[4, 1, 3], &[2, 1]), Ok(vec![4, 2, 3]));
+        assert_eq!(broadcast_shapes(&[], &[2, 0]), Ok(vec![2, 0]));
+        assert_eq!(
+            broadcast_shapes(&[2, 3], &[2]),
+            Err(IndexError::BroadcastMismatch {
+                lhs: vec![2, 3],
+                rhs: vec![2]
+            })
+        );
+
+        let row = Layout::contiguous(&[1, 3])
+            .broadcast_to(&[2, 2, 3])
+            .unwrap();
+        assert_eq!(row.strides(), &[0, 0, 1]);
+        assert_eq!(row.offsets(), vec![0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2]);
+        assert!(Layout::contiguous(&[2]).broadcast_to(&[2, 3]).is_err());
+    }
+
+    #[test]
+    fn test_split_axes() {
+        let layout = Layout::contiguous(&[2, 3, 4]).slice((.., 1..)).unwrap();
+
+        let (outer, inner) = layout.split_axes(&[2, 0]).unwrap();
+        assert_eq!(
+            (outer.shape(), outer.strides(), outer.offset()),
+            (&[2][..], &[4][..], 4)
+        );
+        assert_eq!(
+            (inner.shape(), inner.strides()),
+            (&[2, 4][..], &[12, 1][..])
+        );
+
+        assert_eq!(
+            layout.split_axes(&[1, 1]),
+            Err(IndexError::InvalidAxes(vec![1, 1], 3))
+        );
+        assert!(layout.split_axes(&[3]).is_err());
+    }
+}
@@ -1,7 +1,102 @@
-use once_cell::sync::Lazy;
-use std::str::FromStr;
+use once_cell::sync::{Lazy, OnceCell};
+use std::{cell::RefCell, str::FromStr, sync::Arc};
 
-pub(crate) struct GlobalContext {}
+use crate::backend::{
+    adapter::AdapterSelector,
+    device::{Context, ContextError},
+};
+
+/// Comma-separated list of backends to request adapters from, e.g. `vulkan,gl`.
+pub const BACKEND_VAR: &str = "RAYNFOREST_BACKEND";
+/// Adapter to use, parsed as an [`AdapterSelector`].
+pub const ADAPTER_VAR: &str = "RAYNFOREST_ADAPTER";
+/// One of `low`, `high` or `none`.
+pub const POWER_PREFERENCE_VAR: &str = "RAYNFOREST_POWER_PREFERENCE";
+/// An `env_logger` filter, installed when the default context is created.
+pub const LOG_VAR: &str = "RAYNFOREST_LOG";
+
+pub(crate) struct GlobalContext {
+    backends: Backends,
+    adapter: Option<AdapterSelector>,
+    power_preference: PowerPreference,
+    log_filter: Option<String>,
+    context: OnceCell<Arc<Context>>,
+}
+
+impl GlobalContext {
+    fn from_env() -> Self {
+        GlobalContext {
+            backends: try_initialize_as_or_default(BACKEND_VAR, Backends(wgpu::Backends::all())),
+            adapter: initialize_as_or_default::<Option<String>>(ADAPTER_VAR, None)
+                .and_then(|s| s.parse().ok()),
+            power_preference: try_initialize_as_or_default(
+                POWER_PREFERENCE_VAR,
+                PowerPreference(wgpu::PowerPreference::default()),
+            ),
+            log_filter: initialize_as_or_default(LOG_VAR, None),
+            context: OnceCell::new(),
+        }
+    }
+
+    fn try_get(&self) -> Result<Arc<Context>, ContextError> {
+        self.context
+            .get_or_try_init(|| {
+                if let Some(filter) = &self.log_filter {
+                    // Another logger may already be installed, in which case we defer to it.
+                    let _ = env_logger::Builder::new().parse_filters(filter).try_init();
+                }
+
+                let mut builder = Context::builder().backends(self.backends.0).adapter(
+                    wgpu::RequestAdapterOptions {
+                        power_preference: self.power_preference.0,
+                        ..Default::default()
+                    },
+                );
+                if let Some(selector) = &self.adapter {
+                    builder = builder.select(selector.clone());
+                }
+
+                smol::block_on(builder.try_build())
+            })
+            .cloned()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Backends(wgpu::Backends);
+
+impl FromStr for Backends {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let backends = wgpu::util::parse_backends_from_comma_list(&s.to_lowercase());
+        if backends.is_empty() {
+            log::warn!("Ignoring {}={:?}: no known backends", BACKEND_VAR, s);
+            return Err(());
+        }
+
+        Ok(Backends(backends))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PowerPreference(wgpu::PowerPreference);
+
+impl FromStr for PowerPreference {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "low" => Ok(PowerPreference(wgpu::PowerPreference::LowPower)),
+            "high" => Ok(PowerPreference(wgpu::PowerPreference::HighPerformance)),
+            "none" => Ok(PowerPreference(wgpu::PowerPreference::None)),
+            _ => {
+                log::warn!("Ignoring {}={:?}", POWER_PREFERENCE_VAR, s);
+                Err(())
+            }
+        }
+    }
+}
 
 fn initialize_as_or_default<T>(env_var: &str, default: T) -> T
 where
@@ -21,4 +116,111 @@ where
         .unwrap_or(default)
 }
 
-pub(crate) static GLOBAL_CONTEXT: Lazy<GlobalContext> = Lazy::new(|| GlobalContext {});
+pub(crate) static GLOBAL_CONTEXT: Lazy<GlobalContext> = Lazy::new(GlobalContext::from_env);
+
+thread_local! {
+    static CONTEXT_OVERRIDES: RefCell<Vec<Arc<Context>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The process-wide context, created on first use from the `RAYNFOREST_*`
+/// environment variables.
+pub fn default_context() -> Arc<Context> {
+    let default_context_result = try_default_context();
+
+    if let Err(e) = &default_context_result {
+        log::error!("Could not construct the default context: {}", e);
+    }
+
+    default_context_result.unwrap()
+}
+
+pub fn try_default_context() -> Result<Arc<Context>, ContextError> {
+    GLOBAL_CONTEXT.try_get()
+}
+
+/// The innermost context set by [`with_context`] on this thread, or the
+/// [`default_context`] if there is none.
+pub fn current_context() -> Arc<Context> {
+    let current_context_result = try_current_context();
+
+    if let Err(e) = &current_context_result {
+        log::error!("Could not construct the current context: {}", e);
+    }
+
+    current_context_result.unwrap()
+}
+
+pub fn try_current_context() -> Result<Arc<Context>, ContextError> {
+    match CONTEXT_OVERRIDES.with_borrow(|overrides| overrides.last().cloned()) {
+        Some(context) => Ok(context),
+        None => try_default_context(),
+    }
+}
+
+/// Runs `f` with `context` as the [`current_context`] of this thread.
+pub fn with_context<R>(context: &Arc<Context>, f: impl FnOnce() -> R) -> R {
+    struct PopOnDrop;
+
+    impl Drop for PopOnDrop {
+        fn drop(&mut self) {
+            CONTEXT_OVERRIDES.with_borrow_mut(|overrides| overrides.pop());
+        }
+    }
+
+    CONTEXT_OVERRIDES.with_borrow_mut(|overrides| overrides.push(context.clone()));
+    let _guard = PopOnDrop;
+
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::backend::{adapter::AdapterSelector, device::test_context};
+
+    use super::{try_current_context, with_context, Backends, PowerPreference};
+
+    #[test]
+    fn test_parse_env_values() {
+        assert_eq!(
+            "Vulkan,GL".parse::<Backends>(),
+            Ok(Backends(wgpu::Backends::VULKAN | wgpu::Backends::GL))
+        );
+        assert!("nonsense".parse::<Backends>().is_err());
+
+        assert_eq!(
+            "high".parse::<PowerPreference>(),
+            Ok(PowerPreference(wgpu::PowerPreference::HighPerformance))
+        );
+        assert!("fast".parse::<PowerPreference>().is_err());
+
+        assert_eq!(
+            "llvmpipe".parse(),
+            Ok(AdapterSelector::Name("llvmpipe".into()))
+        );
+        assert_eq!("cpu".parse(), Ok(AdapterSelector::Software));
+        assert_eq!("1".parse(), Ok(AdapterSelector::Index(1)));
+        assert_eq!(
+            "vulkan".parse(),
+            Ok(AdapterSelector::Backend(wgpu::Backend::Vulkan))
+        );
+    }
+
+    #[test]
+    fn test_with_context() {
+        let (Some(outer), Some(inner)) = (test_context(), test_context()) else {
+            return;
+        };
+
+        with_context(&outer, || {
+            assert!(Arc::ptr_eq(&try_current_context().unwrap(), &outer));
+
+            with_context(&inner, || {
+                assert!(Arc::ptr_eq(&try_current_context().unwrap(), &inner));
+            });
+
+            assert!(Arc::ptr_eq(&try_current_context().unwrap(), &outer));
+        });
+    }
+}
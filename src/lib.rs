@@ -1,6 +1,14 @@
 #![allow(dead_code)] // TODO: Remove this when project is in a more stable state. It exists just to reduce visual noise.
 
 pub mod array;
+pub(crate) mod backend;
 pub mod indexing;
 pub mod initialization;
-pub(crate) mod backend;
+
+pub use array::{Array, ArrayView, AsView};
+pub use backend::{
+    Abs, AdapterDescription, AdapterSelector, ArgReduceOp, BinaryOp, BufferType, CommandBatch,
+    ComplexPart, ComputePipeline, ComputePipelineBuilder, Context, ContextBuilder, ContextError,
+    Gemm, Kernel, KernelArgument, KernelError, PipelineConfiguration, PipelineExecutionError,
+    PipelineLoadingError, PoolStats, ReduceOp, ScanOp, ShaderTemplate, SubmissionFence, UnaryOp,
+};
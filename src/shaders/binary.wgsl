@@ -0,0 +1,22 @@
+// Template for element-wise binary kernels. `{{T}}` is replaced by the element
+// type and `{{EXPR}}` by an expression of `a` and `b`. Elements are read and
+// written with accessors, as in `unary.wgsl`.
+
+@group(0) @binding(0) var<storage, read> lhs: array<{{LHS}}>;
+@group(0) @binding(1) var<storage, read> rhs: array<{{RHS}}>;
+@group(0) @binding(2) var<storage, read_write> result: array<{{RESULT}}>;
+
+@compute @workgroup_size({{WORKGROUP_SIZE}})
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_id.x + global_id.y * num_workgroups.x * {{WORKGROUP_SIZE_X}}u;
+    if index >= len_result() {
+        return;
+    }
+
+    let a = load_lhs(index);
+    let b = load_rhs(index);
+    store_result(index, {{EXPR}});
+}
@@ -0,0 +1,39 @@
+// Template for element-wise binary kernels on strided, broadcast operands.
+// `{{T}}` is replaced by the element type and `{{EXPR}}` by an expression of
+// `a` and `b`. `geometry` holds the rank, the offsets of both operands, the
+// shape of the result and the strides of both operands, in that order.
+// Broadcast axes have a stride of zero. Elements are read and written with
+// accessors, as in `unary.wgsl`.
+
+@group(0) @binding(0) var<storage, read> lhs: array<{{LHS}}>;
+@group(0) @binding(1) var<storage, read> rhs: array<{{RHS}}>;
+@group(0) @binding(2) var<storage, read_write> result: array<{{RESULT}}>;
+@group(0) @binding(3) var<storage, read> geometry: array<i32>;
+
+@compute @workgroup_size({{WORKGROUP_SIZE}})
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_id.x + global_id.y * num_workgroups.x * {{WORKGROUP_SIZE_X}}u;
+    if index >= len_result() {
+        return;
+    }
+
+    let rank = u32(geometry[0]);
+    var remainder = index;
+    var lhs_index = geometry[1];
+    var rhs_index = geometry[2];
+    for (var axis = rank; axis > 0u; axis--) {
+        let len = u32(geometry[2u + axis]);
+        let i = i32(remainder % len);
+        remainder /= len;
+
+        lhs_index += i * geometry[2u + rank + axis];
+        rhs_index += i * geometry[2u + 2u * rank + axis];
+    }
+
+    let a = load_lhs(u32(lhs_index));
+    let b = load_rhs(u32(rhs_index));
+    store_result(index, {{EXPR}});
+}
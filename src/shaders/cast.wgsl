@@ -0,0 +1,46 @@
+// Template converting packed floats of `{{SRC_BITS}}` bits to packed floats of
+// `{{DST_BITS}}` bits, through `f32`. `{{READ}}` widens the element at bit
+// `offset` of `word`, and `{{WRITE}}` narrows `value` to the lowest bits of a
+// `u32`. Each invocation writes one whole word of `dst`, so that no two
+// invocations write the same word.
+
+const LEN: u32 = 0u;
+
+const SRC_BITS: u32 = {{SRC_BITS}}u;
+const SRC_LANES: u32 = 32u / SRC_BITS;
+const DST_BITS: u32 = {{DST_BITS}}u;
+const DST_LANES: u32 = 32u / DST_BITS;
+
+@group(0) @binding(0) var<storage, read> src: array<u32>;
+@group(0) @binding(1) var<storage, read_write> dst: array<u32>;
+@group(0) @binding(2) var<storage, read> geometry: array<i32>;
+
+fn read(index: u32) -> f32 {
+    let word = src[index / SRC_LANES];
+    let offset = (index % SRC_LANES) * SRC_BITS;
+    return {{READ}};
+}
+
+fn write(value: f32) -> u32 {
+    return {{WRITE}};
+}
+
+@compute @workgroup_size({{WORKGROUP_SIZE}})
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_id.x + global_id.y * num_workgroups.x * {{WORKGROUP_SIZE_X}}u;
+    if index >= arrayLength(&dst) {
+        return;
+    }
+
+    var word = 0u;
+    for (var lane = 0u; lane < DST_LANES; lane++) {
+        let i = index * DST_LANES + lane;
+        if i < u32(geometry[LEN]) {
+            word = insertBits(word, write(read(i)), lane * DST_BITS, DST_BITS);
+        }
+    }
+    dst[index] = word;
+}
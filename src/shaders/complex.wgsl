@@ -0,0 +1,26 @@
+// Prelude of kernels on complex numbers, stored as `vec2<f32>` with the real
+// part first. Addition, subtraction and scaling by reals are those of vectors.
+
+fn cmul(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    return vec2(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x);
+}
+
+// NOTE: Divides by the largest part of `b` first, so that its squared norm
+// does not overflow.
+fn cdiv(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    let scale = max(abs(b.x), abs(b.y));
+    let d = b / scale;
+    return vec2(a.x * d.x + a.y * d.y, a.y * d.x - a.x * d.y) / (dot(d, d) * scale);
+}
+
+fn conj(a: vec2<f32>) -> vec2<f32> {
+    return vec2(a.x, -a.y);
+}
+
+fn cabs(a: vec2<f32>) -> f32 {
+    let scale = max(abs(a.x), abs(a.y));
+    if scale == 0.0 {
+        return 0.0;
+    }
+    return scale * length(a / scale);
+}
@@ -0,0 +1,19 @@
+// Template for element-wise kernels from complex numbers to reals. `{{EXPR}}`
+// is replaced by an `f32` expression of `a`.
+
+@group(0) @binding(0) var<storage, read> operand: array<vec2<f32>>;
+@group(0) @binding(1) var<storage, read_write> result: array<f32>;
+
+@compute @workgroup_size({{WORKGROUP_SIZE}})
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_id.x + global_id.y * num_workgroups.x * {{WORKGROUP_SIZE_X}}u;
+    if index >= arrayLength(&result) {
+        return;
+    }
+
+    let a = operand[index];
+    result[index] = {{EXPR}};
+}
@@ -0,0 +1,63 @@
+// Template copying integers of `{{BITS}}` bits, packed `32 / {{BITS}}` per
+// `u32` with the first in the lowest bits, to a range of another buffer that
+// needs not be aligned to whole words. Each invocation rewrites one word of
+// `dst`, keeping the integers outside the range.
+//
+// Elements wider than `{{BITS}}` bits are copied as `SCALE` integers each.
+// They are read in row-major order through the layout in `geometry`, whose
+// header is followed by its shape and strides, in elements.
+
+const SRC_OFFSET: u32 = 0u;
+const DST_START: u32 = 1u;
+const LEN: u32 = 2u;
+const FIRST_WORD: u32 = 3u;
+const SCALE: u32 = 4u;
+const RANK: u32 = 5u;
+const HEADER: u32 = 6u;
+
+const BITS: u32 = {{BITS}}u;
+const LANES: u32 = 32u / BITS;
+
+@group(0) @binding(0) var<storage, read> src: array<u32>;
+@group(0) @binding(1) var<storage, read_write> dst: array<u32>;
+@group(0) @binding(2) var<storage, read> geometry: array<i32>;
+
+// The position in `src` of integer `i` of the copied range.
+fn source(i: u32) -> u32 {
+    let scale = u32(geometry[SCALE]);
+    let rank = u32(geometry[RANK]);
+
+    var remainder = i / scale;
+    var offset = geometry[SRC_OFFSET];
+    for (var axis = rank; axis > 0u; axis--) {
+        let len = u32(geometry[HEADER + axis - 1u]);
+        offset += i32(remainder % len) * geometry[HEADER + rank + axis - 1u];
+        remainder /= len;
+    }
+    return u32(offset) * scale + i % scale;
+}
+
+@compute @workgroup_size({{WORKGROUP_SIZE}})
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_id.x + global_id.y * num_workgroups.x * {{WORKGROUP_SIZE_X}}u;
+    let word_index = u32(geometry[FIRST_WORD]) + index;
+    let start = u32(geometry[DST_START]);
+    let end = start + u32(geometry[LEN]);
+    if word_index * LANES >= end {
+        return;
+    }
+
+    var word = dst[word_index];
+    for (var lane = 0u; lane < LANES; lane++) {
+        let i = word_index * LANES + lane;
+        if i >= start && i < end {
+            let j = source(i - start);
+            let value = extractBits(src[j / LANES], (j % LANES) * BITS, BITS);
+            word = insertBits(word, value, lane * BITS, BITS);
+        }
+    }
+    dst[word_index] = word;
+}
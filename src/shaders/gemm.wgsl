@@ -0,0 +1,90 @@
+// Template for the tiled general matrix multiply `C = alpha * A * B + beta * C`.
+// `{{T}}` is replaced by the element type, `{{TILE}}` by the tile size,
+// `{{TILE_AREA}}` by its square and `{{MUL}}` by the product of `a` and `b`.
+// Each workgroup computes one `TILE x TILE` tile of C, staging the matching
+// tiles of A and B through workgroup memory. `workgroup_id.z` is the batch.
+
+struct Params {
+    m: u32,
+    n: u32,
+    k: u32,
+    transpose_a: u32,
+    transpose_b: u32,
+    // Elements between consecutive matrices of a batch, zero to broadcast.
+    stride_a: u32,
+    stride_b: u32,
+    stride_c: u32,
+    alpha: {{T}},
+    beta: {{T}},
+    _padding: vec2<u32>,
+}
+
+const TILE: u32 = {{TILE}}u;
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> a: array<{{T}}>;
+@group(0) @binding(2) var<storage, read> b: array<{{T}}>;
+@group(0) @binding(3) var<storage, read_write> c: array<{{T}}>;
+
+var<workgroup> tile_a: array<{{T}}, {{TILE_AREA}}>;
+var<workgroup> tile_b: array<{{T}}, {{TILE_AREA}}>;
+
+fn mul(a: {{T}}, b: {{T}}) -> {{T}} {
+    return {{MUL}};
+}
+
+// Element (row, col) of the m x k matrix op(A).
+fn load_a(batch: u32, row: u32, col: u32) -> {{T}} {
+    if row >= params.m || col >= params.k {
+        return {{T}}(0);
+    }
+
+    let index = select(row * params.k + col, col * params.m + row, params.transpose_a != 0u);
+    return a[batch * params.stride_a + index];
+}
+
+// Element (row, col) of the k x n matrix op(B).
+fn load_b(batch: u32, row: u32, col: u32) -> {{T}} {
+    if row >= params.k || col >= params.n {
+        return {{T}}(0);
+    }
+
+    let index = select(row * params.n + col, col * params.k + row, params.transpose_b != 0u);
+    return b[batch * params.stride_b + index];
+}
+
+@compute @workgroup_size({{TILE}}, {{TILE}})
+fn main(
+    @builtin(local_invocation_id) local_id: vec3<u32>,
+    @builtin(workgroup_id) group_id: vec3<u32>,
+) {
+    let row = group_id.y * TILE + local_id.y;
+    let col = group_id.x * TILE + local_id.x;
+    let batch = group_id.z;
+    let local = local_id.y * TILE + local_id.x;
+
+    var acc = {{T}}(0);
+    let tiles = (params.k + TILE - 1u) / TILE;
+    for (var t = 0u; t < tiles; t++) {
+        tile_a[local] = load_a(batch, row, t * TILE + local_id.x);
+        tile_b[local] = load_b(batch, t * TILE + local_id.y, col);
+        workgroupBarrier();
+
+        for (var p = 0u; p < TILE; p++) {
+            acc += mul(tile_a[local_id.y * TILE + p], tile_b[p * TILE + local_id.x]);
+        }
+        workgroupBarrier();
+    }
+
+    if row >= params.m || col >= params.n {
+        return;
+    }
+
+    // NOTE: Like BLAS, C is not read when beta is zero, so it may hold NaNs.
+    let index = batch * params.stride_c + row * params.n + col;
+    if all(params.beta == {{T}}(0)) {
+        c[index] = mul(params.alpha, acc);
+    } else {
+        c[index] = mul(params.alpha, acc) + mul(params.beta, c[index]);
+    }
+}
@@ -0,0 +1,29 @@
+// Prelude of kernels on packed halves, rounding `f32` values to the nearest
+// binary16 or bfloat16, ties to even, like the `half` crate. NaNs and
+// infinities are told apart by their bits, since WGSL implementations may
+// assume that floats are finite.
+
+// NOTE: `pack2x16float` is indeterminate outside the finite binary16 range,
+// so overflows and NaNs are built by hand. NaNs keep the sign and the high
+// bits of their payload, and become quiet.
+fn pack_f16(value: f32) -> u32 {
+    let bits = bitcast<u32>(value);
+    let sign = (bits >> 16u) & 0x8000u;
+    let magnitude = bits & 0x7fffffffu;
+    if magnitude > 0x7f800000u {
+        return sign | 0x7e00u | ((magnitude >> 13u) & 0x3ffu);
+    }
+    // Halfway between the largest finite half and 2^16 rounds up.
+    if magnitude >= 0x477ff000u {
+        return sign | 0x7c00u;
+    }
+    return pack2x16float(vec2(clamp(value, -65504.0, 65504.0), 0.0)) & 0xffffu;
+}
+
+fn pack_bf16(value: f32) -> u32 {
+    let bits = bitcast<u32>(value);
+    if (bits & 0x7fffffffu) > 0x7f800000u {
+        return (bits >> 16u) | 0x40u;
+    }
+    return (bits + 0x7fffu + ((bits >> 16u) & 1u)) >> 16u;
+}
@@ -0,0 +1,56 @@
+// Template for the counting pass of an LSD radix sort. `{{T}}` is replaced by
+// the key type and `{{KEY}}` by an expression of the key bits `b`, as a
+// `u32` ordered like the keys.
+//
+// Each workgroup counts the digits of one block of keys, one per invocation,
+// writing the count of digit `d` in block `g` to `counts[d * blocks + g]`, so
+// that an exclusive scan of `counts` yields where each block places each
+// digit.
+
+const LEN: u32 = 0u;
+const BLOCKS: u32 = 1u;
+const SHIFT: u32 = 2u;
+
+const RADIX: u32 = 16u;
+
+@group(0) @binding(0) var<storage, read> keys: array<{{T}}>;
+@group(0) @binding(1) var<storage, read_write> counts: array<u32>;
+@group(0) @binding(2) var<storage, read> geometry: array<i32>;
+
+var<workgroup> digits: array<u32, {{WORKGROUP_SIZE_X}}>;
+
+fn digit(key: {{T}}) -> u32 {
+    let b = bitcast<u32>(key);
+    return (({{KEY}}) >> u32(geometry[SHIFT])) & (RADIX - 1u);
+}
+
+@compute @workgroup_size({{WORKGROUP_SIZE}})
+fn main(
+    @builtin(local_invocation_index) local: u32,
+    @builtin(workgroup_id) group_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let group = group_id.x + group_id.y * num_workgroups.x;
+    let blocks = u32(geometry[BLOCKS]);
+    if group >= blocks {
+        return;
+    }
+
+    let index = group * {{WORKGROUP_SIZE_X}}u + local;
+    // Keys past the end are marked with a digit that is never counted.
+    digits[local] = RADIX;
+    if index < u32(geometry[LEN]) {
+        digits[local] = digit(keys[index]);
+    }
+    workgroupBarrier();
+
+    if local < RADIX {
+        var count = 0u;
+        for (var i = 0u; i < {{WORKGROUP_SIZE_X}}u; i++) {
+            if digits[i] == local {
+                count++;
+            }
+        }
+        counts[local * blocks + group] = count;
+    }
+}
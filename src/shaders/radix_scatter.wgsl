@@ -0,0 +1,72 @@
+// Template for the scatter pass of an LSD radix sort. `{{T}}` and `{{KEY}}`
+// are replaced as in `radix_count.wgsl`.
+//
+// Each key moves to the offset of its digit in its block, from the scanned
+// `offsets`, plus the number of keys with the same digit before it in the
+// block, which keeps the sort stable. Values move along with their keys, or
+// are initialized to the positions of the keys.
+
+const LEN: u32 = 0u;
+const BLOCKS: u32 = 1u;
+const SHIFT: u32 = 2u;
+const READ_VALUES: u32 = 3u;
+const WRITE_VALUES: u32 = 4u;
+
+const RADIX: u32 = 16u;
+
+@group(0) @binding(0) var<storage, read> keys: array<{{T}}>;
+@group(0) @binding(1) var<storage, read> values: array<u32>;
+@group(0) @binding(2) var<storage, read_write> sorted_keys: array<{{T}}>;
+@group(0) @binding(3) var<storage, read_write> sorted_values: array<u32>;
+@group(0) @binding(4) var<storage, read> offsets: array<u32>;
+@group(0) @binding(5) var<storage, read> geometry: array<i32>;
+
+var<workgroup> digits: array<u32, {{WORKGROUP_SIZE_X}}>;
+
+fn digit(key: {{T}}) -> u32 {
+    let b = bitcast<u32>(key);
+    return (({{KEY}}) >> u32(geometry[SHIFT])) & (RADIX - 1u);
+}
+
+@compute @workgroup_size({{WORKGROUP_SIZE}})
+fn main(
+    @builtin(local_invocation_index) local: u32,
+    @builtin(workgroup_id) group_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let group = group_id.x + group_id.y * num_workgroups.x;
+    let blocks = u32(geometry[BLOCKS]);
+    if group >= blocks {
+        return;
+    }
+
+    let index = group * {{WORKGROUP_SIZE_X}}u + local;
+    let in_bounds = index < u32(geometry[LEN]);
+    digits[local] = RADIX;
+    if in_bounds {
+        digits[local] = digit(keys[index]);
+    }
+    workgroupBarrier();
+
+    if !in_bounds {
+        return;
+    }
+
+    let d = digits[local];
+    var rank = 0u;
+    for (var i = 0u; i < local; i++) {
+        if digits[i] == d {
+            rank++;
+        }
+    }
+
+    let destination = offsets[d * blocks + group] + rank;
+    sorted_keys[destination] = keys[index];
+    if geometry[WRITE_VALUES] != 0 {
+        var value = index;
+        if geometry[READ_VALUES] != 0 {
+            value = values[index];
+        }
+        sorted_values[destination] = value;
+    }
+}
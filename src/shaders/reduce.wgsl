@@ -0,0 +1,108 @@
+// Template for reductions along axes. `{{T}}` is replaced by the element type,
+// `{{IDENTITY}}` by the identity of the reduction and `{{COMBINE}}` by the body
+// of `combine`, which merges two values and the positions they came from.
+// `{{MEAN}}` is replaced by the division of the sum `value` by the count of
+// reduced elements.
+//
+// `geometry` holds a header, then the shape and strides of the kept (outer)
+// axes, then those of the reduced (inner) axes. Each workgroup reduces one
+// part of the inner elements of one output with a tree in workgroup memory.
+// When a reduction is split into several parts, a second pass reduces the
+// partial results, reading their positions from `input_index`. Values are
+// read and written with accessors, as in `unary.wgsl`.
+
+struct Pair {
+    value: {{T}},
+    index: u32,
+}
+
+const OUTER_RANK: u32 = 0u;
+const INNER_RANK: u32 = 1u;
+const OFFSET: u32 = 2u;
+const OUTPUTS: u32 = 3u;
+const INNER_LEN: u32 = 4u;
+const PARTS: u32 = 5u;
+const PART_LEN: u32 = 6u;
+const READ_INDEX: u32 = 7u;
+const WRITE_INDEX: u32 = 8u;
+const MEAN_COUNT: u32 = 9u;
+const HEADER: u32 = 10u;
+
+@group(0) @binding(0) var<storage, read> input: array<{{INPUT}}>;
+@group(0) @binding(1) var<storage, read> input_index: array<u32>;
+@group(0) @binding(2) var<storage, read_write> output: array<{{OUTPUT}}>;
+@group(0) @binding(3) var<storage, read_write> output_index: array<u32>;
+@group(0) @binding(4) var<storage, read> geometry: array<i32>;
+
+var<workgroup> values: array<{{T}}, {{WORKGROUP_SIZE_X}}>;
+var<workgroup> indices: array<u32, {{WORKGROUP_SIZE_X}}>;
+
+fn combine(a: {{T}}, ia: u32, b: {{T}}, ib: u32) -> Pair {
+    {{COMBINE}}
+}
+
+// The offset of element `position` of the axes whose shape starts at `first`.
+fn unravel(position: u32, first: u32, rank: u32) -> i32 {
+    var remainder = position;
+    var offset = 0;
+    for (var axis = rank; axis > 0u; axis--) {
+        let len = u32(geometry[first + axis - 1u]);
+        offset += i32(remainder % len) * geometry[first + rank + axis - 1u];
+        remainder /= len;
+    }
+    return offset;
+}
+
+@compute @workgroup_size({{WORKGROUP_SIZE}})
+fn main(
+    @builtin(local_invocation_index) local: u32,
+    @builtin(workgroup_id) group_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let group = group_id.x + group_id.y * num_workgroups.x;
+    let parts = u32(geometry[PARTS]);
+    if group >= u32(geometry[OUTPUTS]) * parts {
+        return;
+    }
+
+    let outer_rank = u32(geometry[OUTER_RANK]);
+    let inner_rank = u32(geometry[INNER_RANK]);
+    let base = geometry[OFFSET] + unravel(group / parts, HEADER, outer_rank);
+
+    let start = (group % parts) * u32(geometry[PART_LEN]);
+    let end = min(start + u32(geometry[PART_LEN]), u32(geometry[INNER_LEN]));
+
+    var acc = Pair({{IDENTITY}}, 0xffffffffu);
+    for (var position = start + local; position < end; position += {{WORKGROUP_SIZE_X}}u) {
+        let at = base + unravel(position, HEADER + 2u * outer_rank, inner_rank);
+        var index = position;
+        if geometry[READ_INDEX] != 0 {
+            index = input_index[at];
+        }
+        acc = combine(acc.value, acc.index, load_input(u32(at)), index);
+    }
+
+    values[local] = acc.value;
+    indices[local] = acc.index;
+    workgroupBarrier();
+
+    for (var stride = {{WORKGROUP_SIZE_X}}u / 2u; stride > 0u; stride /= 2u) {
+        if local < stride {
+            let merged = combine(values[local], indices[local], values[local + stride], indices[local + stride]);
+            values[local] = merged.value;
+            indices[local] = merged.index;
+        }
+        workgroupBarrier();
+    }
+
+    if local == 0u {
+        var value = values[0];
+        if geometry[MEAN_COUNT] != 0 {
+            value = {{MEAN}};
+        }
+        store_output(group, value);
+        if geometry[WRITE_INDEX] != 0 {
+            output_index[group] = indices[0];
+        }
+    }
+}
@@ -0,0 +1,118 @@
+// Template for the block pass of a prefix scan along one axis. `{{T}}` is
+// replaced by the element type, `{{IDENTITY}}` by the identity of the scan
+// and `{{OP}}` by the associative expression of `a` and `b` it accumulates.
+//
+// Each workgroup scans one block of two elements per invocation of one row
+// with Blelloch's work-efficient scan in workgroup memory, and writes the
+// block total to `sums`. Scanning `sums` and adding it back to the blocks
+// completes scans of rows longer than one block.
+//
+// `geometry` holds a header, then the shape of the rows, then the strides of
+// the rows in the input and in the output.
+
+const OUTER_RANK: u32 = 0u;
+const INPUT_OFFSET: u32 = 1u;
+const LEN: u32 = 2u;
+const INPUT_STRIDE: u32 = 3u;
+const OUTPUT_STRIDE: u32 = 4u;
+const ROWS: u32 = 5u;
+const BLOCKS: u32 = 6u;
+const EXCLUSIVE: u32 = 7u;
+const HEADER: u32 = 8u;
+
+const BLOCK: u32 = {{BLOCK}}u;
+
+@group(0) @binding(0) var<storage, read> input: array<{{T}}>;
+@group(0) @binding(1) var<storage, read_write> output: array<{{T}}>;
+@group(0) @binding(2) var<storage, read_write> sums: array<{{T}}>;
+@group(0) @binding(3) var<storage, read> geometry: array<i32>;
+
+var<workgroup> temp: array<{{T}}, {{BLOCK}}>;
+
+fn op(a: {{T}}, b: {{T}}) -> {{T}} {
+    return {{OP}};
+}
+
+// The offset of row `row`, for the row strides starting at `strides`.
+fn unravel(row: u32, strides: u32) -> i32 {
+    let rank = u32(geometry[OUTER_RANK]);
+    var remainder = row;
+    var offset = 0;
+    for (var axis = rank; axis > 0u; axis--) {
+        let len = u32(geometry[HEADER + axis - 1u]);
+        offset += i32(remainder % len) * geometry[strides + axis - 1u];
+        remainder /= len;
+    }
+    return offset;
+}
+
+@compute @workgroup_size({{WORKGROUP_SIZE}})
+fn main(
+    @builtin(local_invocation_index) local: u32,
+    @builtin(workgroup_id) group_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let group = group_id.x + group_id.y * num_workgroups.x;
+    let blocks = u32(geometry[BLOCKS]);
+    if group >= u32(geometry[ROWS]) * blocks {
+        return;
+    }
+
+    let rank = u32(geometry[OUTER_RANK]);
+    let len = u32(geometry[LEN]);
+    let row = group / blocks;
+    let start = (group % blocks) * BLOCK;
+    let input_base = geometry[INPUT_OFFSET] + unravel(row, HEADER + rank);
+    let output_base = unravel(row, HEADER + 2u * rank);
+
+    var own: array<{{T}}, 2>;
+    for (var j = 0u; j < 2u; j++) {
+        let i = local + j * {{WORKGROUP_SIZE_X}}u;
+        own[j] = {{IDENTITY}};
+        if start + i < len {
+            own[j] = input[input_base + i32(start + i) * geometry[INPUT_STRIDE]];
+        }
+        temp[i] = own[j];
+    }
+
+    var offset = 1u;
+    for (var d = BLOCK / 2u; d > 0u; d /= 2u) {
+        workgroupBarrier();
+        if local < d {
+            let ai = offset * (2u * local + 1u) - 1u;
+            let bi = offset * (2u * local + 2u) - 1u;
+            temp[bi] = op(temp[ai], temp[bi]);
+        }
+        offset *= 2u;
+    }
+
+    workgroupBarrier();
+    if local == 0u {
+        sums[group] = temp[BLOCK - 1u];
+        temp[BLOCK - 1u] = {{IDENTITY}};
+    }
+
+    for (var d = 1u; d < BLOCK; d *= 2u) {
+        offset /= 2u;
+        workgroupBarrier();
+        if local < d {
+            let ai = offset * (2u * local + 1u) - 1u;
+            let bi = offset * (2u * local + 2u) - 1u;
+            let t = temp[ai];
+            temp[ai] = temp[bi];
+            temp[bi] = op(t, temp[bi]);
+        }
+    }
+    workgroupBarrier();
+
+    for (var j = 0u; j < 2u; j++) {
+        let i = local + j * {{WORKGROUP_SIZE_X}}u;
+        if start + i < len {
+            var value = temp[i];
+            if geometry[EXCLUSIVE] == 0 {
+                value = op(value, own[j]);
+            }
+            output[output_base + i32(start + i) * geometry[OUTPUT_STRIDE]] = value;
+        }
+    }
+}
@@ -0,0 +1,47 @@
+// Template for the pass of a prefix scan that combines the scanned block
+// totals in `offsets` into every element of their block. `{{T}}` and `{{OP}}`
+// are replaced as in `scan.wgsl`, which also describes `geometry`.
+
+const OUTER_RANK: u32 = 0u;
+const LEN: u32 = 2u;
+const OUTPUT_STRIDE: u32 = 4u;
+const ROWS: u32 = 5u;
+const BLOCKS: u32 = 6u;
+const HEADER: u32 = 8u;
+
+const BLOCK: u32 = {{BLOCK}}u;
+
+@group(0) @binding(0) var<storage, read> offsets: array<{{T}}>;
+@group(0) @binding(1) var<storage, read_write> output: array<{{T}}>;
+@group(0) @binding(2) var<storage, read> geometry: array<i32>;
+
+fn op(a: {{T}}, b: {{T}}) -> {{T}} {
+    return {{OP}};
+}
+
+@compute @workgroup_size({{WORKGROUP_SIZE}})
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_id.x + global_id.y * num_workgroups.x * {{WORKGROUP_SIZE_X}}u;
+    let len = u32(geometry[LEN]);
+    if index >= u32(geometry[ROWS]) * len {
+        return;
+    }
+
+    let rank = u32(geometry[OUTER_RANK]);
+    let row = index / len;
+    let position = index % len;
+
+    var remainder = row;
+    var at = i32(position) * geometry[OUTPUT_STRIDE];
+    for (var axis = rank; axis > 0u; axis--) {
+        let axis_len = u32(geometry[HEADER + axis - 1u]);
+        at += i32(remainder % axis_len) * geometry[HEADER + 2u * rank + axis - 1u];
+        remainder /= axis_len;
+    }
+
+    let offset = offsets[row * u32(geometry[BLOCKS]) + position / BLOCK];
+    output[at] = op(offset, output[at]);
+}
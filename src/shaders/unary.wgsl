@@ -0,0 +1,21 @@
+// Template for element-wise unary kernels. `{{T}}` is replaced by the element
+// type and `{{EXPR}}` by an expression of `a`. `{{OPERAND}}` and `{{RESULT}}`
+// are replaced by how the arrays store elements, which are read and written
+// with the accessors `with_element_arrays` appends.
+
+@group(0) @binding(0) var<storage, read> operand: array<{{OPERAND}}>;
+@group(0) @binding(1) var<storage, read_write> result: array<{{RESULT}}>;
+
+@compute @workgroup_size({{WORKGROUP_SIZE}})
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = global_id.x + global_id.y * num_workgroups.x * {{WORKGROUP_SIZE_X}}u;
+    if index >= len_result() {
+        return;
+    }
+
+    let a = load_operand(index);
+    store_result(index, {{EXPR}});
+}
@@ -0,0 +1,103 @@
+// Arithmetic on 64-bit integers emulated as `vec2<u32>`, low word first,
+// prepended to kernels on such elements. `{{SIGNED}}` is replaced by whether
+// the integers are signed, in two's complement. Like 32-bit integers, every
+// operation wraps around, and division by zero yields the dividend.
+
+const SIGNED: bool = {{SIGNED}};
+
+fn add64(a: vec2<u32>, b: vec2<u32>) -> vec2<u32> {
+    let low = a.x + b.x;
+    return vec2(low, a.y + b.y + select(0u, 1u, low < a.x));
+}
+
+fn sub64(a: vec2<u32>, b: vec2<u32>) -> vec2<u32> {
+    return vec2(a.x - b.x, a.y - b.y - select(0u, 1u, a.x < b.x));
+}
+
+fn neg64(a: vec2<u32>) -> vec2<u32> {
+    return sub64(vec2(0u), a);
+}
+
+// The full product of two `u32`, from products of their 16-bit halves.
+fn mul_wide(a: u32, b: u32) -> vec2<u32> {
+    let low = (a & 0xffffu) * (b & 0xffffu);
+    let cross_a = (a >> 16u) * (b & 0xffffu);
+    let cross_b = (a & 0xffffu) * (b >> 16u);
+    let high = (a >> 16u) * (b >> 16u);
+    let middle = (low >> 16u) + (cross_a & 0xffffu) + (cross_b & 0xffffu);
+    return vec2(
+        (low & 0xffffu) | (middle << 16u),
+        high + (cross_a >> 16u) + (cross_b >> 16u) + (middle >> 16u),
+    );
+}
+
+fn mul64(a: vec2<u32>, b: vec2<u32>) -> vec2<u32> {
+    let low = mul_wide(a.x, b.x);
+    return vec2(low.x, low.y + a.x * b.y + a.y * b.x);
+}
+
+fn is_negative(a: vec2<u32>) -> bool {
+    return SIGNED && (a.y >> 31u) == 1u;
+}
+
+fn lt64(a: vec2<u32>, b: vec2<u32>) -> bool {
+    // Flipping the sign bits orders signed integers like unsigned ones.
+    let flip = select(0u, 0x80000000u, SIGNED);
+    let a_high = a.y ^ flip;
+    let b_high = b.y ^ flip;
+    return a_high < b_high || (a_high == b_high && a.x < b.x);
+}
+
+fn gt64(a: vec2<u32>, b: vec2<u32>) -> bool {
+    return lt64(b, a);
+}
+
+fn eq64(a: vec2<u32>, b: vec2<u32>) -> bool {
+    return all(a == b);
+}
+
+fn min64(a: vec2<u32>, b: vec2<u32>) -> vec2<u32> {
+    return select(a, b, lt64(b, a));
+}
+
+fn max64(a: vec2<u32>, b: vec2<u32>) -> vec2<u32> {
+    return select(a, b, gt64(b, a));
+}
+
+fn abs64(a: vec2<u32>) -> vec2<u32> {
+    return select(a, neg64(a), is_negative(a));
+}
+
+// Unsigned long division, one bit of the quotient at a time.
+fn div_u64(a: vec2<u32>, b: vec2<u32>) -> vec2<u32> {
+    var quotient = vec2(0u);
+    var remainder = vec2(0u);
+    for (var i = 0u; i < 64u; i++) {
+        let bit = 63u - i;
+        let word = select(a.x, a.y, bit >= 32u);
+        // The bit shifted out only matters when the divisor is above 2^63.
+        let carry = (remainder.y >> 31u) == 1u;
+        remainder = vec2(
+            (remainder.x << 1u) | ((word >> (bit % 32u)) & 1u),
+            (remainder.y << 1u) | (remainder.x >> 31u),
+        );
+        if carry || !(remainder.y < b.y || (remainder.y == b.y && remainder.x < b.x)) {
+            remainder = sub64(remainder, b);
+            if bit >= 32u {
+                quotient.y |= 1u << (bit - 32u);
+            } else {
+                quotient.x |= 1u << bit;
+            }
+        }
+    }
+    return quotient;
+}
+
+// Signed division truncates towards zero.
+fn div64(a: vec2<u32>, b: vec2<u32>) -> vec2<u32> {
+    if eq64(b, vec2(0u)) {
+        return a;
+    }
+    let quotient = div_u64(abs64(a), abs64(b));
+    return select(quotient, neg64(quotient), is_negative(a) != is_negative(b));
+}